@@ -14,6 +14,7 @@ pub enum AnimationType {
     Emp(Color),
     NuclearExplosion,
     ShellExplosion,
+    BeamBurn,
 }
 
 impl Animation {
@@ -64,6 +65,7 @@ impl Animation {
                 draw(0.0, 0.33, 1.5, 0.6, white) | draw(0.0, 1.0, 1.0, 1.0, white)
             }
             AnimationType::ShellExplosion => draw(-0.25, 2.0, 0.3, 0.7, white),
+            AnimationType::BeamBurn => draw(0.0, 3.0, 0.4, 0.8, Vec3::new(1.0, 0.4, 0.2)),
         }
     }
 }
@@ -1,7 +1,7 @@
 // SPDX-FileCopyrightText: 2024 Softbear, Inc.
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
-use crate::game::KiometGame;
+use crate::game::{my_player_id, KiometGame};
 use kodiak_client::glam::Vec3;
 use kodiak_client::renderer::{rgb_hex, rgba_array_to_css};
 use kodiak_client::{ClientContext, PlayerId};
@@ -20,7 +20,7 @@ impl Color {
         let Some(player_id) = player_id else {
             return Self::Gray;
         };
-        let Some(me) = context.player_id().filter(|_| context.state.game.alive) else {
+        let Some(me) = my_player_id(context).filter(|_| context.state.game.alive) else {
             return Self::Red;
         };
 
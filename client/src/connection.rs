@@ -0,0 +1,386 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use common::protocol::{Command, Handshake, ProtocolMismatch, Update};
+use kodiak_client::{bitcode, js_hooks, PlayerId};
+use std::cell::RefCell;
+use std::num::NonZeroU32;
+use std::rc::Rc;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{BinaryType, MessageEvent, WebSocket};
+
+/// Connection to the custom server in
+/// [`TowerSettings::server_address`][`crate::settings::TowerSettings::server_address`].
+///
+/// After the [`Handshake`] (JSON text frames, the server's also containing the `player_id` it
+/// assigned, if any), the client sends bitcode-encoded [`Command`]s and the server sends
+/// bitcode-encoded [`Update`]s, as binary frames. While a server is set, it replaces the default
+/// transport, even if not connected.
+#[derive(Default)]
+pub struct ServerConnection {
+    url: Option<String>,
+    socket: Option<Socket>,
+    state: ConnectionState,
+    /// Assigned by the server in the current session's [`Handshake`].
+    player_id: Option<PlayerId>,
+    /// Consecutive failed attempts.
+    failures: u32,
+}
+
+struct Socket {
+    socket: WebSocket,
+    events: Rc<RefCell<Vec<SocketEvent>>>,
+    /// Must outlive the socket's callbacks.
+    _callbacks: [Closure<dyn FnMut(JsValue)>; 3],
+}
+
+enum SocketEvent {
+    Open,
+    Text(String),
+    Binary(Vec<u8>),
+    Close,
+}
+
+/// Returned by [`ServerConnection::update`].
+pub enum ServerEvent {
+    /// Started a new session (e.g. after reconnecting), with the assigned player id, if any.
+    Connected(Option<PlayerId>),
+    Update(Update),
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum ConnectionState {
+    /// No custom server.
+    #[default]
+    Idle,
+    Connecting,
+    /// Waiting for the server's [`Handshake`].
+    Handshaking,
+    Connected,
+    /// Will try again in `retry_in` seconds, after `attempt` consecutive failures.
+    Reconnecting {
+        attempt: u32,
+        retry_in: f32,
+    },
+    /// Won't reconnect until asked to.
+    Incompatible(ProtocolMismatch),
+    /// Couldn't even create the socket (e.g. blocked by the browser).
+    Failed,
+}
+
+impl ServerConnection {
+    /// Maximum delay between reconnect attempts, in seconds.
+    const MAX_BACKOFF: f32 = 30.0;
+
+    pub fn state(&self) -> &ConnectionState {
+        &self.state
+    }
+
+    pub fn url(&self) -> Option<&str> {
+        self.url.as_deref()
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.state == ConnectionState::Connected
+    }
+
+    pub fn player_id(&self) -> Option<PlayerId> {
+        self.player_id
+    }
+
+    /// Connects to `url` (or disconnects if [`None`]) if it isn't the current server.
+    pub fn set_url(&mut self, url: Option<&str>) {
+        if self.url.as_deref() != url {
+            self.url = url.map(str::to_owned);
+            self.reconnect();
+        }
+    }
+
+    /// Closes the current connection, if any, and immediately tries again.
+    pub fn reconnect(&mut self) {
+        self.close();
+        self.failures = 0;
+        if self.url.is_some() {
+            self.open();
+        } else {
+            self.state = ConnectionState::Idle;
+        }
+    }
+
+    /// Returns false if not connected, in which case `command` was dropped. It must not go to the
+    /// default transport instead, which is for a different server.
+    pub fn send(&self, command: &Command) -> bool {
+        let Some(socket) = self.socket.as_ref().filter(|_| self.is_connected()) else {
+            return false;
+        };
+        if let Err(e) = socket.socket.send_with_u8_array(&bitcode::encode(command)) {
+            js_hooks::console_log(&format!("发送命令失败: {e:?}"));
+        }
+        true
+    }
+
+    /// Handles socket events and reconnects. Returns events from the server, in order.
+    pub fn update(&mut self, elapsed_seconds: f32) -> Vec<ServerEvent> {
+        let mut updates = Vec::new();
+
+        let events = self
+            .socket
+            .as_ref()
+            .map(|s| std::mem::take(&mut *s.events.borrow_mut()))
+            .unwrap_or_default();
+        for event in events {
+            match event {
+                SocketEvent::Open => {
+                    self.state = ConnectionState::Handshaking;
+                    let handshake = serde_json::json!({
+                        "protocol_version": Handshake::new().protocol_version
+                    });
+                    if let Some(socket) = &self.socket {
+                        let _ = socket.socket.send_with_str(&handshake.to_string());
+                    }
+                }
+                SocketEvent::Text(text) if self.state == ConnectionState::Handshaking => {
+                    let handshake = serde_json::from_str::<serde_json::Value>(&text).ok();
+                    let field = |name: &str| {
+                        handshake
+                            .as_ref()
+                            .and_then(|v| v.get(name)?.as_u64())
+                            .and_then(|v| u32::try_from(v).ok())
+                    };
+                    let protocol_version = field("protocol_version").unwrap_or(0);
+                    match Handshake::new().check(Handshake { protocol_version }) {
+                        Ok(()) => {
+                            self.state = ConnectionState::Connected;
+                            self.failures = 0;
+                            self.player_id =
+                                field("player_id").and_then(NonZeroU32::new).map(PlayerId);
+                            updates.push(ServerEvent::Connected(self.player_id));
+                        }
+                        Err(mismatch) => {
+                            js_hooks::console_log(&format!("{mismatch}"));
+                            self.close();
+                            self.state = ConnectionState::Incompatible(mismatch);
+                            return updates;
+                        }
+                    }
+                }
+                SocketEvent::Binary(bytes) if self.is_connected() => {
+                    match bitcode::decode::<Update>(&bytes) {
+                        Ok(update) => updates.push(ServerEvent::Update(update)),
+                        Err(e) => js_hooks::console_log(&format!("无效的服务器更新: {e}")),
+                    }
+                }
+                SocketEvent::Text(_) | SocketEvent::Binary(_) => {}
+                SocketEvent::Close => {
+                    self.close();
+                    self.failures = self.failures.saturating_add(1);
+                    self.state = ConnectionState::Reconnecting {
+                        attempt: self.failures,
+                        retry_in: backoff(self.failures),
+                    };
+                    return updates;
+                }
+            }
+        }
+
+        if let ConnectionState::Reconnecting { retry_in, .. } = &mut self.state {
+            *retry_in -= elapsed_seconds;
+            if *retry_in <= 0.0 {
+                self.open();
+            }
+        }
+
+        updates
+    }
+
+    fn open(&mut self) {
+        let Some(url) = self.url.as_deref() else {
+            return;
+        };
+        let socket = match WebSocket::new(url) {
+            Ok(socket) => socket,
+            Err(e) => {
+                js_hooks::console_log(&format!("创建WebSocket连接失败: {e:?}"));
+                self.state = ConnectionState::Failed;
+                return;
+            }
+        };
+        socket.set_binary_type(BinaryType::Arraybuffer);
+
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let callback = |f: fn(JsValue) -> Option<SocketEvent>| {
+            let events = Rc::clone(&events);
+            Closure::<dyn FnMut(JsValue)>::new(move |e: JsValue| {
+                if let Some(event) = f(e) {
+                    events.borrow_mut().push(event);
+                }
+            })
+        };
+        let on_open = callback(|_| Some(SocketEvent::Open));
+        let on_message = callback(|e| {
+            let data = e.dyn_into::<MessageEvent>().ok()?.data();
+            if let Some(text) = data.as_string() {
+                Some(SocketEvent::Text(text))
+            } else {
+                let buffer = data.dyn_into::<js_sys::ArrayBuffer>().ok()?;
+                Some(SocketEvent::Binary(
+                    js_sys::Uint8Array::new(&buffer).to_vec(),
+                ))
+            }
+        });
+        // Errors are always followed by close.
+        let on_close = callback(|_| Some(SocketEvent::Close));
+        socket.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+        socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+
+        self.socket = Some(Socket {
+            socket,
+            events,
+            _callbacks: [on_open, on_message, on_close],
+        });
+        self.state = ConnectionState::Connecting;
+    }
+
+    fn close(&mut self) {
+        self.player_id = None;
+        if let Some(Socket { socket, .. }) = self.socket.take() {
+            socket.set_onopen(None);
+            socket.set_onmessage(None);
+            socket.set_onclose(None);
+            let _ = socket.close();
+        }
+    }
+}
+
+impl Drop for ServerConnection {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/// Delay before reconnect `attempt` (starting at 1), in seconds.
+pub fn backoff(attempt: u32) -> f32 {
+    (0.5 * 2f32.powi(attempt.saturating_sub(1).min(16) as i32)).min(ServerConnection::MAX_BACKOFF)
+}
+
+/// How long [`probe_latency`] waits for a server, in milliseconds.
+pub const PROBE_TIMEOUT_MS: i32 = 5000;
+
+/// Measures how long it takes to open a WebSocket to `url`, in milliseconds, and passes it to
+/// `on_result` ([`None`] if the server is unreachable or doesn't answer within
+/// [`PROBE_TIMEOUT_MS`]). The socket is closed before the handshake, so the server never sees a
+/// player.
+pub fn probe_latency(url: &str, on_result: impl FnOnce(Option<f64>) + 'static) {
+    let Ok(socket) = WebSocket::new(url) else {
+        on_result(None);
+        return;
+    };
+    let start = js_sys::Date::now();
+    let on_result = RefCell::new(Some(on_result));
+    let finish = Rc::new({
+        let socket = socket.clone();
+        move |latency: Option<f64>| {
+            if let Some(on_result) = on_result.borrow_mut().take() {
+                socket.set_onopen(None);
+                socket.set_onclose(None);
+                let _ = socket.close();
+                on_result(latency);
+            }
+        }
+    });
+
+    // Whichever of these isn't called is leaked, which is fine for an occasional probe.
+    let f = Rc::clone(&finish);
+    let on_open = Closure::once_into_js(move |_: JsValue| f(Some(js_sys::Date::now() - start)));
+    // Errors are always followed by close.
+    let f = Rc::clone(&finish);
+    let on_close = Closure::once_into_js(move |_: JsValue| f(None));
+    let on_timeout = Closure::once_into_js(move || finish(None));
+    socket.set_onopen(Some(on_open.unchecked_ref()));
+    socket.set_onclose(Some(on_close.unchecked_ref()));
+    if let Some(window) = web_sys::window() {
+        let _ = window.set_timeout_with_callback_and_timeout_and_arguments_0(
+            on_timeout.unchecked_ref(),
+            PROBE_TIMEOUT_MS,
+        );
+    }
+}
+
+/// Server linked to by the page's `?server=` query parameter, if valid, along with the optional
+/// `&name=` to save it as, e.g. `?server=wss://example.com/ws&name=Team`.
+pub fn deep_link_server() -> Option<(String, Option<String>)> {
+    let search = web_sys::window()?.location().search().ok()?;
+    let params = web_sys::UrlSearchParams::new_with_str(&search).ok()?;
+    let url = validate_server_url(&params.get("server")?).ok()?.to_owned();
+    Some((url, params.get("name")))
+}
+
+/// Returns the trimmed `url` if it is a plausible WebSocket URL.
+pub fn validate_server_url(url: &str) -> Result<&str, &'static str> {
+    let url = url.trim();
+    let rest = url
+        .strip_prefix("wss://")
+        .or_else(|| url.strip_prefix("ws://"))
+        .ok_or("must start with ws:// or wss://")?;
+    if url.len() > 2048 {
+        return Err("too long");
+    }
+    if url.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return Err("contains whitespace");
+    }
+    let host = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = host.rsplit_once('@').map_or(host, |(_, host)| host);
+    let (hostname, port) = if let Some(ipv6) = host.strip_prefix('[') {
+        let (ipv6, rest) = ipv6.split_once(']').ok_or("invalid host")?;
+        (ipv6, rest.strip_prefix(':'))
+    } else {
+        match host.rsplit_once(':') {
+            Some((hostname, port)) => (hostname, Some(port)),
+            None => (host, None),
+        }
+    };
+    if hostname.is_empty() {
+        return Err("missing host");
+    }
+    let ipv6 = host.starts_with('[');
+    if !hostname
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.' || (ipv6 && c == ':'))
+    {
+        return Err("invalid host");
+    }
+    if let Some(port) = port {
+        port.parse::<u16>().map_err(|_| "invalid port")?;
+    }
+    Ok(url)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::connection::{backoff, validate_server_url};
+
+    #[test]
+    fn validate_server_url_() {
+        assert_eq!(
+            validate_server_url(" wss://example.com/ws "),
+            Ok("wss://example.com/ws")
+        );
+        assert!(validate_server_url("ws://localhost:8443").is_ok());
+        assert!(validate_server_url("ws://[::1]:8443/ws").is_ok());
+        assert!(validate_server_url("https://example.com").is_err());
+        assert!(validate_server_url("wss://").is_err());
+        assert!(validate_server_url("wss://example.com:99999").is_err());
+        assert!(validate_server_url("wss://exa mple.com").is_err());
+        assert!(validate_server_url("wss://x');alert(1);('").is_err());
+    }
+
+    #[test]
+    fn backoff_() {
+        assert_eq!(backoff(1), 0.5);
+        assert_eq!(backoff(2), 1.0);
+        assert!((1..100).all(|a| backoff(a) <= backoff(a + 1)));
+        assert_eq!(backoff(u32::MAX), 30.0);
+    }
+}
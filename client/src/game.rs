@@ -4,16 +4,23 @@
 use crate::animation::{Animation, AnimationType};
 use crate::background::TowerBackgroundLayer;
 use crate::color::Color;
+use crate::connection::{deep_link_server, validate_server_url, ServerConnection, ServerEvent};
+use crate::key_bindings::KeyAction;
 use crate::key_dispenser::KeyDispenser;
 use crate::layout::{force_layout, tower_layout};
+use crate::minimap::{Minimap, MinimapLayer};
 use crate::path::*;
 use crate::road::RoadLayer;
+use crate::script::ScriptRunner;
 use crate::settings::TowerSettings;
 use crate::state::TowerState;
 use crate::territory::Territories;
 use crate::tutorial::Tutorial;
+use crate::ui::upgrade_all_dialog::UpgradeCandidates;
 use crate::ui::{KiometRoute, KiometUi, KiometUiEvent, KiometUiProps, SelectedTower};
+use common::alerts::Alerts;
 use common::chunk::ChunkRectangle;
+use common::death_reason::DeathReason;
 use common::force::{Force, Path};
 use common::info::{GainedTowerReason, Info, InfoEvent};
 use common::protocol::{Command, Update};
@@ -27,9 +34,10 @@ use kodiak_client::renderer::{DefaultRender, Layer, RenderChain, TextStyle};
 use kodiak_client::renderer2d::{Camera2d, TextLayer};
 use kodiak_client::{
     include_audio, js_hooks, translate, ClientContext, FatalError, GameClient, GameConstants, Key,
-    MouseButton, MouseEvent, PanZoom, RankNumber, RateLimiter, Translator,
+    MouseButton, MouseEvent, PanZoom, PlayerId, RankNumber, RateLimiter, Translator,
 };
-use serde::{Serialize, Deserialize};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
 use std::f32::consts::PI;
 
 use wasm_bindgen::prelude::*;
@@ -38,17 +46,37 @@ use wasm_bindgen::JsValue;
 include_audio!("/data/audio.mp3" "./audio.json");
 
 pub struct KiometGame {
+    /// Index of the next alert to pan to with [`KeyAction::CycleAlerts`].
+    alert_cursor: usize,
     animations: Vec<Animation>,
+    /// Start of a shift-drag box selection, in world space.
+    box_select: Option<Vec2>,
     camera: Camera2d,
+    connection: ServerConnection,
+    cycle_alerts_held: bool,
     drag: Option<Drag>,
+    /// Last [`Alerts`] sent to [`kiomet_on_event`] listeners.
+    emitted_alerts: Alerts,
+    key_bindings_dialog: bool,
     key_dispenser: KeyDispenser,
     lock_dialog: Option<TowerType>,
+    minimap: Minimap,
+    /// Left mouse button went down over the [`Minimap`] and is still down.
+    minimap_drag: bool,
     pan_zoom: PanZoom,
     panning: bool,
     render_chain: RenderChain<TowerLayer>,
+    script: ScriptRunner,
+    script_dialog: bool,
     selected_tower_id: Option<TowerId>,
+    /// Own towers selected by box selection or ctrl-click, which are ordered together. At most
+    /// [`Command::MAX_BATCH_LEN`], so orders fit in one batch.
+    selected_tower_ids: BTreeSet<TowerId>,
     territories: Territories,
     tutorial: Tutorial,
+    upgrade_all_dialog: Option<TowerType>,
+    /// Updated every tick by [`upgrade_candidates`] while [`Self::upgrade_all_dialog`] is open.
+    upgrade_candidates: UpgradeCandidates,
     was_alive: bool,
     set_viewport_rate_limit: RateLimiter,
 }
@@ -89,6 +117,8 @@ pub struct TowerLayer {
     roads: RoadLayer,
     paths: PathLayer,
     text: TextLayer,
+    /// Last, so it is drawn over the world.
+    minimap: MinimapLayer,
 }
 
 impl KiometGame {
@@ -121,29 +151,56 @@ impl GameClient for KiometGame {
                 roads: RoadLayer::new(&*renderer),
                 paths: PathLayer::new(&*renderer),
                 text: TextLayer::new(&*renderer),
+                minimap: MinimapLayer::new(&*renderer),
             }
         })?;
 
-        let mut game = Self {
+        // 链接中的服务器（?server=）优先于保存的地址。
+        if let Some((url, name)) = deep_link_server() {
+            if context.settings.servers.get(&url).is_none() {
+                if let Some(servers) = context
+                    .settings
+                    .servers
+                    .insert(name.as_deref().unwrap_or_default(), &url)
+                {
+                    context
+                        .settings
+                        .set_servers(servers, &mut context.browser_storages);
+                }
+            }
+            context
+                .settings
+                .set_server_address(Some(url), &mut context.browser_storages);
+        }
+
+        Ok(Self {
+            alert_cursor: 0,
             animations: Default::default(),
+            box_select: None,
             camera: Camera2d::default(),
+            connection: Default::default(),
+            cycle_alerts_held: false,
             drag: Default::default(),
+            emitted_alerts: Default::default(),
+            key_bindings_dialog: false,
             key_dispenser: Default::default(),
             lock_dialog: None,
+            minimap: Default::default(),
+            minimap_drag: false,
             pan_zoom: Default::default(),
             panning: Default::default(),
             render_chain,
+            script: Default::default(),
+            script_dialog: false,
             selected_tower_id: Default::default(),
+            selected_tower_ids: Default::default(),
             territories: Default::default(),
             tutorial: Default::default(),
+            upgrade_all_dialog: None,
+            upgrade_candidates: UpgradeCandidates::default(),
             was_alive: Default::default(),
             set_viewport_rate_limit: RateLimiter::new(0.15),
-        };
-        
-        // 注册全局指针
-        game.register_global_ptr();
-        
-        Ok(game)
+        })
     }
 
     fn translate_rank_number(t: &Translator, n: RankNumber) -> String {
@@ -173,7 +230,11 @@ impl GameClient for KiometGame {
 
         match *event {
             MouseEvent::MoveViewSpace(view_space) => {
-                if self.panning {
+                if self.minimap_drag {
+                    if let Some(world_space) = self.minimap.to_world(view_space) {
+                        self.pan_zoom.pan_to(world_space);
+                    }
+                } else if self.panning {
                     if let Some(old_view_space) = context.mouse.view_position {
                         let world_space = self.camera.to_world_position(view_space);
                         let old_world_space = self.camera.to_world_position(old_view_space);
@@ -194,25 +255,78 @@ impl GameClient for KiometGame {
                     }
                 }
                 MouseButton::Left => {
-                    if down {
+                    // 点击小地图只移动相机。
+                    let minimap_position = context
+                        .mouse
+                        .view_position
+                        .and_then(|v| self.minimap.to_world(v));
+                    if down && self.drag.is_none() && self.box_select.is_none() {
+                        if let Some(world_space) = minimap_position {
+                            self.close_tower_menu();
+                            self.pan_zoom.pan_to(world_space);
+                            self.minimap_drag = true;
+                            return;
+                        }
+                    } else if !down && std::mem::take(&mut self.minimap_drag) {
+                        return;
+                    }
+
+                    if context.state.game.spectating {
+                        // Spectators can inspect towers but not give orders.
+                        let closest = context
+                            .mouse
+                            .view_position
+                            .and_then(|v| get_closest(self.camera.to_world_position(v), context));
+                        self.selected_tower_id =
+                            spectator_click(self.selected_tower_id, closest, down);
+                    } else if down {
+                        let world_position = context
+                            .mouse
+                            .view_position
+                            .map(|v| self.camera.to_world_position(v));
+                        let closest = world_position.and_then(|p| get_closest(p, context));
                         if self.drag.is_none() && !self.panning {
-                            if let Some(drag_start) = context.mouse.view_position.and_then(|v| {
-                                get_closest(self.camera.to_world_position(v), context)
-                            }) {
+                            if context.keyboard.is_down(Key::Shift) {
+                                self.box_select = world_position;
+                                self.selected_tower_id = None;
+                            } else if context.keyboard.is_down(Key::Ctrl) {
+                                if let Some(tower_id) = closest.filter(|&id| is_mine(context, id)) {
+                                    if !self.selected_tower_ids.remove(&tower_id)
+                                        && self.selected_tower_ids.len() < Command::MAX_BATCH_LEN
+                                    {
+                                        self.selected_tower_ids.insert(tower_id);
+                                    }
+                                }
+                            } else if let Some(drag_start) = closest {
                                 self.drag = Some(Drag {
                                     start: drag_start,
                                     current: Some((drag_start, context.client.time_seconds)),
                                 });
-                                if self.selected_tower_id != Some(drag_start) {
-                                    // If they were equal, wait for mouse up before clearing selection.
+                                if !self.is_supply_line_source(drag_start) {
+                                    // If it is, wait for mouse up before clearing selection.
                                     self.selected_tower_id = None;
                                 }
+                                if !self.selected_tower_ids.contains(&drag_start) {
+                                    self.selected_tower_ids.clear();
+                                }
                             } else {
                                 self.selected_tower_id = None;
+                                self.selected_tower_ids.clear();
                             }
                         }
                     } else {
-                        if let Some((start, current, current_start_time)) = Drag::zip(self.drag) {
+                        if let Some(box_start) = self.box_select.take() {
+                            if let Some(box_end) = context
+                                .mouse
+                                .view_position
+                                .map(|v| self.camera.to_world_position(v))
+                            {
+                                self.selected_tower_ids =
+                                    towers_in_box(box_start, box_end, context);
+                            }
+                        } else if let Some((start, current, current_start_time)) =
+                            Drag::zip(self.drag)
+                        {
                             if start == current {
                                 if self.selected_tower_id == Some(start) {
                                     // Double click to deselect.
@@ -221,63 +335,41 @@ impl GameClient for KiometGame {
                                 } else {
                                     self.selected_tower_id = Some(start);
                                 }
-                            } else if let Some((source_tower, _destination_tower)) = context
-                                .state
-                                .game
-                                .world
-                                .chunk
-                                .get(start)
-                                .zip(context.state.game.world.chunk.get(current))
-                            {
-                                if self.selected_tower_id != Some(start) {
+                            } else if exists(context, start) && exists(context, current) {
+                                if !self.is_supply_line_source(start) {
                                     self.selected_tower_id = None;
                                 }
-
-                                let strength = source_tower.force_units();
-                                let tower_edge_distance = source_tower.tower_type.ranged_distance();
-                                let strength_edge_distance =
-                                    (!strength.is_empty()).then(|| strength.max_edge_distance());
-                                let max_edge_distance = strength_edge_distance
-                                    .map_or(tower_edge_distance, |e| e.min(tower_edge_distance));
-                                let shorter_max_edge_distance =
-                                    max_edge_distance != tower_edge_distance;
-                                let supply_tower_id = self.selected_tower_id.filter(|_| {
-                                    source_tower.generates_mobile_units()
-                                        && !shorter_max_edge_distance
-                                });
-
-                                let path = context.state.game.world.find_best_path(
-                                    start,
-                                    current,
-                                    max_edge_distance,
-                                    context.player_id().unwrap(),
-                                    |tower_id| is_visible(context, tower_id),
-                                );
-
-                                if let Some(path) = path {
-                                    let perilous =
-                                        path.iter().any(|&tower_id| is_perilous(context, tower_id));
-
-                                    if !perilous
-                                        || !strength.contains(Unit::Ruler)
-                                        || context.client.time_seconds
-                                            >= current_start_time + Self::RULER_DRAG_DELAY
-                                    {
-                                        context.send_to_game(
-                                            if let Some(tower_id) = supply_tower_id {
-                                                let path = Path::new(path);
-                                                Command::SetSupplyLine {
-                                                    tower_id,
-                                                    // TODO accept any invalid path.
-                                                    path: (source_tower.supply_line.as_ref()
-                                                        != Some(&path))
-                                                    .then_some(path),
-                                                }
-                                            } else {
-                                                Command::deploy_force_from_path(path)
-                                            },
-                                        );
-                                    }
+                                let supply_line = self.selected_tower_id.is_some();
+
+                                let command = if self.selected_tower_ids.contains(&start) {
+                                    // Order every selected tower at once.
+                                    let commands = self
+                                        .selected_tower_ids
+                                        .iter()
+                                        .filter(|&&source| source != current)
+                                        .filter_map(|&source| {
+                                            self.path_command(
+                                                source,
+                                                current,
+                                                supply_line,
+                                                current_start_time,
+                                                context,
+                                            )
+                                        })
+                                        .take(Command::MAX_BATCH_LEN)
+                                        .collect();
+                                    Command::batch(commands)
+                                } else {
+                                    self.path_command(
+                                        start,
+                                        current,
+                                        supply_line,
+                                        current_start_time,
+                                        context,
+                                    )
+                                };
+                                if let Some(command) = command {
+                                    self.send_to_game(command, context);
                                 }
                             } else {
                                 self.selected_tower_id = None;
@@ -327,17 +419,41 @@ impl GameClient for KiometGame {
             context.client.time_seconds,
         );
 
+        if let Some((start, end)) = self.box_select.zip(
+            context
+                .mouse
+                .view_position
+                .map(|v| self.camera.to_world_position(v)),
+        ) {
+            let corners = [
+                start,
+                Vec2::new(end.x, start.y),
+                end,
+                Vec2::new(start.x, end.y),
+            ];
+            let color = Vec3::splat(1.0).extend(0.5);
+            for (i, &corner) in corners.iter().enumerate() {
+                layer.roads.draw_road(
+                    corner,
+                    corners[(i + 1) % corners.len()],
+                    zoom_per_pixel * 2.0,
+                    color,
+                    color.w,
+                );
+            }
+        }
+
         let hovered_tower_id = context
             .mouse
             .view_position
             .and_then(|v| TowerId::closest(self.camera.to_world_position(v)));
         let show_similar_towers = self
             .selected_tower_id
-            .filter(|_| context.keyboard.is_down(Key::T))
+            .filter(|_| self.action_down(KeyAction::ShowSimilarTowers, context))
             .and_then(|id| context.state.game.world.chunk.get(id))
             .map(|t| t.tower_type);
         let get_visibility = |id| is_visible(context, id).then_some(1.0).unwrap_or_default();
-        let me = context.player_id();
+        let me = my_player_id(context);
 
         for (tower_id, tower) in context
             .state
@@ -352,7 +468,8 @@ impl GameClient for KiometGame {
 
             let tower_position = tower_id.as_vec2();
             let hovered = hovered_tower_id == Some(tower_id);
-            let selected = self.selected_tower_id == Some(tower_id);
+            let selected = self.selected_tower_id == Some(tower_id)
+                || self.selected_tower_ids.contains(&tower_id);
             let tower_scale = tower.tower_type.scale() as f32;
 
             if zoom_per_pixel < 0.3 {
@@ -376,7 +493,7 @@ impl GameClient for KiometGame {
                 }
             }
 
-            let show_supply_lines = context.keyboard.is_down(Key::R);
+            let show_supply_lines = self.action_down(KeyAction::ShowSupplyLines, context);
             if show_supply_lines
                 || Some(tower_id) == self.selected_tower_id
                 || Some(tower_id) == hovered_tower_id
@@ -449,7 +566,7 @@ impl GameClient for KiometGame {
 
             let mut nuke = None;
             for force in &tower.inbound_forces {
-                if force.units.contains(Unit::Nuke)
+                if (force.units.contains(Unit::Nuke) || force.units.contains(Unit::Missile))
                     && (force.units.len() == 1
                         || (!tower.units.is_empty() && tower.player_id != force.player_id))
                 {
@@ -689,6 +806,18 @@ impl GameClient for KiometGame {
             layer,
         );
 
+        self.minimap.draw(
+            &mut layer.minimap,
+            canvas_size,
+            (
+                self.camera.to_world_position(Vec2::NEG_ONE),
+                self.camera.to_world_position(Vec2::ONE),
+            ),
+            context.state.game.alerts.ruler_position,
+            context.client.time_seconds,
+            context,
+        );
+
         frame.end(&self.camera);
     }
 
@@ -698,10 +827,13 @@ impl GameClient for KiometGame {
                 with,
                 break_alliance,
             } => {
-                context.send_to_game(Command::Alliance {
-                    with,
-                    break_alliance,
-                });
+                self.send_to_game(
+                    Command::Alliance {
+                        with,
+                        break_alliance,
+                    },
+                    context,
+                );
                 self.close_tower_menu();
             }
             KiometUiEvent::DismissCaptureTutorial => {
@@ -711,11 +843,32 @@ impl GameClient for KiometGame {
                 self.tutorial.dismiss_upgrade();
             }
             KiometUiEvent::Spawn(alias) => {
-                context.send_to_game(Command::Spawn(alias));
+                self.send_to_game(Command::Spawn(alias), context);
+            }
+            KiometUiEvent::Spectate(spectate) => {
+                self.send_to_game(Command::Spectate(spectate), context);
+                self.close_tower_menu();
+            }
+            KiometUiEvent::Surrender => {
+                self.send_to_game(Command::Surrender, context);
+                self.close_tower_menu();
             }
             KiometUiEvent::PanTo(tower_id) => {
                 self.pan_zoom.pan_to(tower_id.as_vec2());
             }
+            KiometUiEvent::QueueUpgrade {
+                tower_id,
+                tower_type,
+            } => {
+                self.send_to_game(
+                    Command::QueueUpgrade {
+                        tower_id,
+                        tower_type,
+                    },
+                    context,
+                );
+                self.close_tower_menu();
+            }
             KiometUiEvent::Upgrade {
                 tower_id,
                 tower_type,
@@ -725,10 +878,13 @@ impl GameClient for KiometGame {
                         .settings
                         .set_unlocks(unlocks, &mut context.browser_storages);
                 }
-                context.send_to_game(Command::Upgrade {
-                    tower_id,
-                    tower_type,
-                });
+                self.send_to_game(
+                    Command::Upgrade {
+                        tower_id,
+                        tower_type,
+                    },
+                    context,
+                );
                 self.close_tower_menu();
             }
             KiometUiEvent::Unlock(tower_type) => {
@@ -739,14 +895,102 @@ impl GameClient for KiometGame {
                 }
                 self.lock_dialog = None;
             }
+            KiometUiEvent::UpgradeAll {
+                tower_type,
+                in_viewport,
+            } => {
+                let commands = upgrade_all_targets(tower_type, in_viewport, context)
+                    .into_iter()
+                    .take(Command::MAX_BATCH_LEN)
+                    .map(|tower_id| Command::Upgrade {
+                        tower_id,
+                        tower_type,
+                    })
+                    .collect();
+                if let Some(command) = Command::batch(commands) {
+                    self.send_to_game(command, context);
+                }
+                self.upgrade_all_dialog = None;
+            }
+            KiometUiEvent::UpgradeAllDialog(show) => {
+                self.upgrade_all_dialog = show;
+                self.upgrade_candidates = upgrade_candidates(show, context);
+            }
             KiometUiEvent::LockDialog(show) => {
                 self.lock_dialog = show;
             }
+            KiometUiEvent::SetServerAddress(url) => {
+                self.set_server_address(url, context);
+            }
+            KiometUiEvent::Reconnect => {
+                self.connection.reconnect();
+            }
+            KiometUiEvent::SaveServer { name, url } => {
+                if let Some(servers) = validate_server_url(&url)
+                    .ok()
+                    .and_then(|url| context.settings.servers.insert(&name, url))
+                {
+                    context
+                        .settings
+                        .set_servers(servers, &mut context.browser_storages);
+                }
+            }
+            KiometUiEvent::RemoveServer(url) => {
+                let servers = context.settings.servers.remove(&url);
+                context
+                    .settings
+                    .set_servers(servers, &mut context.browser_storages);
+            }
+            KiometUiEvent::KeyBindingsDialog(show) => {
+                self.key_bindings_dialog = show;
+            }
+            KiometUiEvent::SetKeyBindings(key_bindings) => {
+                context
+                    .settings
+                    .set_key_bindings(key_bindings, &mut context.browser_storages);
+                self.key_bindings_dialog = false;
+            }
+            KiometUiEvent::ScriptDialog(show) => {
+                self.script_dialog = show;
+            }
+            KiometUiEvent::SetScript { source, enabled } => {
+                context.settings.set_script(
+                    (!source.trim().is_empty()).then_some(source),
+                    &mut context.browser_storages,
+                );
+                context
+                    .settings
+                    .set_script_enabled(enabled, &mut context.browser_storages);
+            }
         }
     }
 
     fn update(&mut self, elapsed_seconds: f32, context: &mut ClientContext<Self>) {
-        let me = context.player_id();
+        // 自定义服务器（如果有）代替默认的连接，即使尚未连接。
+        self.connection
+            .set_url(context.settings.server_address.as_deref());
+        context
+            .state
+            .game
+            .set_custom_server(self.connection.url().is_some());
+        for event in self.connection.update(elapsed_seconds) {
+            match event {
+                ServerEvent::Connected(player_id) => {
+                    context.state.game.connected_to_custom_server(player_id)
+                }
+                ServerEvent::Update(update) => context.state.game.apply_custom(update),
+            }
+        }
+        if self.connection.is_connected()
+            && context.settings.last_connected_server.as_deref() != self.connection.url()
+        {
+            context.settings.set_last_connected_server(
+                self.connection.url().map(str::to_owned),
+                &mut context.browser_storages,
+            );
+        }
+
+        let me = my_player_id(context);
 
         // Has it's own method of determining ticked (because it's used in peek_mouse).
         update_visible(context);
@@ -760,8 +1004,29 @@ impl GameClient for KiometGame {
             self.move_world_space(world_space, context);
         }
 
+        self.drain_actions(context);
+
         let ticked = std::mem::take(&mut context.state.game.ticked);
         if ticked {
+            self.publish_snapshot(context);
+            self.upgrade_candidates = upgrade_candidates(self.upgrade_all_dialog, context);
+            self.minimap.update(context);
+            emit_event(&KiometEvent::Tick);
+            // 失去的塔不再被选中。
+            self.selected_tower_ids
+                .retain(|&tower_id| is_mine(context, tower_id));
+            if context.state.game.alive && context.settings.script_enabled {
+                if let Some(source) = context.settings.script.as_deref() {
+                    let towers = context
+                        .state
+                        .game
+                        .visible
+                        .iter(&context.state.game.world.chunk);
+                    if let Some(command) = self.script.run(source, towers, me) {
+                        self.send_to_game(command, context);
+                    }
+                }
+            }
             self.tutorial.update(context);
             if context.client.rewarded_ads && self.key_dispenser.update(context) {
                 context.settings.set_unlocks(
@@ -771,37 +1036,45 @@ impl GameClient for KiometGame {
             }
         }
 
-        if context.keyboard.is_down(Key::R) && context.keyboard.is_down(Key::Shift) {
+        if self.action_down(KeyAction::ClearSupplyLines, context) {
             if let Some(tower_id) = self.selected_tower_id {
                 // Clear supply line of selected tower.
                 if let Some(tower) = context.state.game.world.chunk.get(tower_id) {
                     if tower.supply_line.is_some() {
-                        context.send_to_game(Command::SetSupplyLine {
-                            tower_id,
-                            path: None,
-                        })
+                        self.send_to_game(
+                            Command::SetSupplyLine {
+                                tower_id,
+                                path: None,
+                            },
+                            context,
+                        )
                     }
                 }
             } else if ticked {
-                // 清除所有可见的供应线（但每个tick只有1个）。
-                let tower = context
+                // 清除所有选中的（或可见的）供应线（但每个tick只有1批）。
+                let commands = context
                     .state
                     .game
                     .visible
                     .iter(&context.state.game.world.chunk)
                     .filter(|&(id, t)| {
-                        context.state.game.margin_viewport.contains(id)
-                            && t.supply_line.is_some()
+                        (if self.selected_tower_ids.is_empty() {
+                            context.state.game.margin_viewport.contains(id)
+                        } else {
+                            self.selected_tower_ids.contains(&id)
+                        }) && t.supply_line.is_some()
                             && t.player_id.is_some()
                             && t.player_id == me
                     })
-                    .next();
-                if let Some((tower_id, _)) = tower {
-                    // TODO 迭代视口交集可见和塔。
-                    context.send_to_game(Command::SetSupplyLine {
+                    .take(Command::MAX_BATCH_LEN)
+                    .map(|(tower_id, _)| Command::SetSupplyLine {
                         tower_id,
                         path: None,
-                    });
+                    })
+                    .collect();
+                // TODO 迭代视口交集可见和塔。
+                if let Some(command) = Command::batch(commands) {
+                    self.send_to_game(command, context);
                 }
             }
         }
@@ -809,7 +1082,9 @@ impl GameClient for KiometGame {
         self.pan_zoom
             .set_aspect_ratio(self.render_chain.renderer().aspect_ratio());
 
-        if context.cheats() && context.keyboard.is_down(Key::B) {
+        if context.state.game.spectating
+            || (context.cheats() && self.action_down(KeyAction::RevealMap, context))
+        {
             self.pan_zoom.set_bounds(
                 Vec2::splat(-100.0),
                 Vec2::splat(WorldChunks::SIZE as f32 * TowerId::CONVERSION as f32 + 100.0),
@@ -823,7 +1098,7 @@ impl GameClient for KiometGame {
             self.pan_zoom.set_bounds(
                 bottom_left,
                 top_right,
-                context.cheats() && context.keyboard.is_down(Key::N),
+                context.cheats() && self.action_down(KeyAction::UnboundedCamera, context),
             );
         }
 
@@ -838,61 +1113,66 @@ impl GameClient for KiometGame {
                 self.pan_zoom.reset_center();
                 self.pan_zoom.reset_zoom()
             }
+        }
 
+        if context.state.game.alive || context.state.game.spectating {
             let mut pan = Vec2::ZERO;
             let mut any = false;
 
-            if context
-                .keyboard
-                .state(Key::Left)
-                .combined(context.keyboard.state(Key::A))
-                .is_down()
-            {
+            if self.action_down(KeyAction::PanLeft, context) {
                 pan.x += 1.0;
                 any = true;
             }
-            if context
-                .keyboard
-                .state(Key::Right)
-                .combined(context.keyboard.state(Key::D))
-                .is_down()
-            {
+            if self.action_down(KeyAction::PanRight, context) {
                 pan.x -= 1.0;
                 any = true;
             }
-            if context
-                .keyboard
-                .state(Key::Down)
-                .combined(context.keyboard.state(Key::S))
-                .is_down()
-            {
+            if self.action_down(KeyAction::PanDown, context) {
                 pan.y += 1.0;
                 any = true;
             }
-            if context
-                .keyboard
-                .state(Key::Up)
-                .combined(context.keyboard.state(Key::W))
-                .is_down()
-            {
+            if self.action_down(KeyAction::PanUp, context) {
                 pan.y -= 1.0;
                 any = true;
             }
             self.pan_zoom
                 .pan(pan * elapsed_seconds * self.pan_zoom.get_zooms().max_element() * 1.5);
 
-            if context.keyboard.is_down(Key::H) {
+            if self.action_down(KeyAction::JumpToRuler, context) {
                 if let Some(king) = context.state.game.alerts.ruler_position {
                     self.pan_zoom.pan_to(king.as_vec2());
                 }
             }
 
+            let cycle_alerts = self.action_down(KeyAction::CycleAlerts, context);
+            if cycle_alerts && !self.cycle_alerts_held {
+                let alerts = context.state.game.alerts;
+                let tower_ids: Vec<TowerId> = [
+                    alerts.incoming_nuke,
+                    alerts.incoming_emp,
+                    alerts.emped,
+                    alerts.supply_line_halted,
+                    alerts.zombies,
+                    alerts.overflowing,
+                    alerts.full,
+                ]
+                .into_iter()
+                .flatten()
+                .collect();
+                if !tower_ids.is_empty() {
+                    let tower_id = tower_ids[self.alert_cursor % tower_ids.len()];
+                    self.alert_cursor = self.alert_cursor.wrapping_add(1);
+                    self.pan_zoom.pan_to(tower_id.as_vec2());
+                }
+            }
+            self.cycle_alerts_held = cycle_alerts;
+
             let mut zoom = 1.0;
-            if context.keyboard.state(Key::Q).is_down() {
+            if self.action_down(KeyAction::ZoomOut, context) {
                 zoom -= (elapsed_seconds * 2.5).min(1.0);
                 any = true;
             }
-            if context.keyboard.state(Key::E).is_down() {
+            if self.action_down(KeyAction::ZoomIn, context) {
                 zoom += (elapsed_seconds * 2.5).min(1.0);
                 any = true;
             }
@@ -906,6 +1186,8 @@ impl GameClient for KiometGame {
         } else {
             context.audio.stop_playing(Audio::Music);
             self.selected_tower_id = None;
+            self.selected_tower_ids.clear();
+            self.box_select = None;
             self.drag = None;
             self.pan_zoom.reset_center();
             self.pan_zoom.reset_zoom();
@@ -914,7 +1196,19 @@ impl GameClient for KiometGame {
         // 时间流逝。
         context.state.game.time_since_last_tick += elapsed_seconds;
 
+        if context.state.game.alerts != self.emitted_alerts {
+            self.emitted_alerts = context.state.game.alerts;
+            emit_event(&KiometEvent::Alerts {
+                alerts: self.emitted_alerts,
+            });
+        }
+
         for InfoEvent { position, info } in std::mem::take(&mut context.state.game.info_events) {
+            emit_event(&KiometEvent::Info {
+                position: position.into(),
+                info,
+            });
+
             let volume = 1.0 / (1.0 + position.distance(self.pan_zoom.get_center()));
 
             let animation_type = match info {
@@ -924,6 +1218,7 @@ impl GameClient for KiometGame {
                 }
                 Info::NuclearExplosion => Some(AnimationType::NuclearExplosion),
                 Info::ShellExplosion => Some(AnimationType::ShellExplosion),
+                Info::BeamBurn => Some(AnimationType::BeamBurn),
                 _ => None,
             };
 
@@ -965,13 +1260,16 @@ impl GameClient for KiometGame {
         if send_viewport != context.state.game.set_viewport && self.set_viewport_rate_limit.ready()
         {
             context.state.game.set_viewport = send_viewport;
-            context.send_to_game(Command::SetViewport(send_viewport));
+            self.send_to_game(Command::SetViewport(send_viewport), context);
         }
 
         context.set_ui_props(
             KiometUiProps {
                 lock_dialog: self.lock_dialog,
+                upgrade_all_dialog: self.upgrade_all_dialog,
+                upgrade_candidates: self.upgrade_candidates,
                 alive: context.state.game.alive,
+                spectating: context.state.game.spectating,
                 death_reason: context.state.game.death_reason.into(),
                 selected_tower: self.selected_tower_id.and_then(|tower_id| {
                     // 不要阻碍拖动。
@@ -988,15 +1286,25 @@ impl GameClient for KiometGame {
                         .map(|tower| SelectedTower {
                             client_position: to_client_position(&self.camera, tower_id.as_vec2()),
                             color: Color::new(context, tower.player_id),
-                            outgoing_alliance: context
-                                .state
-                                .core
-                                .player_id
+                            outgoing_alliance: my_player_id(context)
                                 .zip(tower.player_id)
                                 .map(|(us, them)| {
                                     context.state.game.world.player(us).allies.contains(&them)
                                 })
                                 .unwrap_or(false),
+                            has_room_for_large: context
+                                .state
+                                .game
+                                .world
+                                .chunk
+                                .has_room_for_large(tower_id),
+                            queued_upgrade: context
+                                .state
+                                .game
+                                .queued_upgrades
+                                .iter()
+                                .find(|&&(queued, _)| queued == tower_id)
+                                .map(|&(_, tower_type)| tower_type),
                             tower,
                             tower_id,
                         })
@@ -1005,18 +1313,21 @@ impl GameClient for KiometGame {
                 alerts: context.state.game.alerts,
                 tutorial_alert: self.tutorial.alert(),
                 unlocks: context.settings.unlocks.clone(),
+                connection: self.connection.state().clone(),
+                script_dialog: self.script_dialog,
+                script_error: self.script.error().map(str::to_owned),
+                key_bindings_dialog: self.key_bindings_dialog,
             },
             context.state.game.alive,
         );
 
-        self.was_alive = context.state.game.alive;
-    }
+        if self.was_alive && !context.state.game.alive {
+            emit_event(&KiometEvent::Death {
+                reason: context.state.game.death_reason,
+            });
+        }
 
-    pub fn register_global_ptr(&mut self) {
-        let ptr = self as *mut KiometGame;
-        KIOMET_GAME_PTR.with(|cell| {
-            *cell.borrow_mut() = Some(ptr);
-        });
+        self.was_alive = context.state.game.alive;
     }
 }
 
@@ -1030,12 +1341,97 @@ fn is_perilous(context: &ClientContext<KiometGame>, tower_id: TowerId) -> bool {
         .get(tower_id)
         .map(|tower| {
             // 不同的玩家或未被占领的土地是危险的。
-            tower.player_id != context.player_id()
+            tower.player_id != my_player_id(context)
         })
         .unwrap_or(false)
 }
 
 impl KiometGame {
+    /// Command that sends `source`'s forces along the best path to `destination`, or makes that
+    /// path its supply line if `supply_line` and possible. [`None`] if there is no path, or if it
+    /// is perilous to the ruler and the drag started less than [`Self::RULER_DRAG_DELAY`] ago.
+    fn path_command(
+        &self,
+        source: TowerId,
+        destination: TowerId,
+        supply_line: bool,
+        drag_start_time: f32,
+        context: &ClientContext<Self>,
+    ) -> Option<Command> {
+        let source_tower = context.state.game.world.chunk.get(source)?;
+        let strength = source_tower.force_units();
+        let tower_edge_distance = source_tower.tower_type.ranged_distance();
+        let strength_edge_distance = (!strength.is_empty()).then(|| strength.max_edge_distance());
+        let max_edge_distance =
+            strength_edge_distance.map_or(tower_edge_distance, |e| e.min(tower_edge_distance));
+        let shorter_max_edge_distance = max_edge_distance != tower_edge_distance;
+        let supply_line =
+            supply_line && source_tower.generates_mobile_units() && !shorter_max_edge_distance;
+
+        let path = context.state.game.world.find_best_path(
+            source,
+            destination,
+            max_edge_distance,
+            my_player_id(context)?,
+            |tower_id| is_visible(context, tower_id),
+        )?;
+
+        let perilous = path.iter().any(|&tower_id| is_perilous(context, tower_id));
+        if perilous
+            && strength.contains(Unit::Ruler)
+            && context.client.time_seconds < drag_start_time + Self::RULER_DRAG_DELAY
+        {
+            return None;
+        }
+
+        Some(if supply_line {
+            let path = Path::new(path);
+            Command::SetSupplyLine {
+                tower_id: source,
+                // TODO accept any invalid path.
+                path: (source_tower.supply_line.as_ref() != Some(&path)).then_some(path),
+            }
+        } else {
+            Command::deploy_force_from_path(path)
+        })
+    }
+
+    /// Returns true if a key bound to `action` is down, unless the player is rebinding keys.
+    fn action_down(&self, action: KeyAction, context: &ClientContext<Self>) -> bool {
+        !self.key_bindings_dialog && context.settings.key_bindings.is_down(action, context)
+    }
+
+    /// Sends `command` to the custom server if one is set, otherwise to the default one.
+    fn send_to_game(&self, command: Command, context: &mut ClientContext<Self>) {
+        if self.connection.url().is_none() {
+            context.send_to_game(command);
+        } else if !self.connection.send(&command) {
+            // 默认服务器是另一个游戏，不能代替。
+            js_hooks::console_log(&format!(
+                "未连接到自定义服务器 ({:?})，已丢弃命令",
+                self.connection.state()
+            ));
+        }
+    }
+
+    /// Saves `url`, which [`ServerConnection`] picks up on the next update.
+    fn set_server_address(&mut self, url: Option<String>, context: &mut ClientContext<Self>) {
+        let url = url.and_then(|url| validate_server_url(&url).ok().map(str::to_owned));
+        context
+            .settings
+            .set_server_address(url, &mut context.browser_storages);
+    }
+
+    /// Returns true if dragging from `tower_id` sets supply lines, which it does if its menu is
+    /// open, or the menu of another tower in [`Self::selected_tower_ids`] along with it.
+    fn is_supply_line_source(&self, tower_id: TowerId) -> bool {
+        self.selected_tower_id.is_some_and(|selected| {
+            selected == tower_id
+                || (self.selected_tower_ids.contains(&selected)
+                    && self.selected_tower_ids.contains(&tower_id))
+        })
+    }
+
     fn close_tower_menu(&mut self) {
         // Ui 在拖动时已经隐藏。
         if self.drag.is_none() {
@@ -1054,7 +1450,7 @@ impl KiometGame {
             let Some(source_tower) = context.state.game.world.chunk.get(start) else {
                 return;
             };
-            if source_tower.player_id.is_none() || source_tower.player_id != context.player_id() {
+            if source_tower.player_id.is_none() || source_tower.player_id != my_player_id(context) {
                 return;
             }
 
@@ -1086,7 +1482,7 @@ impl KiometGame {
                         start,
                         current,
                         max_edge_distance,
-                        context.player_id().unwrap(),
+                        my_player_id(context).unwrap(),
                         &|tower_id| is_visible(context, tower_id),
                     )
                     .into_iter()
@@ -1124,18 +1520,117 @@ pub fn exists(context: &ClientContext<KiometGame>, tower_id: TowerId) -> bool {
     context.state.game.world.chunk.get(tower_id).is_some()
 }
 
+/// The player's id on the custom server, if one is set, otherwise on the default one.
+pub fn my_player_id(context: &ClientContext<KiometGame>) -> Option<PlayerId> {
+    context
+        .state
+        .game
+        .custom_player_id()
+        .unwrap_or_else(|| context.player_id())
+}
+
 pub fn is_visible(context: &ClientContext<KiometGame>, tower_id: TowerId) -> bool {
     context.state.game.visible.contains(tower_id)
 }
 
+fn own_towers(context: &ClientContext<KiometGame>) -> impl Iterator<Item = (TowerId, &Tower)> + '_ {
+    let me = my_player_id(context);
+    context
+        .state
+        .game
+        .visible
+        .iter(&context.state.game.world.chunk)
+        .filter(move |(_, tower)| me.is_some() && tower.player_id == me)
+}
+
+/// Returns true if the player could upgrade their `tower` to `tower_type` right now, like
+/// [`TowerOverlay`][`crate::ui::tower_overlay::TowerOverlay`] allows.
+fn can_upgrade(
+    tower_id: TowerId,
+    tower: &Tower,
+    tower_type: TowerType,
+    context: &ClientContext<KiometGame>,
+) -> bool {
+    tower.active()
+        && tower.tower_type.can_upgrade_to(tower_type)
+        && context.settings.unlocks.contains(tower_type)
+        && tower_type.has_prerequisites(&context.state.game.tower_counts)
+        && (!tower_type.is_large() || context.state.game.world.chunk.has_room_for_large(tower_id))
+}
+
+fn is_mine(context: &ClientContext<KiometGame>, tower_id: TowerId) -> bool {
+    let me = my_player_id(context);
+    me.is_some()
+        && context
+            .state
+            .game
+            .world
+            .chunk
+            .get(tower_id)
+            .map_or(false, |tower| tower.player_id == me)
+}
+
+/// Returns what a spectator has selected after the mouse goes `down` (or up) near `closest`, with
+/// `selected` selected before. Selects on mouse down, since spectators can't drag, and clicking
+/// the selected tower again deselects it, like double clicking does for players.
+fn spectator_click(
+    selected: Option<TowerId>,
+    closest: Option<TowerId>,
+    down: bool,
+) -> Option<TowerId> {
+    if down {
+        closest.filter(|&closest| selected != Some(closest))
+    } else {
+        selected
+    }
+}
+
+/// Own visible towers in the box between world positions `a` and `b`, up to
+/// [`Command::MAX_BATCH_LEN`].
+fn towers_in_box(a: Vec2, b: Vec2, context: &ClientContext<KiometGame>) -> BTreeSet<TowerId> {
+    let visible = context
+        .state
+        .game
+        .visible
+        .iter(&context.state.game.world.chunk);
+    towers_in_box_inner(a, b, my_player_id(context), visible)
+}
+
+fn towers_in_box_inner<'a>(
+    a: Vec2,
+    b: Vec2,
+    me: Option<PlayerId>,
+    towers: impl Iterator<Item = (TowerId, &'a Tower)>,
+) -> BTreeSet<TowerId> {
+    let (min, max) = (a.min(b), a.max(b));
+    towers
+        .filter(|&(tower_id, tower)| {
+            let position = tower_id.as_vec2();
+            me.is_some()
+                && tower.player_id == me
+                && position.cmpge(min).all()
+                && position.cmple(max).all()
+        })
+        .map(|(tower_id, _)| tower_id)
+        .take(Command::MAX_BATCH_LEN)
+        .collect()
+}
+
 /// 更新可见的塔（只在每个游戏tick中执行工作）。
 fn update_visible(context: &mut ClientContext<KiometGame>) {
-    let Some(me) = context.player_id() else {
+    let me = my_player_id(context);
+    let spectating = context.state.game.spectating;
+    if me.is_none() && !spectating {
         return;
-    };
+    }
 
-    let all_visible =
-        !context.state.game.alive || (context.cheats() && context.keyboard.is_down(Key::B));
+    let all_visible = spectating
+        || !context.state.game.alive
+        || (context.cheats()
+            && context
+                .settings
+                .key_bindings
+                .is_down(KeyAction::RevealMap, context));
     context
         .state
         .game
@@ -1193,699 +1688,574 @@ fn tower_shield_intensity_radius(tower: &Tower) -> (f32, f32) {
     )
 }
 
-
-
-
-
-
-// 全局可访问的游戏实例
-thread_local! {
-    pub static KIOMET_GAME_PTR: std::cell::RefCell<Option<*mut KiometGame>> = std::cell::RefCell::new(None);
-}
-
-// 1. 定义一个包含所有游戏信息的结构体
-#[derive(Serialize, Deserialize)]
+/// 游戏的只读快照，由 [`KiometGame::update`] 在每个 tick 发布，所有 `kiomet_get_*` 函数都读取它。
+#[derive(Serialize)]
 pub struct KiometFullState {
     // 游戏核心状态
     pub alive: bool,
-    pub death_reason: Option<String>,
-    pub time_since_last_tick: f32,
-    
+    pub spectating: bool,
+    pub death_reason: Option<DeathReason>,
+
     // 玩家信息
-    pub current_player_id: Option<common::PlayerId>,
+    pub current_player_id: Option<PlayerId>,
     pub players: Vec<PlayerInfo>,
-    
+
     // 塔信息
     pub towers: Vec<TowerInfo>,
-    
+
     // 部队信息
     pub forces: Vec<ForceInfo>,
-    
+
     // 地图/世界信息
-    pub world_bounds: Option<WorldBounds>,
-    pub tight_viewport: Option<ViewportInfo>,
-    pub margin_viewport: Option<ViewportInfo>,
-    
-    // 警报和事件
-    pub alerts: AlertsInfo,
-    
+    pub world_bounds: Option<TowerRectangle>,
+    pub tight_viewport: TowerRectangle,
+    pub margin_viewport: TowerRectangle,
+
+    // 警报
+    pub alerts: Alerts,
+
     // 摄像机信息
     pub camera: CameraInfo,
-    
+
     // 选中状态
-    pub selected_tower_id: Option<u32>,
-    
-    // 其他状态
-    pub tutorial_state: Option<TutorialState>,
+    pub selected_tower_id: Option<TowerId>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize)]
 pub struct PlayerInfo {
-    pub id: common::PlayerId,
+    pub id: PlayerId,
     pub alias: String,
     pub authentic: bool,
-    pub allies: Vec<common::PlayerId>,
+    pub allies: Vec<PlayerId>,
     pub tower_count: u32,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize)]
 pub struct TowerInfo {
-    pub id: u32,
+    pub id: TowerId,
     pub position: [f32; 2],
-    pub tower_type: String,
-    pub player_id: Option<common::PlayerId>,
-    pub units: Vec<UnitInfo>,
-    pub inbound_forces: Vec<u32>, // 引用forces数组中的索引
+    pub tower_type: TowerType,
+    pub player_id: Option<PlayerId>,
+    pub units: Units,
+    pub inbound_forces: Vec<u32>,  // 引用forces数组中的索引
     pub outbound_forces: Vec<u32>, // 引用forces数组中的索引
-    pub supply_line: Option<Vec<u32>>, // 供应线路径（塔ID列表）
+    pub supply_line: Option<Path>,
     pub active: bool,
     pub visible: bool,
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct UnitInfo {
-    pub unit_type: String,
-    pub count: u32,
+#[derive(Serialize)]
+pub struct ForceInfo {
+    pub id: u32, // forces数组中的索引
+    pub player_id: Option<PlayerId>,
+    pub units: Units,
+    pub source: TowerId,
+    pub destination: TowerId,
+    pub position: [f32; 2],
+    pub fuel: u8,
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct ForceInfo {
-    pub id: u32, // 自定义ID，用于引用
-    pub player_id: Option<common::PlayerId>,
-    pub units: Vec<UnitInfo>,
-    pub source: u32, // 源塔ID
-    pub destination: u32, // 目标塔ID
-    pub current_position: [f32; 2],
-    pub progress: f32, // 0.0-1.0之间的进度
+#[derive(Serialize)]
+pub struct CameraInfo {
+    pub center: [f32; 2],
+    pub zoom: f32,
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct WorldBounds {
-    pub min_x: f32,
-    pub min_y: f32,
-    pub max_x: f32,
-    pub max_y: f32,
+thread_local! {
+    /// 由 [`KiometGame::publish_snapshot`] 替换。JS 回调永远不会访问游戏本身。
+    static KIOMET_SNAPSHOT: std::cell::RefCell<Option<KiometFullState>> = Default::default();
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct ViewportInfo {
-    pub min_x: i32,
-    pub min_y: i32,
-    pub max_x: i32,
-    pub max_y: i32,
+/// Own towers that can be upgraded to `tower_type`, of which [`KiometUiEvent::UpgradeAll`]
+/// upgrades as many as fit in one batch. Large towers can't be next to each other, so neither can
+/// those picked to become large.
+fn upgrade_all_targets(
+    tower_type: TowerType,
+    in_viewport: bool,
+    context: &ClientContext<KiometGame>,
+) -> BTreeSet<TowerId> {
+    let mut targets = BTreeSet::new();
+    for (tower_id, tower) in own_towers(context) {
+        if (in_viewport && !context.state.game.tight_viewport.contains(tower_id))
+            || !can_upgrade(tower_id, tower, tower_type, context)
+        {
+            continue;
+        }
+        if tower_type.is_large() && tower_id.neighbors().any(|n| targets.contains(&n)) {
+            continue;
+        }
+        targets.insert(tower_id);
+    }
+    targets
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct AlertsInfo {
-    pub ruler_position: Option<u32>, // 国王位置（塔ID）
-    pub messages: Vec<String>,
+/// How many towers [`upgrade_all_targets`] finds for the tower type of the open
+/// [`UpgradeAllDialog`][`crate::ui::upgrade_all_dialog::UpgradeAllDialog`], for its preview.
+fn upgrade_candidates(
+    upgrade_all_dialog: Option<TowerType>,
+    context: &ClientContext<KiometGame>,
+) -> UpgradeCandidates {
+    let Some(tower_type) = upgrade_all_dialog.filter(|_| context.state.game.alive) else {
+        return UpgradeCandidates::default();
+    };
+    let count = |in_viewport| upgrade_all_targets(tower_type, in_viewport, context).len() as u16;
+    UpgradeCandidates {
+        all: count(false),
+        in_viewport: count(true),
+    }
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct CameraInfo {
-    pub center: [f32; 2],
-    pub zoom: f32,
+/// 如果还没有快照，返回 `null`。
+fn with_snapshot(f: impl FnOnce(&KiometFullState) -> JsValue) -> JsValue {
+    KIOMET_SNAPSHOT.with(|snapshot| snapshot.borrow().as_ref().map_or(JsValue::NULL, f))
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct TutorialState {
-    pub completed: Vec<String>,
-    pub current: Option<String>,
+fn to_js<T: Serialize + ?Sized>(value: &T) -> JsValue {
+    JsValue::from_serde(value).unwrap_or(JsValue::NULL)
 }
 
 #[wasm_bindgen]
 pub fn kiomet_get_full_state() -> JsValue {
-    KIOMET_GAME_PTR.with(|ptr| {
-        if let Some(game_ptr) = *ptr.borrow() {
-            let game = unsafe { &*game_ptr };
-            
-            // 构建完整状态
-            let mut full_state = KiometFullState {
-                alive: game.territories.state().game.alive,
-                death_reason: game.territories.state().game.death_reason.clone().map(|r| format!("{:?}", r)),
-                time_since_last_tick: game.territories.state().game.time_since_last_tick,
-                
-                current_player_id: game.territories.state().core.player_id,
-                players: Vec::new(),
-                
-                towers: Vec::new(),
-                forces: Vec::new(),
-                
-                world_bounds: game.territories.state().game.world.bounds.clone().map(|b| WorldBounds {
-                    min_x: b.min.x,
-                    min_y: b.min.y,
-                    max_x: b.max.x,
-                    max_y: b.max.y,
-                }),
-                
-                tight_viewport: game.territories.state().game.tight_viewport.map(|v| ViewportInfo {
-                    min_x: v.bottom_left.x,
-                    min_y: v.bottom_left.y,
-                    max_x: v.top_right.x,
-                    max_y: v.top_right.y,
-                }),
-                
-                margin_viewport: game.territories.state().game.margin_viewport.map(|v| ViewportInfo {
-                    min_x: v.bottom_left.x,
-                    min_y: v.bottom_left.y,
-                    max_x: v.top_right.x,
-                    max_y: v.top_right.y,
-                }),
-                
-                alerts: AlertsInfo {
-                    ruler_position: game.territories.state().game.alerts.ruler_position.map(|id| id.as_u32()),
-                    messages: game.territories.state().game.alerts.messages.clone(),
-                },
-                
-                camera: CameraInfo {
-                    center: [game.pan_zoom.get_center().x, game.pan_zoom.get_center().y],
-                    zoom: game.pan_zoom.get_zoom(),
-                },
-                
-                selected_tower_id: game.selected_tower_id.map(|id| id.as_u32()),
-                
-                tutorial_state: Some(TutorialState {
-                    completed: game.tutorial.completed_steps(),
-                    current: game.tutorial.current_step(),
-                }),
-            };
-            
-            // 填充玩家信息
-            for (&player_id, player) in &game.territories.state().game.world.players {
-                full_state.players.push(PlayerInfo {
-                    id: player_id,
-                    alias: player.alias.clone(),
-                    authentic: player.authentic,
-                    allies: player.allies.iter().copied().collect(),
-                    tower_count: game.territories.state().game.world.count_towers(player_id) as u32,
-                });
-            }
-            
-            // 临时存储部队，用于引用
-            let mut force_map = std::collections::HashMap::new();
-            let mut force_id_counter = 0u32;
-            
-            // 填充塔信息
-            for (tower_id, tower) in game.territories.state().game.world.chunk.iter() {
-                let mut tower_info = TowerInfo {
-                    id: tower_id.as_u32(),
-                    position: [tower_id.as_vec2().x, tower_id.as_vec2().y],
-                    tower_type: format!("{:?}", tower.tower_type),
-                    player_id: tower.player_id,
-                    units: tower.units.iter().map(|(unit, count)| UnitInfo {
-                        unit_type: format!("{:?}", unit),
-                        count: *count as u32,
-                    }).collect(),
-                    inbound_forces: Vec::new(),
-                    outbound_forces: Vec::new(),
-                    supply_line: tower.supply_line.as_ref().map(|path| 
-                        path.iter().map(|id| id.as_u32()).collect()
-                    ),
-                    active: tower.active(),
-                    visible: game.territories.state().game.visible.contains(tower_id),
-                };
-                
-                // 处理入站部队
-                for force in &tower.inbound_forces {
-                    let force_id = force_id_counter;
-                    force_id_counter += 1;
-                    
-                    let force_info = ForceInfo {
-                        id: force_id,
-                        player_id: force.player_id,
-                        units: force.units.iter().map(|(unit, count)| UnitInfo {
-                            unit_type: format!("{:?}", unit),
-                            count: *count as u32,
-                        }).collect(),
-                        source: force.source.as_u32(),
-                        destination: force.destination.as_u32(),
-                        current_position: {
-                            let pos = force.interpolated_position(game.territories.state().game.time_since_last_tick);
-                            [pos.x, pos.y]
-                        },
-                        progress: force.progress,
-                    };
-                    
-                    force_map.insert(force_id, force_info);
-                    tower_info.inbound_forces.push(force_id);
-                }
-                
-                // 处理出站部队
-                for force in &tower.outbound_forces {
-                    let force_id = force_id_counter;
-                    force_id_counter += 1;
-                    
-                    let force_info = ForceInfo {
-                        id: force_id,
-                        player_id: force.player_id,
-                        units: force.units.iter().map(|(unit, count)| UnitInfo {
-                            unit_type: format!("{:?}", unit),
-                            count: *count as u32,
-                        }).collect(),
-                        source: force.source.as_u32(),
-                        destination: force.destination.as_u32(),
-                        current_position: {
-                            let pos = force.interpolated_position(game.territories.state().game.time_since_last_tick);
-                            [pos.x, pos.y]
-                        },
-                        progress: force.progress,
-                    };
-                    
-                    force_map.insert(force_id, force_info);
-                    tower_info.outbound_forces.push(force_id);
-                }
-                
-                full_state.towers.push(tower_info);
-            }
-            
-            // 添加所有部队
-            full_state.forces = force_map.into_iter().map(|(_, force)| force).collect();
-            
-            // 序列化并返回
-            match JsValue::from_serde(&full_state) {
-                Ok(js_value) => js_value,
-                Err(_) => {
-                    // 如果序列化失败，至少返回基本信息
-                    let basic_info = serde_json::json!({
-                        "error": "序列化完整状态失败",
-                        "alive": full_state.alive,
-                        "player_id": full_state.current_player_id,
-                        "tower_count": full_state.towers.len(),
-                    });
-                    JsValue::from_serde(&basic_info).unwrap_or(JsValue::NULL)
-                }
-            }
-        } else {
-            JsValue::NULL
+    with_snapshot(to_js)
+}
+
+/// 脚本可以执行的操作，例如 `{"type": "command", "command": {"Upgrade": {...}}}`。
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum KiometAction {
+    /// 发送到服务器的任意 [`Command`]，使用与 [`Command`] 相同的 JSON 格式。
+    Command {
+        command: Command,
+    },
+    PanCamera {
+        x: f32,
+        y: f32,
+        zoom: Option<f32>,
+    },
+    SelectTower {
+        tower_id: TowerId,
+    },
+    DeselectTower,
+    /// 保存自定义服务器地址（[`None`] 则使用默认服务器）。
+    SetServerAddress {
+        url: Option<String>,
+    },
+    /// 立即重新连接到自定义服务器。
+    Reconnect,
+}
+
+/// [`kiomet_do_action`] 的结果。
+#[derive(Serialize)]
+pub struct KiometActionResult {
+    /// 操作是否已排队。
+    pub accepted: bool,
+    /// 如果没有被接受，原因是什么。
+    pub error: Option<String>,
+}
+
+impl KiometActionResult {
+    fn accepted() -> Self {
+        Self {
+            accepted: true,
+            error: None,
         }
-    })
+    }
+
+    fn rejected(error: impl Into<String>) -> Self {
+        Self {
+            accepted: false,
+            error: Some(error.into()),
+        }
+    }
+}
+
+/// 防止脚本使用无限的内存。
+const MAX_QUEUED_ACTIONS: usize = 256;
+
+thread_local! {
+    /// 由 [`kiomet_do_action`] 排队，在 [`KiometGame::update`] 中执行。
+    static KIOMET_ACTIONS: std::cell::RefCell<Vec<KiometAction>> = Default::default();
 }
 
+/// 排队一个 [`KiometAction`]，在下一次更新时执行。返回 [`KiometActionResult`]。
 #[wasm_bindgen]
-pub fn kiomet_do_action(action: &JsValue) -> bool {
-    KIOMET_GAME_PTR.with(|ptr| {
-        if let Some(game_ptr) = *ptr.borrow() {
-            let game = unsafe { &mut *game_ptr };
-            
-            // 尝试解析为Command
-            if let Ok(command) = action.into_serde::<Command>() {
-                // 由于我们无法直接访问ClientContext，这里直接记录命令
-                // 在实际项目中，你需要找到一种方法来访问ClientContext或直接处理命令
-                js_hooks::console_log(&format!("收到命令: {:?}", command));
-                
-                // 对于某些可以直接在游戏实例上操作的命令，我们可以直接处理
-                match command {
-                    Command::SetSupplyLine { tower_id, path } => {
-                        // 在这里我们可以尝试直接修改游戏状态
-                        if let Some(tower) = game.territories.state_mut().game.world.chunk.get_mut(tower_id) {
-                            tower.supply_line = path;
-                            return true;
-                        }
-                    },
-                    Command::Upgrade { tower_id, tower_type } => {
-                        // 记录升级请求
-                        js_hooks::console_log(&format!("升级塔 {} 到类型 {:?}", tower_id.as_u32(), tower_type));
-                    },
-                    _ => {}
-                }
-                
-                // 返回true表示我们接收了命令，即使我们可能无法立即处理它
-                return true;
-            }
-            
-            // 尝试解析为自定义操作
-            if let Ok(action_data) = action.into_serde::<serde_json::Value>() {
-                if let Some(action_type) = action_data.get("type").and_then(|v| v.as_str()) {
-                    match action_type {
-                        "pan_camera" => {
-                            if let (Some(x), Some(y)) = (
-                                action_data.get("x").and_then(|v| v.as_f64()).map(|v| v as f32),
-                                action_data.get("y").and_then(|v| v.as_f64()).map(|v| v as f32)
-                            ) {
-                                let pos = Vec2::new(x, y);
-                                game.pan_zoom.pan_to(pos);
-                                
-                                if let Some(zoom) = action_data.get("zoom").and_then(|v| v.as_f64()).map(|v| v as f32) {
-                                    game.pan_zoom.set_zoom(zoom);
-                                }
-                                
-                                return true;
-                            }
-                        },
-                        "select_tower" => {
-                            if let Some(tower_id) = action_data.get("tower_id").and_then(|v| v.as_u64()).map(|v| TowerId::from_u32(v as u32)) {
-                                game.selected_tower_id = Some(tower_id);
-                                return true;
-                            }
-                        },
-                        "deselect_tower" => {
-                            game.selected_tower_id = None;
-                            return true;
-                        },
-                        _ => {}
-                    }
-                }
-            }
-            
-            false
+pub fn kiomet_do_action(action: &JsValue) -> JsValue {
+    let result = match action.into_serde::<KiometAction>() {
+        Ok(action) => enqueue_action(action),
+        Err(e) => KiometActionResult::rejected(format!("invalid action: {e}")),
+    };
+    JsValue::from_serde(&result).unwrap_or(JsValue::NULL)
+}
+
+fn enqueue_action(action: KiometAction) -> KiometActionResult {
+    if let KiometAction::Command { command } = &action {
+        // 服务器会拒绝整批，所以提前告诉脚本。
+        if let Err(e) = command.check_batch() {
+            return KiometActionResult::rejected(e);
+        }
+    }
+    if let KiometAction::SetServerAddress { url: Some(url) } = &action {
+        if let Err(e) = validate_server_url(url) {
+            return KiometActionResult::rejected(e);
+        }
+    }
+    KIOMET_ACTIONS.with(|queue| {
+        let mut queue = queue.borrow_mut();
+        if queue.len() >= MAX_QUEUED_ACTIONS {
+            KiometActionResult::rejected("too many queued actions")
         } else {
-            false
+            queue.push(action);
+            KiometActionResult::accepted()
         }
     })
 }
 
 // 添加分类获取游戏信息的函数
 
+/// 所有塔，或者只有 `filter_type` 类型的塔（例如 `"Barracks"`）。
 #[wasm_bindgen]
 pub fn kiomet_get_towers(filter_type: Option<String>) -> JsValue {
-    KIOMET_GAME_PTR.with(|ptr| {
-        if let Some(game_ptr) = *ptr.borrow() {
-            let game = unsafe { &*game_ptr };
-            
-            // 收集所有塔或按类型过滤
-            let towers: Vec<TowerInfo> = game.territories.state().game.world.chunk.iter()
-                .filter(|(_, tower)| {
-                    if let Some(ref tower_type) = &filter_type {
-                        format!("{:?}", tower.tower_type) == *tower_type
-                    } else {
-                        true
-                    }
-                })
-                .map(|(tower_id, tower)| {
-                    TowerInfo {
-                        id: tower_id.as_u32(),
-                        position: [tower_id.as_vec2().x, tower_id.as_vec2().y],
-                        tower_type: format!("{:?}", tower.tower_type),
-                        player_id: tower.player_id,
-                        units: tower.units.iter().map(|(unit, count)| UnitInfo {
-                            unit_type: format!("{:?}", unit),
-                            count: *count as u32,
-                        }).collect(),
-                        inbound_forces: Vec::new(), // 简化版不包含力量引用
-                        outbound_forces: Vec::new(),
-                        supply_line: tower.supply_line.as_ref().map(|path| 
-                            path.iter().map(|id| id.as_u32()).collect()
-                        ),
-                        active: tower.active(),
-                        visible: game.territories.state().game.visible.contains(tower_id),
-                    }
-                })
-                .collect();
-            
-            JsValue::from_serde(&towers).unwrap_or(JsValue::NULL)
-        } else {
-            JsValue::NULL
-        }
+    with_snapshot(|snapshot| {
+        let towers: Vec<&TowerInfo> = snapshot
+            .towers
+            .iter()
+            .filter(|tower| {
+                filter_type
+                    .as_ref()
+                    .map_or(true, |t| format!("{:?}", tower.tower_type) == *t)
+            })
+            .collect();
+        to_js(&towers)
     })
 }
 
 #[wasm_bindgen]
-pub fn kiomet_get_tower_detail(tower_id: u32) -> JsValue {
-    KIOMET_GAME_PTR.with(|ptr| {
-        if let Some(game_ptr) = *ptr.borrow() {
-            let game = unsafe { &*game_ptr };
-            
-            let tower_id = TowerId::from_u32(tower_id);
-            if let Some(tower) = game.territories.state().game.world.chunk.get(tower_id) {
-                let tower_info = TowerInfo {
-                    id: tower_id.as_u32(),
-                    position: [tower_id.as_vec2().x, tower_id.as_vec2().y],
-                    tower_type: format!("{:?}", tower.tower_type),
-                    player_id: tower.player_id,
-                    units: tower.units.iter().map(|(unit, count)| UnitInfo {
-                        unit_type: format!("{:?}", unit),
-                        count: *count as u32,
-                    }).collect(),
-                    inbound_forces: tower.inbound_forces.iter().enumerate().map(|(i, _)| i as u32).collect(),
-                    outbound_forces: tower.outbound_forces.iter().enumerate().map(|(i, _)| i as u32).collect(),
-                    supply_line: tower.supply_line.as_ref().map(|path| 
-                        path.iter().map(|id| id.as_u32()).collect()
-                    ),
-                    active: tower.active(),
-                    visible: game.territories.state().game.visible.contains(tower_id),
-                };
-                
-                JsValue::from_serde(&tower_info).unwrap_or(JsValue::NULL)
-            } else {
-                JsValue::NULL
-            }
-        } else {
-            JsValue::NULL
-        }
+pub fn kiomet_get_tower_detail(x: u16, y: u16) -> JsValue {
+    let tower_id = TowerId::new(x, y);
+    with_snapshot(|snapshot| {
+        snapshot
+            .towers
+            .iter()
+            .find(|tower| tower.id == tower_id)
+            .map_or(JsValue::NULL, to_js)
     })
 }
 
 #[wasm_bindgen]
 pub fn kiomet_get_forces() -> JsValue {
-    KIOMET_GAME_PTR.with(|ptr| {
-        if let Some(game_ptr) = *ptr.borrow() {
-            let game = unsafe { &*game_ptr };
-            
-            // 收集所有移动中的部队
-            let mut forces = Vec::new();
-            let mut force_id_counter = 0u32;
-            
-            for (tower_id, tower) in game.territories.state().game.world.chunk.iter() {
-                // 收集入站部队
-                for force in &tower.inbound_forces {
-                    let force_info = ForceInfo {
-                        id: force_id_counter,
-                        player_id: force.player_id,
-                        units: force.units.iter().map(|(unit, count)| UnitInfo {
-                            unit_type: format!("{:?}", unit),
-                            count: *count as u32,
-                        }).collect(),
-                        source: force.source.as_u32(),
-                        destination: force.destination.as_u32(),
-                        current_position: {
-                            let pos = force.interpolated_position(game.territories.state().game.time_since_last_tick);
-                            [pos.x, pos.y]
-                        },
-                        progress: force.progress,
-                    };
-                    
-                    forces.push(force_info);
-                    force_id_counter += 1;
-                }
-                
-                // 收集出站部队
-                for force in &tower.outbound_forces {
-                    let force_info = ForceInfo {
-                        id: force_id_counter,
-                        player_id: force.player_id,
-                        units: force.units.iter().map(|(unit, count)| UnitInfo {
-                            unit_type: format!("{:?}", unit),
-                            count: *count as u32,
-                        }).collect(),
-                        source: force.source.as_u32(),
-                        destination: force.destination.as_u32(),
-                        current_position: {
-                            let pos = force.interpolated_position(game.territories.state().game.time_since_last_tick);
-                            [pos.x, pos.y]
-                        },
-                        progress: force.progress,
-                    };
-                    
-                    forces.push(force_info);
-                    force_id_counter += 1;
-                }
-            }
-            
-            JsValue::from_serde(&forces).unwrap_or(JsValue::NULL)
-        } else {
-            JsValue::NULL
-        }
-    })
+    with_snapshot(|snapshot| to_js(&snapshot.forces))
 }
 
 #[wasm_bindgen]
 pub fn kiomet_get_players() -> JsValue {
-    KIOMET_GAME_PTR.with(|ptr| {
-        if let Some(game_ptr) = *ptr.borrow() {
-            let game = unsafe { &*game_ptr };
-            
-            // 收集所有玩家信息
-            let players: Vec<PlayerInfo> = game.territories.state().game.world.players.iter()
-                .map(|(&player_id, player)| {
-                    PlayerInfo {
-                        id: player_id,
-                        alias: player.alias.clone(),
-                        authentic: player.authentic,
-                        allies: player.allies.iter().copied().collect(),
-                        tower_count: game.territories.state().game.world.count_towers(player_id) as u32,
-                    }
-                })
-                .collect();
-            
-            JsValue::from_serde(&players).unwrap_or(JsValue::NULL)
-        } else {
-            JsValue::NULL
-        }
-    })
+    with_snapshot(|snapshot| to_js(&snapshot.players))
 }
 
 #[wasm_bindgen]
 pub fn kiomet_get_game_state() -> JsValue {
-    KIOMET_GAME_PTR.with(|ptr| {
-        if let Some(game_ptr) = *ptr.borrow() {
-            let game = unsafe { &*game_ptr };
-            
-            // 游戏状态信息
-            let state_info = serde_json::json!({
-                "alive": game.territories.state().game.alive,
-                "death_reason": game.territories.state().game.death_reason.clone().map(|r| format!("{:?}", r)),
-                "time_since_last_tick": game.territories.state().game.time_since_last_tick,
-                "current_player_id": game.territories.state().core.player_id,
-                "selected_tower_id": game.selected_tower_id.map(|id| id.as_u32()),
-                "camera": {
-                    "center": [game.pan_zoom.get_center().x, game.pan_zoom.get_center().y],
-                    "zoom": game.pan_zoom.get_zoom()
-                },
-                "alerts": {
-                    "ruler_position": game.territories.state().game.alerts.ruler_position.map(|id| id.as_u32()),
-                    "messages": game.territories.state().game.alerts.messages
-                }
-            });
-            
-            JsValue::from_serde(&state_info).unwrap_or(JsValue::NULL)
-        } else {
-            JsValue::NULL
-        }
+    with_snapshot(|snapshot| {
+        to_js(&serde_json::json!({
+            "alive": snapshot.alive,
+            "spectating": snapshot.spectating,
+            "death_reason": snapshot.death_reason,
+            "current_player_id": snapshot.current_player_id,
+            "selected_tower_id": snapshot.selected_tower_id,
+            "camera": snapshot.camera,
+            "alerts": snapshot.alerts,
+        }))
     })
 }
 
 #[wasm_bindgen]
-pub fn kiomet_get_area_towers(x1: i32, y1: i32, x2: i32, y2: i32) -> JsValue {
-    KIOMET_GAME_PTR.with(|ptr| {
-        if let Some(game_ptr) = *ptr.borrow() {
-            let game = unsafe { &*game_ptr };
-            
-            let rect = TowerRectangle::new(
-                TowerId::new(x1, y1),
-                TowerId::new(x2, y2)
-            );
-            
-            let area_towers: Vec<TowerInfo> = game.territories.state().game.world.chunk
-                .iter_towers_rectangle(rect)
-                .map(|(tower_id, tower)| {
-                    TowerInfo {
-                        id: tower_id.as_u32(),
-                        position: [tower_id.as_vec2().x, tower_id.as_vec2().y],
-                        tower_type: format!("{:?}", tower.tower_type),
-                        player_id: tower.player_id,
-                        units: tower.units.iter().map(|(unit, count)| UnitInfo {
-                            unit_type: format!("{:?}", unit),
-                            count: *count as u32,
-                        }).collect(),
-                        inbound_forces: Vec::new(),
-                        outbound_forces: Vec::new(),
-                        supply_line: tower.supply_line.as_ref().map(|path| 
-                            path.iter().map(|id| id.as_u32()).collect()
-                        ),
-                        active: tower.active(),
-                        visible: game.territories.state().game.visible.contains(tower_id),
-                    }
-                })
-                .collect();
-                
-            JsValue::from_serde(&area_towers).unwrap_or(JsValue::NULL)
-        } else {
-            JsValue::NULL
-        }
+pub fn kiomet_get_area_towers(x1: u16, y1: u16, x2: u16, y2: u16) -> JsValue {
+    let rect = TowerRectangle::new(
+        TowerId::new(x1.min(x2), y1.min(y2)),
+        TowerId::new(x1.max(x2), y1.max(y2)),
+    );
+    with_snapshot(|snapshot| {
+        let towers: Vec<&TowerInfo> = snapshot
+            .towers
+            .iter()
+            .filter(|tower| rect.contains(tower.id))
+            .collect();
+        to_js(&towers)
     })
 }
 
-// 添加一个新函数，用于设置自定义服务器地址
+/// 保存自定义服务器地址（空字符串则清除），然后连接到它。如果地址无效，返回 false。
 #[wasm_bindgen(js_name = "kiomet_set_server_address")]
 pub fn kiomet_set_server_address(server_url: &str) -> bool {
-    js_hooks::console_log(&format!("设置服务器地址: {}", server_url));
-    
-    KIOMET_GAME_PTR.with(|ptr| {
-        if let Some(game_ptr) = *ptr.borrow() {
-            let game = unsafe { &mut *game_ptr };
-            
-            // 直接存储服务器地址，即使无法获取上下文
-            // 在JS端可以通过localStorage存储
-            js_hooks::console_log("服务器地址已设置");
-            js_hooks::eval(&format!(
-                "localStorage.setItem('kiomet_server_address', '{}');", 
-                server_url.replace('\'', "\\'")
-            ));
-            return true;
-        } else {
-            js_hooks::console_log("游戏实例不可用，无法保存服务器地址");
+    let url = if server_url.trim().is_empty() {
+        None
+    } else {
+        match validate_server_url(server_url) {
+            Ok(url) => Some(url.to_owned()),
+            Err(e) => {
+                js_hooks::console_log(&format!("无效的服务器地址: {e}"));
+                return false;
+            }
         }
-        
-        false
-    })
+    };
+    enqueue_action(KiometAction::SetServerAddress { url }).accepted
 }
 
-// 添加一个函数用于连接到自定义服务器
+/// 立即重新连接到已保存的自定义服务器。
 #[wasm_bindgen(js_name = "kiomet_connect_to_server")]
 pub fn kiomet_connect_to_server() -> bool {
-    js_hooks::console_log("尝试连接到自定义服务器...");
-    
-    // 从localStorage获取服务器地址
-    let server_url = js_hooks::eval_returns_string(
-        "localStorage.getItem('kiomet_server_address') || ''"
-    );
-    
-    if server_url.is_empty() {
-        js_hooks::console_log("未设置自定义服务器地址，无法连接");
-        js_hooks::eval("alert('请先输入服务器地址！')");
-        return false;
+    enqueue_action(KiometAction::Reconnect).accepted
+}
+
+/// 传递给 [`kiomet_on_event`] 回调的事件，例如 `{"type": "info", "position": [x, y], "info": {...}}`。
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum KiometEvent {
+    /// 占领、损失、爆炸、失去国王等。
+    Info { position: [f32; 2], info: Info },
+    /// 收到了服务器的更新。
+    Tick,
+    /// 警报改变了。
+    Alerts { alerts: Alerts },
+    /// 玩家死亡。
+    Death { reason: Option<DeathReason> },
+}
+
+thread_local! {
+    /// 由 [`kiomet_on_event`] 注册的回调。
+    static KIOMET_EVENT_LISTENERS: std::cell::RefCell<Vec<js_sys::Function>> = Default::default();
+}
+
+/// 注册一个回调，每个 [`KiometEvent`] 都会调用它。
+#[wasm_bindgen(js_name = "kiomet_on_event")]
+pub fn kiomet_on_event(callback: js_sys::Function) {
+    KIOMET_EVENT_LISTENERS.with(|listeners| listeners.borrow_mut().push(callback));
+}
+
+/// 取消由 [`kiomet_on_event`] 注册的回调。如果找到了，返回 true。
+#[wasm_bindgen(js_name = "kiomet_off_event")]
+pub fn kiomet_off_event(callback: js_sys::Function) -> bool {
+    KIOMET_EVENT_LISTENERS.with(|listeners| {
+        let mut listeners = listeners.borrow_mut();
+        let len = listeners.len();
+        listeners.retain(|f| **f != *callback);
+        listeners.len() != len
+    })
+}
+
+fn emit_event(event: &KiometEvent) {
+    // 克隆，以便回调可以调用 `kiomet_off_event`。
+    let listeners = KIOMET_EVENT_LISTENERS.with(|listeners| listeners.borrow().clone());
+    if listeners.is_empty() {
+        return;
+    }
+    let Ok(event) = JsValue::from_serde(event) else {
+        return;
+    };
+    for listener in listeners {
+        if let Err(e) = listener.call1(&JsValue::NULL, &event) {
+            js_hooks::console_log(&format!("事件回调失败: {e:?}"));
+        }
     }
-    
-    // 使用JavaScript创建WebSocket连接
-    js_hooks::console_log(&format!("正在连接到服务器: {}", server_url));
-    js_hooks::eval(&format!(
-        `
-        try {{
-            // 存储当前服务器地址到全局变量
-            window.customServerAddress = '{}';
-            
-            // 尝试创建WebSocket连接
-            if (window.customWebSocket) {{
-                window.customWebSocket.close();
-            }}
-            
-            window.customWebSocket = new WebSocket('{}');
-            
-            window.customWebSocket.onopen = function() {{
-                console.log('已连接到自定义服务器');
-                alert('已成功连接到服务器！');
-            }};
-            
-            window.customWebSocket.onerror = function(error) {{
-                console.error('连接服务器失败:', error);
-                alert('连接服务器失败，请检查地址是否正确');
-            }};
-            
-            window.customWebSocket.onclose = function() {{
-                console.log('服务器连接已关闭');
-            }};
-            
-            window.customWebSocket.onmessage = function(event) {{
-                console.log('收到服务器消息:', event.data);
-                // 这里可以处理服务器消息
-            }};
-        }} catch(e) {{
-            console.error('创建WebSocket连接失败:', e);
-            alert('创建WebSocket连接失败: ' + e.message);
-        }}
-        `, 
-        server_url.replace('\'', "\\'"),
-        server_url.replace('\'', "\\'")
-    ));
-    
-    true
 }
 
-// 添加辅助方法来获取可变的ClientContext
 impl KiometGame {
-    fn get_context_mut(&mut self) -> Option<&mut ClientContext<Self>> {
-        // 由于架构限制，这里无法直接获取ClientContext
-        // 实际实现需要根据游戏架构调整
-        None
+    /// 替换 `kiomet_get_*` 函数读取的快照。
+    fn publish_snapshot(&self, context: &ClientContext<Self>) {
+        let game = &context.state.game;
+        let mut towers = Vec::new();
+        let mut forces = Vec::new();
+        let mut tower_counts = std::collections::BTreeMap::<PlayerId, u32>::new();
+
+        let mut force_info = |force: &Force| {
+            let id = forces.len() as u32;
+            forces.push(ForceInfo {
+                id,
+                player_id: force.player_id,
+                units: force.units.clone(),
+                source: force.source(),
+                destination: force.destination(),
+                position: force
+                    .interpolated_position(game.time_since_last_tick)
+                    .into(),
+                fuel: force.fuel,
+            });
+            id
+        };
+
+        for (tower_id, tower) in game.world.chunk.iter_towers() {
+            if let Some(player_id) = tower.player_id {
+                *tower_counts.entry(player_id).or_default() += 1;
+            }
+            towers.push(TowerInfo {
+                id: tower_id,
+                position: tower_id.as_vec2().into(),
+                tower_type: tower.tower_type,
+                player_id: tower.player_id,
+                units: tower.units.clone(),
+                inbound_forces: tower.inbound_forces.iter().map(&mut force_info).collect(),
+                outbound_forces: tower.outbound_forces.iter().map(&mut force_info).collect(),
+                supply_line: tower.supply_line.clone(),
+                active: tower.active(),
+                visible: game.visible.contains(tower_id),
+            });
+        }
+
+        let players = tower_counts
+            .into_iter()
+            .filter_map(|(player_id, tower_count)| {
+                let player = context.state.core.player_or_bot(player_id)?;
+                let mut allies: Vec<PlayerId> = game
+                    .world
+                    .player(player_id)
+                    .allies
+                    .iter()
+                    .copied()
+                    .collect();
+                allies.sort();
+                Some(PlayerInfo {
+                    id: player_id,
+                    alias: player.alias.as_str().to_owned(),
+                    authentic: player.authentic,
+                    allies,
+                    tower_count,
+                })
+            })
+            .collect();
+
+        let snapshot = KiometFullState {
+            alive: game.alive,
+            spectating: game.spectating,
+            death_reason: game.death_reason,
+            current_player_id: my_player_id(context),
+            players,
+            towers,
+            forces,
+            world_bounds: game
+                .bounding_rectangle
+                .is_valid()
+                .then_some(game.bounding_rectangle),
+            tight_viewport: game.tight_viewport,
+            margin_viewport: game.margin_viewport,
+            alerts: game.alerts,
+            camera: CameraInfo {
+                center: self.pan_zoom.get_center().into(),
+                zoom: self.pan_zoom.get_zoom(),
+            },
+            selected_tower_id: self.selected_tower_id,
+        };
+        KIOMET_SNAPSHOT.with(|cell| *cell.borrow_mut() = Some(snapshot));
+    }
+
+    /// 执行所有由 [`kiomet_do_action`] 排队的操作。
+    fn drain_actions(&mut self, context: &mut ClientContext<Self>) {
+        for action in KIOMET_ACTIONS.with(|queue| std::mem::take(&mut *queue.borrow_mut())) {
+            match action {
+                KiometAction::Command { command } => {
+                    self.send_to_game(command, context);
+                }
+                KiometAction::PanCamera { x, y, zoom } => {
+                    self.pan_zoom.pan_to(Vec2::new(x, y));
+                    if let Some(zoom) = zoom.filter(|z| z.is_finite() && *z > 0.0) {
+                        self.pan_zoom.multiply_zoom(
+                            self.pan_zoom.get_center(),
+                            zoom / self.pan_zoom.get_zoom(),
+                        );
+                    }
+                }
+                KiometAction::SelectTower { tower_id } => {
+                    if context.state.game.world.chunk.get(tower_id).is_some() {
+                        self.selected_tower_id = Some(tower_id);
+                    }
+                }
+                KiometAction::DeselectTower => {
+                    self.close_tower_menu();
+                }
+                KiometAction::SetServerAddress { url } => {
+                    self.set_server_address(url, context);
+                }
+                KiometAction::Reconnect => {
+                    self.connection.reconnect();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{spectator_click, towers_in_box_inner};
+    use common::protocol::Command;
+    use common::tower::{Tower, TowerId};
+    use kodiak_client::PlayerId;
+    use std::num::NonZeroU32;
+
+    #[test]
+    fn towers_in_box() {
+        let [me, other] = [1, 2].map(|i| PlayerId(NonZeroU32::new(i).unwrap()));
+        let towers: Vec<_> = (0..20)
+            .flat_map(|x| (0..20).map(move |y| TowerId::new(x, y)))
+            .map(|tower_id| {
+                let mut tower = Tower::new(tower_id);
+                let owner = if (tower_id.x + tower_id.y) % 3 == 0 {
+                    None
+                } else if tower_id.x < 10 {
+                    Some(me)
+                } else {
+                    Some(other)
+                };
+                tower.set_player_id(owner);
+                (tower_id, tower)
+            })
+            .collect();
+        let in_box = |a: TowerId, b: TowerId, me: Option<PlayerId>| {
+            let towers = towers.iter().map(|(tower_id, tower)| (*tower_id, tower));
+            towers_in_box_inner(a.as_vec2(), b.as_vec2(), me, towers)
+        };
+
+        let (a, b) = (TowerId::new(2, 3), TowerId::new(12, 6));
+        let selected = in_box(a, b, Some(me));
+        assert!(!selected.is_empty());
+        for (tower_id, tower) in &towers {
+            let position = tower_id.as_vec2();
+            let inside = position.cmpge(a.as_vec2().min(b.as_vec2())).all()
+                && position.cmple(a.as_vec2().max(b.as_vec2())).all();
+            assert_eq!(
+                selected.contains(tower_id),
+                inside && tower.player_id == Some(me),
+                "{tower_id:?}"
+            );
+        }
+
+        // Either corner can come first.
+        assert_eq!(in_box(b, a, Some(me)), selected);
+        // Corners are included.
+        assert_eq!(
+            in_box(TowerId::new(2, 5), TowerId::new(2, 5), Some(me)),
+            [TowerId::new(2, 5)].into_iter().collect()
+        );
+        // Nothing is selectable without a player id.
+        assert!(in_box(a, b, None).is_empty());
+        // Fits in one batch.
+        assert_eq!(
+            in_box(TowerId::new(0, 0), TowerId::new(19, 19), Some(me)).len(),
+            Command::MAX_BATCH_LEN
+        );
+    }
+
+    #[test]
+    fn spectator_click_() {
+        let [a, b] = [TowerId::new(1, 2), TowerId::new(3, 4)];
+        let click = |selected, closest| {
+            let selected = spectator_click(selected, closest, true);
+            spectator_click(selected, closest, false)
+        };
+
+        // Stays selected after the mouse goes up.
+        assert_eq!(click(None, Some(a)), Some(a));
+        assert_eq!(click(Some(a), Some(b)), Some(b));
+        // Clicking the same tower again deselects it.
+        assert_eq!(click(Some(a), Some(a)), None);
+        // Clicking nowhere deselects.
+        assert_eq!(click(Some(a), None), None);
+        assert_eq!(click(None, None), None);
     }
 }
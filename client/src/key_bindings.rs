@@ -0,0 +1,340 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use crate::game::KiometGame;
+use kodiak_client::{ClientContext, Key};
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+/// Something that can be bound to a key in [`KeyBindings`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum KeyAction {
+    PanLeft,
+    PanRight,
+    PanUp,
+    PanDown,
+    ZoomOut,
+    ZoomIn,
+    JumpToRuler,
+    /// Pans to each active alert in turn.
+    CycleAlerts,
+    ShowSupplyLines,
+    /// Of the selected tower, or all visible towers if none is selected.
+    ClearSupplyLines,
+    ShowSimilarTowers,
+    /// Cheat.
+    RevealMap,
+    /// Cheat.
+    UnboundedCamera,
+}
+
+impl KeyAction {
+    pub const ALL: [Self; 13] = [
+        Self::PanLeft,
+        Self::PanRight,
+        Self::PanUp,
+        Self::PanDown,
+        Self::ZoomOut,
+        Self::ZoomIn,
+        Self::JumpToRuler,
+        Self::CycleAlerts,
+        Self::ShowSupplyLines,
+        Self::ClearSupplyLines,
+        Self::ShowSimilarTowers,
+        Self::RevealMap,
+        Self::UnboundedCamera,
+    ];
+
+    /// Name used in [`TowerSettings`][`crate::settings::TowerSettings`].
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::PanLeft => "pan_left",
+            Self::PanRight => "pan_right",
+            Self::PanUp => "pan_up",
+            Self::PanDown => "pan_down",
+            Self::ZoomOut => "zoom_out",
+            Self::ZoomIn => "zoom_in",
+            Self::JumpToRuler => "jump_to_ruler",
+            Self::CycleAlerts => "cycle_alerts",
+            Self::ShowSupplyLines => "show_supply_lines",
+            Self::ClearSupplyLines => "clear_supply_lines",
+            Self::ShowSimilarTowers => "show_similar_towers",
+            Self::RevealMap => "reveal_map",
+            Self::UnboundedCamera => "unbounded_camera",
+        }
+    }
+
+    pub fn is_cheat(self) -> bool {
+        matches!(self, Self::RevealMap | Self::UnboundedCamera)
+    }
+
+    fn default_bindings(self) -> [Option<KeyBinding>; KeyBindings::SLOTS] {
+        let key = |key| Some(KeyBinding { key, shift: false });
+        match self {
+            Self::PanLeft => [key(Key::Left), key(Key::A)],
+            Self::PanRight => [key(Key::Right), key(Key::D)],
+            Self::PanUp => [key(Key::Up), key(Key::W)],
+            Self::PanDown => [key(Key::Down), key(Key::S)],
+            Self::ZoomOut => [key(Key::Q), None],
+            Self::ZoomIn => [key(Key::E), None],
+            Self::JumpToRuler => [key(Key::H), None],
+            Self::CycleAlerts => [key(Key::C), None],
+            Self::ShowSupplyLines => [key(Key::R), None],
+            Self::ClearSupplyLines => [
+                Some(KeyBinding {
+                    key: Key::R,
+                    shift: true,
+                }),
+                None,
+            ],
+            Self::ShowSimilarTowers => [key(Key::T), None],
+            Self::RevealMap => [key(Key::B), None],
+            Self::UnboundedCamera => [key(Key::N), None],
+        }
+    }
+}
+
+impl FromStr for KeyAction {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.into_iter().find(|a| a.name() == s).ok_or(())
+    }
+}
+
+/// A key, optionally combined with shift.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct KeyBinding {
+    pub key: Key,
+    /// If false, the binding works with or without shift.
+    pub shift: bool,
+}
+
+/// Keys that can be bound, with their names and DOM `KeyboardEvent.code`s.
+const KEYS: &[(Key, &str, &str)] = &[
+    (Key::A, "A", "KeyA"),
+    (Key::B, "B", "KeyB"),
+    (Key::C, "C", "KeyC"),
+    (Key::D, "D", "KeyD"),
+    (Key::E, "E", "KeyE"),
+    (Key::F, "F", "KeyF"),
+    (Key::G, "G", "KeyG"),
+    (Key::H, "H", "KeyH"),
+    (Key::I, "I", "KeyI"),
+    (Key::J, "J", "KeyJ"),
+    (Key::K, "K", "KeyK"),
+    (Key::L, "L", "KeyL"),
+    (Key::M, "M", "KeyM"),
+    (Key::N, "N", "KeyN"),
+    (Key::O, "O", "KeyO"),
+    (Key::P, "P", "KeyP"),
+    (Key::Q, "Q", "KeyQ"),
+    (Key::R, "R", "KeyR"),
+    (Key::S, "S", "KeyS"),
+    (Key::T, "T", "KeyT"),
+    (Key::U, "U", "KeyU"),
+    (Key::V, "V", "KeyV"),
+    (Key::W, "W", "KeyW"),
+    (Key::X, "X", "KeyX"),
+    (Key::Y, "Y", "KeyY"),
+    (Key::Z, "Z", "KeyZ"),
+    (Key::Left, "Left", "ArrowLeft"),
+    (Key::Right, "Right", "ArrowRight"),
+    (Key::Up, "Up", "ArrowUp"),
+    (Key::Down, "Down", "ArrowDown"),
+];
+
+impl KeyBinding {
+    /// Binding for a DOM `KeyboardEvent.code`, if the key can be bound.
+    pub fn from_code(code: &str, shift: bool) -> Option<Self> {
+        KEYS.iter()
+            .find(|(_, _, c)| *c == code)
+            .map(|&(key, _, _)| Self { key, shift })
+    }
+
+    pub fn is_down(self, context: &ClientContext<KiometGame>) -> bool {
+        context.keyboard.is_down(self.key) && (!self.shift || context.keyboard.is_down(Key::Shift))
+    }
+}
+
+impl Display for KeyBinding {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let name = KEYS
+            .iter()
+            .find(|(key, _, _)| *key == self.key)
+            .map_or("?", |(_, name, _)| name);
+        if self.shift {
+            write!(f, "Shift+{name}")
+        } else {
+            f.write_str(name)
+        }
+    }
+}
+
+impl FromStr for KeyBinding {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, shift) = s
+            .strip_prefix("Shift+")
+            .map_or((s, false), |name| (name, true));
+        KEYS.iter()
+            .find(|(_, n, _)| *n == name)
+            .map(|&(key, _, _)| Self { key, shift })
+            .ok_or(())
+    }
+}
+
+/// Up to [`Self::SLOTS`] keys for each [`KeyAction`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct KeyBindings {
+    bindings: [[Option<KeyBinding>; Self::SLOTS]; KeyAction::ALL.len()],
+}
+
+impl KeyBindings {
+    pub const SLOTS: usize = 2;
+
+    pub fn get(&self, action: KeyAction) -> [Option<KeyBinding>; Self::SLOTS] {
+        self.bindings[action as usize]
+    }
+
+    pub fn with(&self, action: KeyAction, slot: usize, binding: Option<KeyBinding>) -> Self {
+        let mut ret = self.clone();
+        ret.bindings[action as usize][slot] = binding;
+        ret
+    }
+
+    /// Returns true if any key bound to `action` is down.
+    pub fn is_down(&self, action: KeyAction, context: &ClientContext<KiometGame>) -> bool {
+        self.get(action)
+            .into_iter()
+            .flatten()
+            .any(|binding| binding.is_down(context))
+    }
+
+    /// Bindings shared by two different actions, in order.
+    pub fn conflicts(&self) -> Vec<(KeyBinding, KeyAction, KeyAction)> {
+        let mut ret = Vec::new();
+        for (i, &a) in KeyAction::ALL.iter().enumerate() {
+            for &b in &KeyAction::ALL[i + 1..] {
+                for binding in self.get(a).into_iter().flatten() {
+                    if self.get(b).contains(&Some(binding)) {
+                        ret.push((binding, a, b));
+                    }
+                }
+            }
+        }
+        ret
+    }
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            bindings: KeyAction::ALL.map(KeyAction::default_bindings),
+        }
+    }
+}
+
+/// Only stores actions that differ from the defaults, e.g. `pan_up=Up,Z;cycle_alerts=`.
+impl Display for KeyBindings {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let mut first = true;
+        for action in KeyAction::ALL {
+            let bindings = self.get(action);
+            if bindings == action.default_bindings() {
+                continue;
+            }
+            if !first {
+                f.write_str(";")?;
+            }
+            first = false;
+            write!(f, "{}=", action.name())?;
+            let mut first_binding = true;
+            for binding in bindings.into_iter().flatten() {
+                if !first_binding {
+                    f.write_str(",")?;
+                }
+                first_binding = false;
+                write!(f, "{binding}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for KeyBindings {
+    type Err = ();
+
+    /// Ignores unknown actions and keys, so settings survive renames.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut ret = Self::default();
+        for entry in s.split(';').filter(|e| !e.is_empty()) {
+            let Some((action, bindings)) = entry.split_once('=') else {
+                continue;
+            };
+            let Ok(action) = KeyAction::from_str(action) else {
+                continue;
+            };
+            let slots = &mut ret.bindings[action as usize];
+            *slots = [None; Self::SLOTS];
+            for (slot, binding) in slots.iter_mut().zip(
+                bindings
+                    .split(',')
+                    .filter_map(|b| KeyBinding::from_str(b).ok()),
+            ) {
+                *slot = Some(binding);
+            }
+        }
+        Ok(ret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::key_bindings::{KeyAction, KeyBinding, KeyBindings};
+    use kodiak_client::Key;
+    use std::str::FromStr;
+
+    #[test]
+    fn key_bindings() {
+        let defaults = KeyBindings::default();
+        assert_eq!(defaults.to_string(), "");
+        assert!(defaults.conflicts().is_empty());
+        assert_eq!(KeyBindings::from_str(""), Ok(defaults.clone()));
+
+        let z = Some(KeyBinding {
+            key: Key::Z,
+            shift: false,
+        });
+        let bindings = defaults
+            .with(KeyAction::PanUp, 1, z)
+            .with(KeyAction::CycleAlerts, 0, None);
+        let string = bindings.to_string();
+        assert_eq!(string, "pan_up=Up,Z;cycle_alerts=");
+        assert_eq!(KeyBindings::from_str(&string), Ok(bindings.clone()));
+        assert_eq!(
+            KeyBindings::from_str("bogus=Q;zoom_in=Shift+E,Nope"),
+            Ok(defaults.with(
+                KeyAction::ZoomIn,
+                0,
+                Some(KeyBinding {
+                    key: Key::E,
+                    shift: true
+                })
+            ))
+        );
+
+        let conflicting = bindings.with(KeyAction::ZoomIn, 0, z);
+        assert_eq!(
+            conflicting.conflicts(),
+            vec![(z.unwrap(), KeyAction::PanUp, KeyAction::ZoomIn)]
+        );
+
+        assert_eq!(
+            KeyBinding::from_code("KeyR", true).unwrap().to_string(),
+            "Shift+R"
+        );
+        assert_eq!(KeyBinding::from_code("F13", false), None);
+    }
+}
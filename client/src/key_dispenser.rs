@@ -1,7 +1,8 @@
 // SPDX-FileCopyrightText: 2024 Softbear, Inc.
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
-use crate::{game::KiometGame, settings::Unlocks};
+use crate::game::{my_player_id, KiometGame};
+use crate::settings::Unlocks;
 use common::tower::{Tower, TowerId};
 use kodiak_client::{ClientContext, RankNumber};
 
@@ -52,7 +53,7 @@ impl KeyDispenser {
         if self
             .key
             .and_then(|tower_id| context.state.game.world.chunk.get(tower_id))
-            .map(|tower| tower.player_id.is_some() && tower.player_id == context.player_id())
+            .map(|tower| tower.player_id.is_some() && tower.player_id == my_player_id(context))
             .unwrap_or(false)
         {
             self.key = None;
@@ -89,7 +90,7 @@ impl KeyDispenser {
         if !context.state.game.visible.contains(tower_id) {
             return false;
         }
-        let Some(player_id) = context.player_id() else {
+        let Some(player_id) = my_player_id(context) else {
             return false;
         };
         let Some(player) = context.state.game.world.player.get(player_id) else {
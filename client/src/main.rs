@@ -15,12 +15,16 @@ use kodiak_client::GameClient;
 mod animation;
 mod background;
 mod color;
+mod connection;
 mod finite_index;
 mod game;
+mod key_bindings;
 mod key_dispenser;
 mod layout;
+mod minimap;
 mod path;
 mod road;
+mod script;
 mod settings;
 mod state;
 mod territory;
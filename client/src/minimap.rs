@@ -0,0 +1,281 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use crate::color::Color;
+use crate::game::KiometGame;
+use common::tower::TowerId;
+use common::world::WorldChunks;
+use kodiak_client::glam::{vec2, IVec2, UVec2, Vec2, Vec3, Vec4};
+use kodiak_client::renderer::{
+    derive_vertex, include_shader, rgb_hex, DefaultRender, InstanceLayer, Layer, MeshBuilder,
+    RenderLayer, Renderer, Shader,
+};
+use kodiak_client::renderer2d::Camera2d;
+use kodiak_client::ClientContext;
+use std::collections::HashMap;
+
+/// Low resolution overview of the explored towers, drawn in a corner of the screen.
+#[derive(Default)]
+pub struct Minimap {
+    /// World space cell centers and colors, from [`Self::update`].
+    cells: Vec<(Vec2, Vec3)>,
+    /// World space size of each cell.
+    cell_size: f32,
+    /// Where it was last drawn, if at all.
+    view: Option<MinimapView>,
+}
+
+impl Minimap {
+    /// Cells along the longer side of the explored area.
+    const RESOLUTION: f32 = 64.0;
+
+    /// Buckets the known towers into cells (once per tick, since there may be many).
+    pub fn update(&mut self, context: &ClientContext<KiometGame>) {
+        self.cells.clear();
+        let Some((min, max)) = bounds(context) else {
+            return;
+        };
+        self.cell_size = (max - min).max_element() / Self::RESOLUTION;
+
+        // Towers the player should notice first win their cell.
+        fn priority(color: Color) -> u8 {
+            match color {
+                Color::Gray => 0,
+                Color::Purple => 1,
+                Color::Red => 2,
+                Color::Blue => 3,
+            }
+        }
+
+        let mut cells = HashMap::<IVec2, Color>::new();
+        for (tower_id, tower) in context.state.game.world.chunk.iter_towers() {
+            let position = tower_id.as_vec2();
+            if position.cmplt(min).any() || position.cmpgt(max).any() {
+                continue;
+            }
+            let cell = ((position - min) / self.cell_size).floor().as_ivec2();
+            let color = Color::new(context, tower.player_id);
+            cells
+                .entry(cell)
+                .and_modify(|c| {
+                    if priority(color) > priority(*c) {
+                        *c = color;
+                    }
+                })
+                .or_insert(color);
+        }
+        self.cells.extend(cells.into_iter().map(|(cell, color)| {
+            (
+                min + (cell.as_vec2() + 0.5) * self.cell_size,
+                rgb_hex(color.color_hex_rgb()),
+            )
+        }));
+    }
+
+    /// Draws the minimap, with the `camera` rectangle (world space corners) and ruler.
+    pub fn draw(
+        &mut self,
+        layer: &mut MinimapLayer,
+        canvas_size: UVec2,
+        camera: (Vec2, Vec2),
+        ruler_position: Option<TowerId>,
+        time: f32,
+        context: &ClientContext<KiometGame>,
+    ) {
+        self.view = bounds(context).and_then(|(min, max)| MinimapView::new(canvas_size, min, max));
+        let Some(view) = self.view else {
+            return;
+        };
+
+        // Background with a border of a few pixels.
+        let border = 4.0 / canvas_size.as_vec2();
+        layer.draw_rect(
+            view.view_min - border,
+            view.view_max + border,
+            Vec4::new(0.0, 0.0, 0.0, 0.6),
+        );
+
+        let cell_size = self.cell_size * view.scale;
+        for &(center, color) in &self.cells {
+            let center = view.to_view(center);
+            layer.draw_rect(
+                center - cell_size * 0.5,
+                center + cell_size * 0.5,
+                color.extend(0.9),
+            );
+        }
+
+        let (a, b) = camera;
+        let camera_min = view.clamp(view.to_view(a.min(b)));
+        let camera_max = view.clamp(view.to_view(a.max(b)));
+        let line = 2.0 / canvas_size.as_vec2();
+        let white = Vec4::new(1.0, 1.0, 1.0, 0.8);
+        layer.draw_rect(camera_min, vec2(camera_max.x, camera_min.y + line.y), white);
+        layer.draw_rect(vec2(camera_min.x, camera_max.y - line.y), camera_max, white);
+        layer.draw_rect(camera_min, vec2(camera_min.x + line.x, camera_max.y), white);
+        layer.draw_rect(vec2(camera_max.x - line.x, camera_min.y), camera_max, white);
+
+        if let Some(ruler_position) = ruler_position {
+            let center = view.to_view(ruler_position.as_vec2());
+            let size = cell_size.max(line * 2.0) * (1.5 + (time * 4.0).sin() * 0.25);
+            layer.draw_rect(center - size, center + size, Vec4::new(1.0, 0.85, 0.2, 1.0));
+        }
+    }
+
+    /// World position under `view_position` if it is over the minimap.
+    pub fn to_world(&self, view_position: Vec2) -> Option<Vec2> {
+        self.view?.to_world(view_position)
+    }
+}
+
+/// Explored area, or the whole world when spectating. [`None`] if there is nothing to show.
+fn bounds(context: &ClientContext<KiometGame>) -> Option<(Vec2, Vec2)> {
+    let game = &context.state.game;
+    if game.spectating {
+        Some((
+            Vec2::ZERO,
+            Vec2::splat(WorldChunks::SIZE as f32 * TowerId::CONVERSION as f32),
+        ))
+    } else if game.alive && game.bounding_rectangle.is_valid() {
+        Some((
+            game.bounding_rectangle.bottom_left.floor_position(),
+            game.bounding_rectangle.top_right.ceil_position(),
+        ))
+    } else {
+        None
+    }
+}
+
+/// Maps the minimap's world space rectangle to its place in view space (-1 to 1), in the bottom
+/// right corner above the buttons.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MinimapView {
+    world_min: Vec2,
+    view_min: Vec2,
+    view_max: Vec2,
+    /// View space units per world space unit.
+    scale: Vec2,
+}
+
+impl MinimapView {
+    /// Longer side, relative to the shorter side of the canvas.
+    const SIZE: f32 = 0.25;
+    /// Relative to the canvas width and height.
+    const MARGIN: Vec2 = Vec2::new(0.015, 0.1);
+
+    pub fn new(canvas_size: UVec2, world_min: Vec2, world_max: Vec2) -> Option<Self> {
+        let canvas = canvas_size.as_vec2();
+        let world_size = world_max - world_min;
+        if canvas.min_element() <= 0.0 || world_size.min_element() <= 0.0 {
+            return None;
+        }
+        let pixels_per_world = canvas.min_element() * Self::SIZE / world_size.max_element();
+        let scale = pixels_per_world * 2.0 / canvas;
+        let size = world_size * scale;
+        let view_max_x = 1.0 - Self::MARGIN.x * 2.0;
+        let view_min_y = -1.0 + Self::MARGIN.y * 2.0;
+        Some(Self {
+            world_min,
+            view_min: vec2(view_max_x - size.x, view_min_y),
+            view_max: vec2(view_max_x, view_min_y + size.y),
+            scale,
+        })
+    }
+
+    pub fn to_view(&self, world_position: Vec2) -> Vec2 {
+        self.view_min + (world_position - self.world_min) * self.scale
+    }
+
+    pub fn to_world(&self, view_position: Vec2) -> Option<Vec2> {
+        let inside =
+            view_position.cmpge(self.view_min).all() && view_position.cmple(self.view_max).all();
+        inside.then(|| self.world_min + (view_position - self.view_min) / self.scale)
+    }
+
+    fn clamp(&self, view_position: Vec2) -> Vec2 {
+        view_position.clamp(self.view_min, self.view_max)
+    }
+}
+
+derive_vertex!(
+    struct Instance {
+        center: Vec2,
+        scale: Vec2,
+        color: Vec4,
+    }
+);
+
+type MinimapInstanceLayer = InstanceLayer<Vec2, u16, Instance, ()>;
+
+/// Draws [`Minimap`]s as flat rectangles in view space, ignoring the camera.
+#[derive(Layer)]
+pub struct MinimapLayer {
+    #[layer]
+    instances: MinimapInstanceLayer,
+    shader: Shader,
+}
+
+impl RenderLayer<&Camera2d> for MinimapLayer {
+    fn render(&mut self, renderer: &Renderer, _: &Camera2d) {
+        if let Some(binding) = self.shader.bind(renderer) {
+            self.instances.render(renderer, &binding);
+        }
+    }
+}
+
+impl MinimapLayer {
+    pub fn new(renderer: &Renderer) -> Self {
+        Self {
+            instances: MinimapInstanceLayer::new(renderer),
+            shader: include_shader!(renderer, "minimap"),
+        }
+    }
+
+    fn draw_rect(&mut self, min: Vec2, max: Vec2, color: Vec4) {
+        let instance = Instance {
+            center: (min + max) * 0.5,
+            scale: max - min,
+            color,
+        };
+
+        self.instances.draw((), instance, || {
+            let mut mesh = MeshBuilder::new();
+            mesh.vertices.extend([
+                vec2(-0.5, -0.5), // bottom left
+                vec2(0.5, -0.5),  // bottom right
+                vec2(0.5, 0.5),   // top right
+                vec2(-0.5, 0.5),  // top left
+            ]);
+            mesh.push_default_quads();
+            mesh
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::minimap::MinimapView;
+    use kodiak_client::glam::{uvec2, vec2, Vec2};
+
+    #[test]
+    fn minimap_view() {
+        let view =
+            MinimapView::new(uvec2(1000, 500), vec2(100.0, 50.0), vec2(300.0, 150.0)).unwrap();
+
+        // Keeps the aspect ratio in pixels.
+        let size = view.view_max - view.view_min;
+        assert!(((size.x * 1000.0) / (size.y * 500.0) - 2.0).abs() < 1e-4);
+        assert!(view.view_min.cmpge(Vec2::splat(-1.0)).all());
+        assert!(view.view_max.cmple(Vec2::ONE).all());
+
+        let world = vec2(200.0, 75.0);
+        let round_trip = view.to_world(view.to_view(world)).unwrap();
+        assert!(round_trip.distance(world) < 1e-3);
+        assert_eq!(view.to_world(Vec2::ZERO), None);
+
+        assert_eq!(
+            MinimapView::new(uvec2(1000, 500), Vec2::ZERO, Vec2::ZERO),
+            None
+        );
+    }
+}
@@ -416,7 +416,7 @@ fn tower(tower_type: TowerType) -> Path {
         Artillery => artillery(),
         Barracks => barracks(),
         Bunker => bunker(),
-        //Capitol => capitol(),
+        Capitol => capitol(),
         Centrifuge => centrifuge(),
         City => city(),
         Cliff => cliff(),
@@ -425,10 +425,10 @@ fn tower(tower_type: TowerType) -> Path {
         Generator => generator(),
         Headquarters => headquarters(),
         Helipad => helipad(),
-        //Icbm => icbm(),
-        //Laser => laser(),
+        Icbm => icbm(),
+        Laser => laser(),
         Launcher => launcher(),
-        //Metropolis => metropolis(),
+        Metropolis => metropolis(),
         Mine => mine(),
         Projector => projector(),
         Quarry => quarry(),
@@ -447,10 +447,12 @@ fn tower(tower_type: TowerType) -> Path {
 
 fn unit(unit: Unit) -> Path {
     match unit {
+        Unit::Beam => beam(),
         Unit::Bomber => bomber(),
         Unit::Chopper => chopper(),
         Unit::Emp => emp(),
         Unit::Fighter => fighter(),
+        Unit::Missile => missile(),
         Unit::Nuke => nuke(),
         Unit::Ruler => ruler(),
         Unit::Shell => shell(),
@@ -591,7 +593,6 @@ fn bunker() -> Path {
     p.build()
 }
 
-#[allow(unused)]
 fn capitol() -> Path {
     let pt = offset_pt(1.0, Vec2::new(0.0, 0.075));
     let mut p = Path::builder();
@@ -649,7 +650,6 @@ fn centrifuge() -> Path {
     p.build()
 }
 
-#[allow(unused)]
 fn icbm() -> Path {
     let pt = offset_pt(1.25, Vec2::new(0.0, -0.15));
     let mut p = Path::builder();
@@ -663,7 +663,6 @@ fn icbm() -> Path {
     p.build()
 }
 
-#[allow(unused)]
 fn laser() -> Path {
     let mut p = Path::builder();
     p.begin(pt(0.3, 0.1));
@@ -779,7 +778,6 @@ fn factory() -> Path {
     p.build()
 }
 
-#[allow(unused)]
 fn metropolis() -> Path {
     let pt = offset_pt(2.0 / 3.0, Vec2::new(0.0, -0.04));
     let mut p = Path::builder();
@@ -1299,6 +1297,33 @@ fn nuke() -> Path {
     p.build()
 }
 
+fn missile() -> Path {
+    let mut p = Path::builder();
+    p.begin(pt(0.5, 0.95));
+    p.line_to(pt(0.6, 0.8));
+    p.line_to(pt(0.6, 0.3));
+    p.line_to(pt(0.7, 0.15));
+    p.line_to(pt(0.7, 0.05));
+    p.line_to(pt(0.6, 0.15));
+    p.line_to(pt(0.4, 0.15));
+    p.line_to(pt(0.3, 0.05));
+    p.line_to(pt(0.3, 0.15));
+    p.line_to(pt(0.4, 0.3));
+    p.line_to(pt(0.4, 0.8));
+    p.close();
+    p.build()
+}
+
+fn beam() -> Path {
+    let mut p = Path::builder();
+    p.begin(pt(0.5, 0.95));
+    p.line_to(pt(0.6, 0.5));
+    p.line_to(pt(0.5, 0.05));
+    p.line_to(pt(0.4, 0.5));
+    p.close();
+    p.build()
+}
+
 fn shell() -> Path {
     let mut p = Path::builder();
     p.begin(pt(0.35, 0.45));
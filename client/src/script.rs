@@ -0,0 +1,315 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use common::force::Path;
+use common::protocol::Command;
+use common::tower::{Tower, TowerId, TowerType};
+use kodiak_client::{js_hooks, PlayerId};
+use rhai::{Array, Dynamic, Engine, EvalAltResult, ImmutableString, Map, Scope, AST};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use std::str::FromStr;
+
+/// Runs a player's automation script (written in [Rhai](https://rhai.rs)) once per tick.
+///
+/// The script can query visible towers with `towers()`, `my_towers()`, `tower(x, y)` and
+/// `neighbors(x, y)`, and issue commands with `upgrade(x, y, type)`, `queue_upgrade(x, y, type)`,
+/// `deploy(path)`, `set_supply_line(path)` and `clear_supply_line(x, y)`. Towers are maps like
+/// `#{x: 10, y: 12, tower_type: "Barracks", player_id: 3, mine: true, active: true, units:
+/// #{Soldier: 5}, supply_line: false, inbound: 0, outbound: 0}`, and paths are arrays of them
+/// (or of `#{x, y}`).
+pub struct ScriptRunner {
+    engine: Engine,
+    state: Rc<RefCell<ScriptState>>,
+    /// Last compiled source and the result of compiling it.
+    compiled: Option<(String, Result<AST, String>)>,
+    /// Last compile or runtime error, shown in the script dialog.
+    error: Option<String>,
+}
+
+#[derive(Default)]
+struct ScriptState {
+    me: Option<PlayerId>,
+    towers: BTreeMap<TowerId, Map>,
+    commands: Vec<Command>,
+}
+
+impl ScriptRunner {
+    /// Limits how much work a script can do per tick.
+    const MAX_OPERATIONS: u64 = 50_000;
+
+    pub fn new() -> Self {
+        let state = Rc::new(RefCell::new(ScriptState::default()));
+        let mut engine = Engine::new();
+        engine
+            .set_max_operations(Self::MAX_OPERATIONS)
+            .set_max_expr_depths(32, 32)
+            .set_max_string_size(4096)
+            .set_max_array_size(4096)
+            .set_max_map_size(4096)
+            .on_print(|s| js_hooks::console_log(s));
+
+        let s = state.clone();
+        engine.register_fn("me", move || -> Dynamic {
+            s.borrow()
+                .me
+                .map_or(Dynamic::UNIT, |me| Dynamic::from_int(me.0.get() as i64))
+        });
+        let s = state.clone();
+        engine.register_fn("towers", move || -> Array {
+            s.borrow().towers.values().cloned().map(Dynamic::from).collect()
+        });
+        let s = state.clone();
+        engine.register_fn("my_towers", move || -> Array {
+            s.borrow()
+                .towers
+                .values()
+                .filter(|tower| tower.get("mine").and_then(|m| m.as_bool().ok()) == Some(true))
+                .cloned()
+                .map(Dynamic::from)
+                .collect()
+        });
+        let s = state.clone();
+        engine.register_fn("tower", move |x: i64, y: i64| -> Dynamic {
+            tower_id(x, y)
+                .and_then(|tower_id| s.borrow().towers.get(&tower_id).cloned())
+                .map_or(Dynamic::UNIT, Dynamic::from)
+        });
+        let s = state.clone();
+        engine.register_fn("neighbors", move |x: i64, y: i64| -> Array {
+            let state = s.borrow();
+            tower_id(x, y)
+                .into_iter()
+                .flat_map(TowerId::neighbors)
+                .filter_map(|neighbor| state.towers.get(&neighbor).cloned())
+                .map(Dynamic::from)
+                .collect()
+        });
+
+        let s = state.clone();
+        engine.register_fn("upgrade", move |x: i64, y: i64, tower_type: &str| -> bool {
+            let (Some(tower_id), Ok(tower_type)) = (tower_id(x, y), TowerType::from_str(tower_type))
+            else {
+                return false;
+            };
+            s.borrow_mut().issue(Command::Upgrade {
+                tower_id,
+                tower_type,
+            })
+        });
+        let s = state.clone();
+        engine.register_fn(
+            "queue_upgrade",
+            move |x: i64, y: i64, tower_type: &str| -> bool {
+                let (Some(tower_id), Ok(tower_type)) =
+                    (tower_id(x, y), TowerType::from_str(tower_type))
+                else {
+                    return false;
+                };
+                s.borrow_mut().issue(Command::QueueUpgrade {
+                    tower_id,
+                    tower_type: Some(tower_type),
+                })
+            },
+        );
+        let s = state.clone();
+        engine.register_fn("deploy", move |path: Array| -> bool {
+            let Some(path) = path_from_array(path) else {
+                return false;
+            };
+            s.borrow_mut().issue(Command::DeployForce {
+                tower_id: path.source(),
+                path,
+            })
+        });
+        let s = state.clone();
+        engine.register_fn("set_supply_line", move |path: Array| -> bool {
+            let Some(path) = path_from_array(path) else {
+                return false;
+            };
+            s.borrow_mut().issue(Command::SetSupplyLine {
+                tower_id: path.source(),
+                path: Some(path),
+            })
+        });
+        let s = state.clone();
+        engine.register_fn("clear_supply_line", move |x: i64, y: i64| -> bool {
+            let Some(tower_id) = tower_id(x, y) else {
+                return false;
+            };
+            s.borrow_mut()
+                .issue(Command::SetSupplyLine { tower_id, path: None })
+        });
+
+        Self {
+            engine,
+            state,
+            compiled: None,
+            error: None,
+        }
+    }
+
+    pub fn error(&self) -> Option<&str> {
+        self.error.as_deref()
+    }
+
+    /// Runs `source` (recompiling it if it changed) against the visible `towers`. Returns the
+    /// commands it issued, as a single [`Command::Batch`] if necessary.
+    pub fn run<'a>(
+        &mut self,
+        source: &str,
+        towers: impl Iterator<Item = (TowerId, &'a Tower)>,
+        me: Option<PlayerId>,
+    ) -> Option<Command> {
+        if self.compiled.as_ref().map_or(true, |(s, _)| s != source) {
+            let ast = self.engine.compile(source).map_err(|e| e.to_string());
+            self.compiled = Some((source.to_owned(), ast));
+        }
+        let ast = match &self.compiled.as_ref().unwrap().1 {
+            Ok(ast) => ast,
+            Err(e) => {
+                self.error = Some(e.clone());
+                return None;
+            }
+        };
+
+        {
+            let mut state = self.state.borrow_mut();
+            state.me = me;
+            state.towers = towers
+                .map(|(tower_id, tower)| (tower_id, tower_map(tower_id, tower, me)))
+                .collect();
+            state.commands.clear();
+        }
+
+        let result: Result<(), Box<EvalAltResult>> =
+            self.engine.run_ast_with_scope(&mut Scope::new(), ast);
+        self.error = result.err().map(|e| e.to_string());
+
+        let mut state = self.state.borrow_mut();
+        state.towers.clear();
+        let commands = std::mem::take(&mut state.commands);
+        // Don't act on a partial run.
+        self.error.is_none().then(|| Command::batch(commands)).flatten()
+    }
+}
+
+impl Default for ScriptRunner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScriptState {
+    /// Returns false if the script already issued too many commands this tick.
+    fn issue(&mut self, command: Command) -> bool {
+        if self.commands.len() >= Command::MAX_BATCH_LEN {
+            return false;
+        }
+        self.commands.push(command);
+        true
+    }
+}
+
+fn tower_id(x: i64, y: i64) -> Option<TowerId> {
+    Some(TowerId::new(x.try_into().ok()?, y.try_into().ok()?))
+}
+
+/// Accepts tower maps or any map with `x` and `y`.
+fn path_from_array(path: Array) -> Option<Path> {
+    let tower_ids = path
+        .into_iter()
+        .map(|item| {
+            let map = item.try_cast::<Map>()?;
+            let x = map.get("x")?.as_int().ok()?;
+            let y = map.get("y")?.as_int().ok()?;
+            tower_id(x, y)
+        })
+        .collect::<Option<Vec<_>>>()?;
+    (tower_ids.len() >= 2).then(|| Path::new(tower_ids))
+}
+
+fn tower_map(tower_id: TowerId, tower: &Tower, me: Option<PlayerId>) -> Map {
+    let units: Map = tower
+        .units
+        .iter()
+        .map(|(unit, count)| (format!("{unit:?}").into(), Dynamic::from_int(count as i64)))
+        .collect();
+    let player_id = tower
+        .player_id
+        .map_or(Dynamic::UNIT, |p| Dynamic::from_int(p.0.get() as i64));
+
+    let mut map = Map::new();
+    map.insert("x".into(), Dynamic::from_int(tower_id.x as i64));
+    map.insert("y".into(), Dynamic::from_int(tower_id.y as i64));
+    map.insert(
+        "tower_type".into(),
+        Dynamic::from(ImmutableString::from(format!("{:?}", tower.tower_type))),
+    );
+    map.insert("player_id".into(), player_id);
+    map.insert(
+        "mine".into(),
+        Dynamic::from_bool(me.is_some() && tower.player_id == me),
+    );
+    map.insert("active".into(), Dynamic::from_bool(tower.active()));
+    map.insert("units".into(), Dynamic::from_map(units));
+    map.insert(
+        "supply_line".into(),
+        Dynamic::from_bool(tower.supply_line.is_some()),
+    );
+    map.insert(
+        "inbound".into(),
+        Dynamic::from_int(tower.inbound_forces.len() as i64),
+    );
+    map.insert(
+        "outbound".into(),
+        Dynamic::from_int(tower.outbound_forces.len() as i64),
+    );
+    map
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::script::ScriptRunner;
+    use common::protocol::Command;
+    use common::tower::{Tower, TowerId, TowerType};
+    use kodiak_client::PlayerId;
+    use std::num::NonZeroU32;
+
+    #[test]
+    fn script_runner() {
+        let me = PlayerId(NonZeroU32::new(1).unwrap());
+        let mut mine = Tower::with_type(TowerType::Village);
+        mine.set_player_id(Some(me));
+        let theirs = Tower::with_type(TowerType::Village);
+        let towers = [(TowerId::new(1, 1), &mine), (TowerId::new(2, 1), &theirs)];
+
+        let mut runner = ScriptRunner::new();
+        let command = runner.run(
+            r#"
+            for t in my_towers() {
+                upgrade(t.x, t.y, "Barracks");
+            }
+            "#,
+            towers.into_iter(),
+            Some(me),
+        );
+        assert_eq!(runner.error(), None);
+        assert!(matches!(
+            command,
+            Some(Command::Upgrade {
+                tower_id,
+                tower_type: TowerType::Barracks
+            }) if tower_id == TowerId::new(1, 1)
+        ));
+
+        // Runaway scripts are stopped.
+        assert!(runner.run("loop {}", towers.into_iter(), Some(me)).is_none());
+        assert!(runner.error().is_some());
+
+        // Syntax errors are reported.
+        assert!(runner.run("upgrade(", towers.into_iter(), Some(me)).is_none());
+        assert!(runner.error().is_some());
+    }
+}
@@ -1,10 +1,12 @@
 // SPDX-FileCopyrightText: 2024 Softbear, Inc.
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
+use crate::key_bindings::KeyBindings;
 use common::tower::TowerType;
 use kodiak_client::{
     settings_prerequisites, BrowserStorages, SettingCategory, Settings, Translator,
 };
+use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use std::fmt::{self, Display, Formatter, Write};
 use std::str::FromStr;
@@ -13,6 +15,13 @@ use std::str::FromStr;
 pub struct TowerSettings {
     pub(crate) unlocks: Unlocks,
     pub(crate) server_address: Option<String>, //服务器地址输入框
+    /// Last server that completed a handshake.
+    pub(crate) last_connected_server: Option<String>,
+    pub(crate) servers: SavedServers,
+    /// Automation script, see [`ScriptRunner`][`crate::script::ScriptRunner`].
+    pub(crate) script: Option<String>,
+    pub(crate) script_enabled: bool,
+    pub(crate) key_bindings: KeyBindings,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -87,3 +96,102 @@ impl FromStr for Unlocks {
         Ok(ret)
     }
 }
+
+/// Custom servers saved in the server browser, stored as JSON.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SavedServers {
+    pub servers: Vec<SavedServer>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedServer {
+    pub name: String,
+    pub url: String,
+}
+
+impl SavedServers {
+    pub const MAX: usize = 16;
+    pub const MAX_NAME_LEN: usize = 32;
+
+    pub fn get(&self, url: &str) -> Option<&SavedServer> {
+        self.servers.iter().find(|s| s.url == url)
+    }
+
+    /// Adds or renames the server at `url`. Returns [`None`] if full.
+    pub fn insert(&self, name: &str, url: &str) -> Option<Self> {
+        let name: String = name.trim().chars().take(Self::MAX_NAME_LEN).collect();
+        let name = if name.is_empty() {
+            url.to_owned()
+        } else {
+            name
+        };
+        let mut ret = self.clone();
+        if let Some(server) = ret.servers.iter_mut().find(|s| s.url == url) {
+            server.name = name;
+        } else if ret.servers.len() < Self::MAX {
+            ret.servers.push(SavedServer {
+                name,
+                url: url.to_owned(),
+            });
+        } else {
+            return None;
+        }
+        Some(ret)
+    }
+
+    pub fn remove(&self, url: &str) -> Self {
+        let mut ret = self.clone();
+        ret.servers.retain(|s| s.url != url);
+        ret
+    }
+}
+
+impl Display for SavedServers {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str(&serde_json::to_string(self).map_err(|_| fmt::Error)?)
+    }
+}
+
+impl FromStr for SavedServers {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut ret: Self = serde_json::from_str(s).map_err(|_| ())?;
+        ret.servers.truncate(Self::MAX);
+        Ok(ret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::settings::SavedServers;
+
+    #[test]
+    fn saved_servers() {
+        let servers = SavedServers::default()
+            .insert(" Team ", "wss://a.example.com")
+            .unwrap()
+            .insert("", "wss://b.example.com")
+            .unwrap();
+        assert_eq!(servers.get("wss://a.example.com").unwrap().name, "Team");
+        assert_eq!(
+            servers.get("wss://b.example.com").unwrap().name,
+            "wss://b.example.com"
+        );
+
+        let renamed = servers.insert("Other", "wss://a.example.com").unwrap();
+        assert_eq!(renamed.servers.len(), 2);
+        assert_eq!(renamed.servers[0].name, "Other");
+
+        let string = servers.to_string();
+        assert_eq!(string.parse::<SavedServers>(), Ok(servers.clone()));
+        assert_eq!(servers.remove("wss://a.example.com").servers.len(), 1);
+
+        let full = (0..SavedServers::MAX).fold(SavedServers::default(), |servers, i| {
+            servers.insert("", &format!("wss://{i}.example.com")).unwrap()
+        });
+        assert!(full.insert("", "wss://new.example.com").is_none());
+        assert!(full.insert("Renamed", "wss://0.example.com").is_some());
+    }
+}
@@ -0,0 +1,10 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+precision mediump float;
+varying vec4 vColor;
+
+void main() {
+    // Premultiply alpha.
+    gl_FragColor = vec4(vColor.rgb * vColor.a, vColor.a);
+}
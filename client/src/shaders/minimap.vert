@@ -0,0 +1,14 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+attribute vec2 position;
+attribute vec2 center;
+attribute vec2 scale;
+attribute vec4 color;
+varying vec4 vColor;
+
+void main() {
+    vColor = color;
+    // Already in view space, independent of the camera.
+    gl_Position = vec4(position * scale + center, 0.0, 1.0);
+}
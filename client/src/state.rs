@@ -8,7 +8,7 @@ use common::protocol::{NonActor, Update};
 use common::ticks::Ticks;
 use common::tower::TowerRectangle;
 use common::world::{ApplyOwned, World};
-use kodiak_client::Apply;
+use kodiak_client::{Apply, PlayerId};
 use std::ops::Deref;
 
 #[derive(Default)]
@@ -23,18 +23,48 @@ pub struct TowerState {
     pub margin_viewport: TowerRectangle,
     pub tight_viewport: TowerRectangle,
     pub set_viewport: ChunkRectangle,
+    /// While set, [`Update`]s come from the custom server (see [`TowerState::apply_custom`]) and
+    /// those from the default transport are ignored.
+    custom_server: bool,
+    /// Assigned by the custom server.
+    custom_player_id: Option<PlayerId>,
 }
 
-impl Deref for TowerState {
-    type Target = NonActor;
+impl TowerState {
+    /// Switches between the custom server and the default transport, starting over with an empty
+    /// world if it changed.
+    pub fn set_custom_server(&mut self, custom_server: bool) {
+        if self.custom_server != custom_server {
+            *self = Self {
+                custom_server,
+                ..Self::default()
+            };
+        }
+    }
 
-    fn deref(&self) -> &Self::Target {
-        &self.non_actor
+    /// Starts over with an empty world for a new session on the custom server.
+    pub fn connected_to_custom_server(&mut self, player_id: Option<PlayerId>) {
+        *self = Self {
+            custom_server: true,
+            custom_player_id: player_id,
+            ..Self::default()
+        };
     }
-}
 
-impl Apply<Update> for TowerState {
-    fn apply(&mut self, update: Update) {
+    /// Returns the player id assigned by the custom server, or [`None`] if using the default
+    /// transport.
+    pub fn custom_player_id(&self) -> Option<Option<PlayerId>> {
+        self.custom_server.then_some(self.custom_player_id)
+    }
+
+    /// Like [`Apply::apply`], for [`Update`]s from the custom server.
+    pub fn apply_custom(&mut self, update: Update) {
+        if self.custom_server {
+            self.apply_update(update);
+        }
+    }
+
+    fn apply_update(&mut self, update: Update) {
         self.non_actor = update.non_actor;
 
         let mut on_info_event = |info_event| {
@@ -59,3 +89,19 @@ impl Apply<Update> for TowerState {
         self.ticked = true;
     }
 }
+
+impl Deref for TowerState {
+    type Target = NonActor;
+
+    fn deref(&self) -> &Self::Target {
+        &self.non_actor
+    }
+}
+
+impl Apply<Update> for TowerState {
+    fn apply(&mut self, update: Update) {
+        if !self.custom_server {
+            self.apply_update(update);
+        }
+    }
+}
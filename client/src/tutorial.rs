@@ -1,7 +1,7 @@
 // SPDX-FileCopyrightText: 2024 Softbear, Inc.
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
-use crate::game::is_visible;
+use crate::game::{is_visible, my_player_id};
 use crate::path::{PathId, PathLayer};
 use crate::KiometGame;
 use common::alerts::AlertFlag;
@@ -255,7 +255,7 @@ mod deployment {
 
     /// Is ours and has at least 1 useful unit.
     fn filter_deployment_src(context: &ClientContext<KiometGame>, src: &Tower) -> bool {
-        src.player_id == context.state.core.player_id
+        src.player_id == my_player_id(context)
             && !src.units.has_ruler()
             && src.units.iter().any(|(unit, _)| unit.can_capture())
     }
@@ -294,7 +294,7 @@ mod deployment {
                                 .chunk
                                 .get(tower_id)
                                 .map(|tower| {
-                                    tower.player_id == context.state.core.player_id
+                                    tower.player_id == my_player_id(context)
                                         || filter_deployment_dst(tower)
                                 })
                                 .unwrap_or(false))
@@ -348,7 +348,7 @@ mod upgrade {
             let tower = context.state.game.world.chunk.get(tower_id)?;
             iter_tower_upgrades(context, tower_id, tower)
                 .next()
-                .filter(|_| tower.player_id == context.player_id())
+                .filter(|_| tower.player_id == my_player_id(context))
         })()
         .is_some()
     }
@@ -374,7 +374,7 @@ mod upgrade {
             .world
             .chunk
             .iter_towers()
-            .filter(|(_, tower)| tower.player_id == context.state.core.player_id)
+            .filter(|(_, tower)| tower.player_id == my_player_id(context))
             .flat_map(|(id, t)| iter_tower_upgrades(context, id, t))
     }
 }
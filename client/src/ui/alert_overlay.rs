@@ -6,7 +6,7 @@ use crate::ui::{KiometPhrases, KiometUiEvent};
 use crate::KiometGame;
 use common::alerts::{AlertFlag, Alerts};
 use common::tower::TowerId;
-use kodiak_client::{use_translator, use_ui_event_callback};
+use kodiak_client::{use_core_state, use_translator, use_ui_event_callback};
 use stylist::yew::styled_component;
 use yew::virtual_dom::AttrValue;
 use yew::{classes, hook, html, use_state, Callback, Html, MouseEvent, Properties, UseStateHandle};
@@ -23,7 +23,7 @@ pub struct AlertOverlayProps {
 pub fn alert_overlay(props: &AlertOverlayProps) -> Html {
     let send_event = use_ui_event_callback::<KiometGame>();
     let send_event_factory =
-        |event: KiometUiEvent| -> Callback<MouseEvent> { send_event.reform(move |_| event) };
+        |event: KiometUiEvent| -> Callback<MouseEvent> { send_event.reform(move |_| event.clone()) };
 
     let pan_to = send_event.reform(KiometUiEvent::PanTo);
     let pan_to_factory =
@@ -52,6 +52,20 @@ pub fn alert_overlay(props: &AlertOverlayProps) -> Html {
     let (show_full, dismiss_full) = use_dismissible();
     let (show_overflowing, dismiss_overflowing) = use_dismissible();
     let (show_zombies, dismiss_zombies) = use_dismissible();
+    let (show_supply_line_halted, dismiss_supply_line_halted) = use_dismissible();
+    let (show_emped, dismiss_emped) = use_dismissible();
+    let (show_alliance_request, dismiss_alliance_request) = use_dismissible();
+
+    let core_state = use_core_state();
+    let alliance_request = props
+        .alerts
+        .alliance_request
+        .filter(|_| *show_alliance_request)
+        .and_then(|player_id| {
+            core_state
+                .player_or_bot(player_id)
+                .map(|p| (player_id, p.alias))
+        });
 
     let t = use_translator();
 
@@ -91,6 +105,49 @@ pub fn alert_overlay(props: &AlertOverlayProps) -> Html {
                     onclick_dismiss={dismiss_ruler_not_safe}
                 />
             }
+            if let Some(tower_id) = props.alerts.incoming_nuke {
+                <Alert
+                    instruction={t.alert_incoming_nuke_warning()}
+                    hint={t.alert_incoming_nuke_hint()}
+                    icon_id={IconId::FontAwesomeSolidRadiation}
+                    onclick={pan_to_factory(tower_id)}
+                />
+            }
+            if let Some(tower_id) = props.alerts.incoming_emp {
+                <Alert
+                    instruction={t.alert_incoming_emp_warning()}
+                    hint={t.alert_incoming_emp_hint()}
+                    icon_id={IconId::FontAwesomeSolidBolt}
+                    onclick={pan_to_factory(tower_id)}
+                />
+            }
+            if let Some(tower_id) = props.alerts.emped.filter(|_| *show_emped) {
+                <Alert
+                    instruction={t.alert_emped_warning()}
+                    hint={t.alert_emped_hint()}
+                    icon_id={IconId::FontAwesomeSolidPowerOff}
+                    onclick={pan_to_factory(tower_id)}
+                    onclick_dismiss={dismiss_emped}
+                />
+            }
+            if let Some(tower_id) = props.alerts.supply_line_halted.filter(|_| *show_supply_line_halted) {
+                <Alert
+                    instruction={t.alert_supply_line_halted_warning()}
+                    hint={t.alert_supply_line_halted_hint()}
+                    icon_id={IconId::FontAwesomeSolidLinkSlash}
+                    onclick={pan_to_factory(tower_id)}
+                    onclick_dismiss={dismiss_supply_line_halted}
+                />
+            }
+            if let Some((player_id, alias)) = alliance_request {
+                <Alert
+                    instruction={t.alert_alliance_request_warning(&alias)}
+                    hint={t.alert_alliance_request_hint()}
+                    icon_id={IconId::FontAwesomeSolidHandshake}
+                    onclick={send_event_factory(KiometUiEvent::Alliance{with: player_id, break_alliance: false})}
+                    onclick_dismiss={dismiss_alliance_request}
+                />
+            }
             if let Some(tower_id) = props.alerts.full.filter(|_| *show_full) {
                 <Alert
                     instruction={t.alert_full_warning()}
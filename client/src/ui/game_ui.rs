@@ -2,16 +2,22 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
 use crate::color::Color;
+use crate::connection::ConnectionState;
+use crate::key_bindings::KeyBindings;
 use crate::path::{PathId, SvgCache};
 use crate::settings::Unlocks;
 use crate::tutorial::TutorialAlert;
 use crate::ui::about_dialog::AboutDialog;
 use crate::ui::alert_overlay::AlertOverlay;
+use crate::ui::button::Button;
 use crate::ui::help_dialog::HelpDialog;
+use crate::ui::key_bindings_dialog::KeyBindingsDialog;
 use crate::ui::lock_dialog::LockDialog;
+use crate::ui::script_dialog::ScriptDialog;
 use crate::ui::tower_overlay::TowerOverlay;
 use crate::ui::towers_dialog::TowersDialog;
 use crate::ui::units_dialog::UnitsDialog;
+use crate::ui::upgrade_all_dialog::{UpgradeAllDialog, UpgradeCandidates};
 use crate::ui::KiometPhrases;
 use crate::KiometGame;
 use common::alerts::Alerts;
@@ -21,9 +27,9 @@ use common::unit::Unit;
 use kodiak_client::glam::IVec2;
 use kodiak_client::{
     splash_links, splash_nexus_icons, splash_sign_in_link, splash_social_media, translate, use_ctw,
-    use_translator, use_ui_event_callback, Align, ChatOverlay, GameClient, LeaderboardOverlay,
-    PathParam, PlayerAlias, PlayerId, Position, Positioner, PropertiesWrapper, RoutableExt,
-    SmolRoutable, SpawnOverlay, SplashSocialMediaProps, Translator, SPLASH_MARGIN,
+    use_settings, use_translator, use_ui_event_callback, Align, ChatOverlay, GameClient,
+    LeaderboardOverlay, PathParam, PlayerAlias, PlayerId, Position, Positioner, PropertiesWrapper,
+    RoutableExt, SmolRoutable, SpawnOverlay, SplashSocialMediaProps, Translator, SPLASH_MARGIN,
 };
 use std::fmt::Debug;
 use stylist::yew::styled_component;
@@ -31,7 +37,7 @@ use yew::prelude::*;
 use yew::virtual_dom::AttrValue;
 use yew_router::prelude::*;
 
-#[derive(Copy, Clone)]
+#[derive(Clone)]
 pub enum KiometUiEvent {
     Alliance {
         with: PlayerId,
@@ -40,18 +46,54 @@ pub enum KiometUiEvent {
     DismissCaptureTutorial,
     DismissUpgradeTutorial,
     PanTo(TowerId),
+    QueueUpgrade {
+        tower_id: TowerId,
+        tower_type: Option<TowerType>,
+    },
     Spawn(PlayerAlias),
+    Spectate(bool),
+    /// Gives up, see [`Command::Surrender`][`common::protocol::Command::Surrender`].
+    Surrender,
     Upgrade {
         tower_id: TowerId,
         tower_type: TowerType,
     },
+    /// Upgrades as many own towers as possible (up to a batch) to `tower_type`.
+    UpgradeAll {
+        tower_type: TowerType,
+        /// Only towers in the viewport.
+        in_viewport: bool,
+    },
     Unlock(TowerType),
     LockDialog(Option<TowerType>),
+    /// Opens (or closes if [`None`]) the dialog that sends [`KiometUiEvent::UpgradeAll`].
+    UpgradeAllDialog(Option<TowerType>),
+    /// Saves the custom server address ([`None`] for the default server).
+    SetServerAddress(Option<String>),
+    /// Reconnects to the custom server.
+    Reconnect,
+    /// Adds (or renames) a server in the server browser.
+    SaveServer {
+        name: String,
+        url: String,
+    },
+    /// Removes a server from the server browser, by URL.
+    RemoveServer(String),
+    ScriptDialog(bool),
+    /// Saves the automation script, see [`ScriptRunner`][`crate::script::ScriptRunner`].
+    SetScript {
+        source: String,
+        enabled: bool,
+    },
+    KeyBindingsDialog(bool),
+    /// Saves the key bindings and closes the dialog.
+    SetKeyBindings(KeyBindings),
 }
 
 #[derive(Clone, PartialEq, Default)]
 pub struct KiometUiProps {
     pub alive: bool,
+    pub spectating: bool,
     pub death_reason: Option<DeathReason>,
     pub selected_tower: Option<SelectedTower>,
     pub tower_counts: TowerArray<u16>,
@@ -59,6 +101,15 @@ pub struct KiometUiProps {
     pub tutorial_alert: Option<TutorialAlert>,
     pub unlocks: Unlocks,
     pub lock_dialog: Option<TowerType>,
+    pub upgrade_all_dialog: Option<TowerType>,
+    /// For [`Self::upgrade_all_dialog`], updated every tick while it is open.
+    pub upgrade_candidates: UpgradeCandidates,
+    /// Custom server connection.
+    pub connection: ConnectionState,
+    pub script_dialog: bool,
+    /// Last compile or runtime error of the automation script.
+    pub script_error: Option<String>,
+    pub key_bindings_dialog: bool,
 }
 
 #[derive(Clone, PartialEq, Debug)]
@@ -73,6 +124,10 @@ pub struct SelectedTower {
     pub tower_id: TowerId,
     /// If we are trying to ally with them or already allied with them.
     pub outgoing_alliance: bool,
+    /// If the tower could be upgraded to a large tower.
+    pub has_room_for_large: bool,
+    /// Target of a [`KiometUiEvent::QueueUpgrade`], if any.
+    pub queued_upgrade: Option<TowerType>,
 }
 
 #[styled_component(KiometUi)]
@@ -82,16 +137,8 @@ pub fn kiomet_ui(props: &PropertiesWrapper<KiometUiProps>) -> Html {
     // 创建一个新的on_play回调，检查是否有服务器地址
     let on_play = {
         let callback = ui_event_callback.clone();
+        let has_address = use_settings::<KiometGame>().server_address.is_some();
         Callback::from(move |alias: PlayerAlias| {
-            // 检查是否有服务器地址
-            let has_address = web_sys::window()
-                .and_then(|window| window.local_storage().ok())
-                .flatten()
-                .and_then(|storage| storage.get_item("kiomet_server_address").ok())
-                .flatten()
-                .map(|addr| !addr.is_empty())
-                .unwrap_or(false);
-            
             if has_address {
                 // 如果有地址，正常处理Spawn事件
                 callback.emit(KiometUiEvent::Spawn(alias));
@@ -104,6 +151,17 @@ pub fn kiomet_ui(props: &PropertiesWrapper<KiometUiProps>) -> Html {
         })
     };
 
+    let on_spectate_factory = {
+        let ui_event_callback = ui_event_callback.clone();
+        move |spectate: bool| -> Callback<MouseEvent> {
+            ui_event_callback.reform(move |_| KiometUiEvent::Spectate(spectate))
+        }
+    };
+
+    let on_script_dialog = ui_event_callback.reform(|_| KiometUiEvent::ScriptDialog(true));
+    let on_key_bindings_dialog =
+        ui_event_callback.reform(|_| KiometUiEvent::KeyBindingsDialog(true));
+
     let header_css = css!(
         r#"
         color: white;
@@ -146,6 +204,21 @@ pub fn kiomet_ui(props: &PropertiesWrapper<KiometUiProps>) -> Html {
 
     let ctw = use_ctw();
     let t = use_translator();
+
+    let on_surrender = {
+        let ui_event_callback = ui_event_callback.clone();
+        let confirm = t.surrender_confirm();
+        Callback::from(move |_: MouseEvent| {
+            // Can't be undone, so make sure it wasn't a misclick.
+            let confirmed = web_sys::window()
+                .and_then(|window| window.confirm_with_message(&confirm).ok())
+                .unwrap_or(false);
+            if confirmed {
+                ui_event_callback.emit(KiometUiEvent::Surrender);
+            }
+        })
+    };
+
     let nexus = ctw.escaping.is_escaping();
     let social_media_props = SplashSocialMediaProps::default()
         .github("https://github.com/SoftbearStudios/kiomet")
@@ -154,13 +227,16 @@ pub fn kiomet_ui(props: &PropertiesWrapper<KiometUiProps>) -> Html {
     html! {
         <>
             if props.alive && !nexus {
-                if let Some(SelectedTower{client_position, color, tower, tower_id, outgoing_alliance}) = props.selected_tower.clone() {
+                if let Some(SelectedTower{client_position, color, tower, tower_id, outgoing_alliance, has_room_for_large, queued_upgrade}) = props.selected_tower.clone() {
                     <TowerOverlay
                         {client_position}
                         {color}
                         {tower}
                         {tower_id}
                         {outgoing_alliance}
+                        {has_room_for_large}
+                        {queued_upgrade}
+                        spectating={false}
                         tower_counts={props.tower_counts}
                         tutorial_alert={props.tutorial_alert}
                         unlocks={props.unlocks.clone()}
@@ -173,6 +249,45 @@ pub fn kiomet_ui(props: &PropertiesWrapper<KiometUiProps>) -> Html {
                 if let Some(tower_type) = props.lock_dialog {
                     <LockDialog keys={props.unlocks.keys} {tower_type}/>
                 }
+                <Positioner position={Position::BottomRight{margin: SPLASH_MARGIN}}>
+                    <div style="display: flex; gap: 0.5rem;">
+                        <Button onclick={on_surrender} style="background: #660000; padding: 0.3rem 0.6rem;">
+                            {t.surrender_label()}
+                        </Button>
+                        <Button onclick={on_key_bindings_dialog} style="background: #444444; padding: 0.3rem 0.6rem;">
+                            {t.key_bindings_label()}
+                        </Button>
+                        <Button onclick={on_script_dialog} style="background: #444444; padding: 0.3rem 0.6rem;">
+                            {t.script_label()}
+                        </Button>
+                    </div>
+                </Positioner>
+                if props.script_dialog {
+                    <ScriptDialog error={props.script_error.clone()}/>
+                }
+                if props.key_bindings_dialog {
+                    <KeyBindingsDialog/>
+                }
+            } else if props.spectating && !nexus {
+                if let Some(SelectedTower{client_position, color, tower, tower_id, has_room_for_large, ..}) = props.selected_tower.clone() {
+                    <TowerOverlay
+                        {client_position}
+                        {color}
+                        {tower}
+                        {tower_id}
+                        outgoing_alliance={false}
+                        {has_room_for_large}
+                        spectating={true}
+                        tower_counts={props.tower_counts}
+                        tutorial_alert={None}
+                        unlocks={props.unlocks.clone()}
+                    />
+                }
+                <Positioner position={Position::TopLeft{margin: SPLASH_MARGIN}} align={Align::Left}>
+                    <Button onclick={on_spectate_factory(false)} style="background: #000066; padding: 0.5rem 1rem;">
+                        {t.stop_spectating_label()}
+                    </Button>
+                </Positioner>
             } else {
                 if !props.alive {
                     <SpawnOverlay {on_play}>
@@ -188,13 +303,20 @@ pub fn kiomet_ui(props: &PropertiesWrapper<KiometUiProps>) -> Html {
                         if let Some(death_reason) = props.death_reason {
                             <p class={death_reason_css}>{t.death_reason(death_reason)}</p>
                         }
-                        <ServerAddressInput />
+                        <ServerAddressInput connection={props.connection.clone()}/>
+                        <Button onclick={on_spectate_factory(true)} style="background: #444444; padding: 0.3rem 0.6rem; margin-top: 0.5rem;">
+                            {t.spectate_label()}
+                        </Button>
                     </SpawnOverlay>
                 }
                 {splash_social_media(&ctw, social_media_props)}
                 {splash_links(&ctw, &[KiometRoute::Help], Default::default())}
                 {splash_sign_in_link(&ctw)}
             }
+            // Opened from the towers dialog, which is in the nexus.
+            if let Some(tower_type) = props.upgrade_all_dialog.filter(|_| props.alive) {
+                <UpgradeAllDialog {tower_type} candidates={props.upgrade_candidates}/>
+            }
             {splash_nexus_icons(&ctw, Default::default())}
             <LeaderboardOverlay
                 position={Position::TopRight{margin: SPLASH_MARGIN}}
@@ -0,0 +1,191 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use crate::game::KiometGame;
+use crate::key_bindings::{KeyAction, KeyBinding, KeyBindings};
+use crate::ui::{KiometPhrases, KiometUiEvent};
+use kodiak_client::{
+    use_settings, use_translator, use_ui_event_callback, Curtain, Position, Positioner,
+};
+use stylist::yew::styled_component;
+use yew::{classes, html, use_state, Callback, Html, KeyboardEvent, MouseEvent};
+
+#[styled_component(KeyBindingsDialog)]
+pub fn key_bindings_dialog() -> Html {
+    let button_style = css!(
+        r#"
+        border: none;
+        border-radius: 0.5rem;
+        padding: 0.5rem;
+        color: white;
+        transition: filter 0.1s;
+        font-size: 1.1rem;
+        appearance: none;
+
+        :hover {
+            filter: brightness(0.85);
+        }
+
+        :active {
+            filter: brightness(0.7);
+        }
+
+        :disabled {
+            filter: brightness(0.5);
+        }
+    "#
+    );
+
+    let slot_style = css!(
+        r#"
+        min-width: 6rem;
+        padding: 0.25rem 0.5rem;
+        font-size: 0.9rem;
+        background-color: #4a6784;
+    "#
+    );
+
+    let conflict_style = css!(
+        r#"
+        background-color: #a83232;
+    "#
+    );
+
+    let t = use_translator();
+    let ui_event_callback = use_ui_event_callback::<KiometGame>();
+    let settings = use_settings::<KiometGame>();
+    let bindings = use_state(|| settings.key_bindings.clone());
+    // Slot waiting for a key press.
+    let listening = use_state(|| None::<(KeyAction, usize)>);
+    let conflicts = bindings.conflicts();
+
+    let on_key_down = {
+        let bindings = bindings.clone();
+        let listening = listening.clone();
+        Callback::from(move |e: KeyboardEvent| {
+            let Some((action, slot)) = *listening else {
+                return;
+            };
+            // Don't let the game react to the key.
+            e.prevent_default();
+            e.stop_propagation();
+            let binding = match e.code().as_str() {
+                "Escape" => {
+                    listening.set(None);
+                    return;
+                }
+                "Backspace" | "Delete" => None,
+                code => match KeyBinding::from_code(code, e.shift_key()) {
+                    Some(binding) => Some(binding),
+                    // Modifiers and unsupported keys.
+                    None => return,
+                },
+            };
+            bindings.set(bindings.with(action, slot, binding));
+            listening.set(None);
+        })
+    };
+
+    let on_reset = {
+        let bindings = bindings.clone();
+        let listening = listening.clone();
+        Callback::from(move |_: MouseEvent| {
+            bindings.set(KeyBindings::default());
+            listening.set(None);
+        })
+    };
+
+    let on_save = {
+        let bindings = bindings.clone();
+        ui_event_callback
+            .reform(move |_: MouseEvent| KiometUiEvent::SetKeyBindings((*bindings).clone()))
+    };
+
+    let on_close =
+        ui_event_callback.reform(|_: MouseEvent| KiometUiEvent::KeyBindingsDialog(false));
+
+    let rows = KeyAction::ALL.into_iter().map(|action| {
+        let slots = bindings
+            .get(action)
+            .into_iter()
+            .enumerate()
+            .map(|(slot, binding)| {
+                let conflicting = binding.map_or(false, |binding| {
+                    conflicts.iter().any(|&(b, first, second)| {
+                        b == binding && (first == action || second == action)
+                    })
+                });
+                let text = if *listening == Some((action, slot)) {
+                    t.press_key_hint()
+                } else {
+                    binding.map_or_else(|| String::from("—"), |binding| binding.to_string())
+                };
+                let onclick = {
+                    let listening = listening.clone();
+                    Callback::from(move |_: MouseEvent| listening.set(Some((action, slot))))
+                };
+                html! {
+                    <td>
+                        <button
+                            class={classes!(
+                                button_style.clone(),
+                                slot_style.clone(),
+                                conflicting.then(|| conflict_style.clone())
+                            )}
+                            {onclick}
+                        >{text}</button>
+                    </td>
+                }
+            });
+        html! {
+            <tr>
+                <td style="padding-right: 1rem;">{t.key_action_label(action)}</td>
+                {for slots}
+            </tr>
+        }
+    });
+
+    html! {
+        <Curtain opacity={127} onclick={on_close.clone()}>
+            <Positioner position={Position::Center}>
+                <div
+                    style="display: flex; flex-direction: column; gap: 1rem; text-align: left; padding: 1rem; background-color: #2c3e50; border-radius: 0.5rem;"
+                    onclick={|e: MouseEvent| e.stop_propagation()}
+                    onkeydown={on_key_down}
+                >
+                    <h2 style="margin: 0; font-size: 1.6rem;">{t.key_bindings_label()}</h2>
+                    <table style="border-spacing: 0.25rem;">
+                        {for rows}
+                    </table>
+                    {for conflicts.iter().map(|(binding, first, second)| html! {
+                        <p style="margin: 0; color: #ff6666;">
+                            {t.key_binding_conflict_warning(
+                                &binding.to_string(),
+                                &t.key_action_label(*first),
+                                &t.key_action_label(*second),
+                            )}
+                        </p>
+                    })}
+                    <div style="display: flex; flex-direction: row; gap: 1rem; justify-content: flex-end;">
+                        <button
+                            style="background-color: #4a6784;"
+                            class={button_style.clone()}
+                            onclick={on_reset}
+                        >{t.key_bindings_reset_label()}</button>
+                        <button
+                            style="background-color: #34ace0; font-weight: bold;"
+                            class={button_style.clone()}
+                            disabled={!conflicts.is_empty()}
+                            onclick={on_save}
+                        >{t.key_bindings_save_label()}</button>
+                        <button
+                            style="background-color: #4a6784;"
+                            class={button_style}
+                            onclick={on_close}
+                        >{"Return to game"}</button>
+                    </div>
+                </div>
+            </Positioner>
+        </Curtain>
+    }
+}
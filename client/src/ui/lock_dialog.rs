@@ -51,6 +51,11 @@ pub fn lock_dialog(props: &LockDialogProps) -> Html {
 
     let rewarded_ad = use_rewarded_ad();
     let tower_type = props.tower_type;
+    // Large towers don't have their own paintings yet.
+    let painting = tower_type
+        .downgrade()
+        .filter(|_| tower_type.is_large())
+        .unwrap_or(tower_type);
     let on_ok = if props.keys == 0 {
         let request_ad = if let RewardedAd::Available { request } = rewarded_ad.clone() {
             Some(request)
@@ -145,7 +150,7 @@ pub fn lock_dialog(props: &LockDialogProps) -> Html {
                     </div>
                     if !is_mobile() {
                         <img
-                            src={format!("/data/paintings/{tower_type}.webp")}
+                            src={format!("/data/paintings/{painting}.webp")}
                             style="width: 30rem; min-height: 30rem; height: auto; background-color: #a25e5f; user-drag: none; -webkit-user-drag: none;"
                         />
                     }
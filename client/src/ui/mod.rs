@@ -6,13 +6,16 @@ pub(crate) mod alert_overlay;
 pub(crate) mod button;
 pub(crate) mod game_ui;
 pub(crate) mod help_dialog;
+pub(crate) mod key_bindings_dialog;
 pub(crate) mod lock_dialog;
 mod phrases;
+pub(crate) mod script_dialog;
 pub(crate) mod tower_icon;
 pub(crate) mod tower_overlay;
 pub(crate) mod towers_dialog;
 pub(crate) mod unit_icon;
 pub(crate) mod units_dialog;
+pub(crate) mod upgrade_all_dialog;
 pub(crate) mod server_address_input;
 
 pub use game_ui::{KiometRoute, KiometUi, KiometUiEvent, KiometUiProps, SelectedTower};
@@ -1,6 +1,8 @@
 // SPDX-FileCopyrightText: 2024 Softbear, Inc.
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
+use crate::connection::ConnectionState;
+use crate::key_bindings::KeyAction;
 use common::death_reason::DeathReason;
 use common::tower::TowerType;
 use common::unit::Unit;
@@ -21,16 +23,56 @@ pub trait KiometPhrases {
     fn alert_full_hint(&self) -> String;
     fn alert_overflowing_warning(&self) -> String;
     fn alert_overflowing_hint(&self) -> String;
+    fn alert_incoming_nuke_warning(&self) -> String;
+    fn alert_incoming_nuke_hint(&self) -> String;
+    fn alert_incoming_emp_warning(&self) -> String;
+    fn alert_incoming_emp_hint(&self) -> String;
+    fn alert_emped_warning(&self) -> String;
+    fn alert_emped_hint(&self) -> String;
+    fn alert_supply_line_halted_warning(&self) -> String;
+    fn alert_supply_line_halted_hint(&self) -> String;
+    fn alert_alliance_request_warning(&self, alias: &str) -> String;
+    fn alert_alliance_request_hint(&self) -> String;
     fn break_alliance_hint(&self) -> String;
     fn cancel_alliance_hint(&self) -> String;
+    fn cancel_queued_upgrade_hint(&self) -> String;
+    fn connection_state_label(&self, state: &ConnectionState) -> String;
     fn death_reason(&self, death_reason: DeathReason) -> String;
     fn _demolish_hint(&self) -> String;
+    fn disconnected_death(&self) -> String;
+    fn idle_death(&self) -> String;
+    fn invalid_server_address_error(&self, reason: &str) -> String;
+    fn key_action_label(&self, action: KeyAction) -> String;
+    fn key_binding_conflict_warning(&self, binding: &str, first: &str, second: &str) -> String;
+    fn key_bindings_label(&self) -> String;
+    fn key_bindings_reset_label(&self) -> String;
+    fn key_bindings_save_label(&self) -> String;
+    fn last_connected_label(&self) -> String;
     fn owner_s(&self, owner: &str) -> String;
+    fn press_key_hint(&self) -> String;
+    fn protocol_mismatch_error(&self) -> String;
+    fn queue_upgrade_to_label(&self, tower_type: &str) -> String;
     fn request_alliance_hint(&self) -> String;
+    fn ruler_expired(&self) -> String;
     fn ruler_killed(&self, alias: Option<PlayerAlias>, unit: &str) -> String;
     fn ruler_label(&self) -> String;
+    fn script_enabled_label(&self) -> String;
+    fn script_label(&self) -> String;
+    fn script_save_label(&self) -> String;
+    fn server_latency_label(&self, latency: Option<f64>) -> String;
+    fn server_list_full_error(&self) -> String;
+    fn server_name_placeholder(&self) -> String;
+    fn spectate_label(&self) -> String;
+    fn stop_spectating_label(&self) -> String;
+    fn surrender_confirm(&self) -> String;
+    fn surrender_label(&self) -> String;
+    fn surrendered_death(&self) -> String;
     fn _tower_label(&self) -> String;
     fn tower_type_label(&self, tower_type: TowerType) -> String;
+    fn upgrade_all_label(&self) -> String;
+    fn upgrade_all_partial_preview(&self, count: u16, total: u16, tower_type: &str) -> String;
+    fn upgrade_all_preview(&self, count: u16, tower_type: &str) -> String;
+    fn upgrade_all_viewport_label(&self) -> String;
     fn unit_label(&self, unit: Unit) -> String;
     fn zombie(&self) -> String;
 }
@@ -44,7 +86,7 @@ impl KiometPhrases for Translator {
             Artillery => translate!(self, "Artillery"),
             Barracks => translate!(self, "Barracks"),
             Bunker => translate!(self, "Bunker"),
-            // Capitol => "Capitol", // TODO
+            Capitol => translate!(self, "Capitol"),
             Centrifuge => translate!(self, "Centrifuge"),
             City => translate!(self, "City"),
             Cliff => translate!(self, "Cliff"),
@@ -53,10 +95,10 @@ impl KiometPhrases for Translator {
             Generator => translate!(self, "Generator"),
             Headquarters => translate!(self, "Headquarters"),
             Helipad => translate!(self, "Helipad"),
-            //Icbm => "ICBM",   // TODO
-            //Laser => "Laser", // TODO
+            Icbm => translate!(self, "ICBM"),
+            Laser => translate!(self, "Laser"),
             Launcher => translate!(self, "Launcher"),
-            //Metropolis => "Metropolis", // TODO
+            Metropolis => translate!(self, "Metropolis"),
             Mine => translate!(self, "Mine"),
             Projector => translate!(self, "Projector"),
             Quarry => translate!(self, "Quarry"),
@@ -76,10 +118,12 @@ impl KiometPhrases for Translator {
     fn unit_label(&self, unit: Unit) -> String {
         use Unit::*;
         match unit {
+            Beam => translate!(self, "Beam"),
             Bomber => translate!(self, "Bomber"),
             Chopper => translate!(self, "Chopper"),
             Emp => translate!(self, "EMP"),
             Fighter => translate!(self, "Fighter"),
+            Missile => translate!(self, "Missile"),
             Nuke => translate!(self, "Nuke"),
             Ruler => self.ruler_label(),
             Shell => translate!(self, "Shell"),
@@ -97,9 +141,21 @@ impl KiometPhrases for Translator {
                 // TODO don't use to_lowercase as it adds 32.6 kb to the binary.
                 &self.unit_label(unit),
             ),
+            ForceExpired => self.ruler_expired(),
+            Surrendered => self.surrendered_death(),
+            Disconnected => self.disconnected_death(),
+            Idle => self.idle_death(),
         }
     }
 
+    fn disconnected_death(&self) -> String {
+        translate!(self, "Disconnected for too long!")
+    }
+
+    fn idle_death(&self) -> String {
+        translate!(self, "Inactive for too long!")
+    }
+
     fn _tower_label(&self) -> String {
         translate!(self, "Tower")
     }
@@ -116,6 +172,10 @@ impl KiometPhrases for Translator {
         translate!(self, "Cancel request")
     }
 
+    fn cancel_queued_upgrade_hint(&self) -> String {
+        translate!(self, "Cancel queued upgrade")
+    }
+
     fn break_alliance_hint(&self) -> String {
         translate!(self, "Break alliance")
     }
@@ -211,10 +271,188 @@ impl KiometPhrases for Translator {
         )
     }
 
+    fn alert_incoming_nuke_warning(&self) -> String {
+        translate!(self, "Nuke incoming!")
+    }
+
+    fn alert_incoming_nuke_hint(&self) -> String {
+        translate!(
+            self,
+            "alert_incoming_nuke_hint",
+            "Drag units away from the targeted tower"
+        )
+    }
+
+    fn alert_incoming_emp_warning(&self) -> String {
+        translate!(self, "EMP incoming")
+    }
+
+    fn alert_incoming_emp_hint(&self) -> String {
+        translate!(
+            self,
+            "alert_incoming_emp_hint",
+            "The targeted tower will be disabled for a while"
+        )
+    }
+
+    fn alert_emped_warning(&self) -> String {
+        translate!(self, "A tower was disabled by an EMP")
+    }
+
+    fn alert_emped_hint(&self) -> String {
+        translate!(
+            self,
+            "alert_emped_hint",
+            "It can't generate or upgrade until it recovers"
+        )
+    }
+
+    fn alert_supply_line_halted_warning(&self) -> String {
+        translate!(self, "A supply line was halted")
+    }
+
+    fn alert_supply_line_halted_hint(&self) -> String {
+        translate!(
+            self,
+            "alert_supply_line_halted_hint",
+            "A tower on its path was lost or became an ally's"
+        )
+    }
+
+    fn alert_alliance_request_warning(&self, alias: &str) -> String {
+        translate!(self, "{alias} requested an alliance")
+    }
+
+    fn alert_alliance_request_hint(&self) -> String {
+        translate!(self, "Click to accept")
+    }
+
+    fn queue_upgrade_to_label(&self, tower_type: &str) -> String {
+        translate!(self, "Queue upgrade to {tower_type}")
+    }
+
+    fn connection_state_label(&self, state: &ConnectionState) -> String {
+        match state {
+            ConnectionState::Idle => translate!(self, "Not connected"),
+            ConnectionState::Connecting => translate!(self, "Connecting..."),
+            ConnectionState::Handshaking => translate!(self, "Checking compatibility..."),
+            ConnectionState::Connected => translate!(self, "Connected"),
+            ConnectionState::Reconnecting { retry_in, .. } => {
+                let seconds = retry_in.max(0.0).ceil() as u32;
+                translate!(self, "Connection lost, retrying in {seconds}s")
+            }
+            ConnectionState::Incompatible(_) => self.protocol_mismatch_error(),
+            ConnectionState::Failed => translate!(self, "Couldn't connect"),
+        }
+    }
+
+    fn invalid_server_address_error(&self, reason: &str) -> String {
+        translate!(self, "Invalid server address: {reason}")
+    }
+
+    fn key_action_label(&self, action: KeyAction) -> String {
+        let label = match action {
+            KeyAction::PanLeft => translate!(self, "Pan left"),
+            KeyAction::PanRight => translate!(self, "Pan right"),
+            KeyAction::PanUp => translate!(self, "Pan up"),
+            KeyAction::PanDown => translate!(self, "Pan down"),
+            KeyAction::ZoomOut => translate!(self, "Zoom out"),
+            KeyAction::ZoomIn => translate!(self, "Zoom in"),
+            KeyAction::JumpToRuler => translate!(self, "Jump to ruler"),
+            KeyAction::CycleAlerts => translate!(self, "Cycle alerts"),
+            KeyAction::ShowSupplyLines => translate!(self, "Show supply lines"),
+            KeyAction::ClearSupplyLines => translate!(self, "Clear supply lines"),
+            KeyAction::ShowSimilarTowers => translate!(self, "Show similar towers"),
+            KeyAction::RevealMap => translate!(self, "Reveal map"),
+            KeyAction::UnboundedCamera => translate!(self, "Unbounded camera"),
+        };
+        if action.is_cheat() {
+            format!("{label} (cheat)")
+        } else {
+            label
+        }
+    }
+
+    fn key_binding_conflict_warning(&self, binding: &str, first: &str, second: &str) -> String {
+        translate!(self, "{binding} is bound to both {first} and {second}")
+    }
+
+    fn key_bindings_label(&self) -> String {
+        translate!(self, "Controls")
+    }
+
+    fn key_bindings_reset_label(&self) -> String {
+        translate!(self, "Reset")
+    }
+
+    fn key_bindings_save_label(&self) -> String {
+        translate!(self, "Save")
+    }
+
+    fn upgrade_all_label(&self) -> String {
+        translate!(self, "Upgrade all")
+    }
+
+    fn upgrade_all_partial_preview(&self, count: u16, total: u16, tower_type: &str) -> String {
+        translate!(
+            self,
+            "Upgrading {count} of {total} {tower_type} towers. Upgrade all again for the rest."
+        )
+    }
+
+    fn upgrade_all_preview(&self, count: u16, tower_type: &str) -> String {
+        translate!(
+            self,
+            "{count} of your {tower_type} towers will be upgraded."
+        )
+    }
+
+    fn upgrade_all_viewport_label(&self) -> String {
+        translate!(self, "Only in view")
+    }
+
+    fn press_key_hint(&self) -> String {
+        translate!(self, "Press a key...")
+    }
+
+    fn last_connected_label(&self) -> String {
+        translate!(self, "Last connected")
+    }
+
+    fn server_latency_label(&self, latency: Option<f64>) -> String {
+        if let Some(latency) = latency {
+            let ms = latency.round() as u32;
+            translate!(self, "{ms} ms")
+        } else {
+            translate!(self, "Offline")
+        }
+    }
+
+    fn server_list_full_error(&self) -> String {
+        translate!(self, "Too many saved servers")
+    }
+
+    fn server_name_placeholder(&self) -> String {
+        translate!(self, "Name (optional)")
+    }
+
+    fn protocol_mismatch_error(&self) -> String {
+        translate!(
+            self,
+            "protocol_mismatch_error",
+            "This server runs an incompatible version of the game"
+        )
+    }
+
     fn owner_s(&self, alias: &str) -> String {
         translate!(self, "{alias}'s")
     }
 
+    fn ruler_expired(&self) -> String {
+        let ruler = self.ruler_label();
+        translate!(self, "{ruler} lost in transit!")
+    }
+
     fn ruler_killed(&self, alias: Option<PlayerAlias>, unit: &str) -> String {
         let ruler = self.ruler_label();
         let owner = alias.map_or(self.zombie().into(), |alias| self.owner_s(&alias));
@@ -225,6 +463,38 @@ impl KiometPhrases for Translator {
         translate!(self, "King")
     }
 
+    fn script_enabled_label(&self) -> String {
+        translate!(self, "Run every tick")
+    }
+
+    fn script_label(&self) -> String {
+        translate!(self, "Script")
+    }
+
+    fn script_save_label(&self) -> String {
+        translate!(self, "Save")
+    }
+
+    fn spectate_label(&self) -> String {
+        translate!(self, "Spectate")
+    }
+
+    fn stop_spectating_label(&self) -> String {
+        translate!(self, "Stop spectating")
+    }
+
+    fn surrender_confirm(&self) -> String {
+        translate!(self, "Give up all of your towers and forces?")
+    }
+
+    fn surrender_label(&self) -> String {
+        translate!(self, "Surrender")
+    }
+
+    fn surrendered_death(&self) -> String {
+        translate!(self, "You surrendered!")
+    }
+
     fn zombie(&self) -> String {
         translate!(self, "zombie")
     }
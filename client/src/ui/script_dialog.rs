@@ -0,0 +1,134 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use crate::game::KiometGame;
+use crate::ui::{KiometPhrases, KiometUiEvent};
+use kodiak_client::{
+    use_settings, use_translator, use_ui_event_callback, Curtain, Position, Positioner,
+};
+use stylist::yew::styled_component;
+use wasm_bindgen::JsCast;
+use web_sys::{HtmlInputElement, HtmlTextAreaElement};
+use yew::{html, use_state, Callback, Event, Html, InputEvent, MouseEvent, Properties};
+
+#[derive(PartialEq, Properties)]
+pub struct ScriptDialogProps {
+    /// Last compile or runtime error of the saved script.
+    pub error: Option<String>,
+}
+
+#[styled_component(ScriptDialog)]
+pub fn script_dialog(props: &ScriptDialogProps) -> Html {
+    let button_style = css!(
+        r#"
+        border: none;
+        border-radius: 0.5rem;
+        padding: 0.5rem;
+        color: white;
+        transition: filter 0.1s;
+        font-size: 1.1rem;
+        appearance: none;
+
+        :hover {
+            filter: brightness(0.85);
+        }
+
+        :active {
+            filter: brightness(0.7);
+        }
+    "#
+    );
+
+    let textarea_style = css!(
+        r#"
+        background-color: rgba(30, 30, 30, 0.7);
+        border: 1px solid rgba(255, 255, 255, 0.3);
+        border-radius: 0.5rem;
+        color: white;
+        font-family: monospace;
+        font-size: 0.9rem;
+        padding: 0.5rem;
+        width: 40rem;
+        max-width: 80vw;
+        height: 20rem;
+        resize: vertical;
+    "#
+    );
+
+    let t = use_translator();
+    let ui_event_callback = use_ui_event_callback::<KiometGame>();
+    let settings = use_settings::<KiometGame>();
+    let source = use_state(|| settings.script.clone().unwrap_or_default());
+    let enabled = use_state(|| settings.script_enabled);
+
+    let on_input = {
+        let source = source.clone();
+        Callback::from(move |e: InputEvent| {
+            if let Some(textarea) = e
+                .target()
+                .and_then(|t| t.dyn_into::<HtmlTextAreaElement>().ok())
+            {
+                source.set(textarea.value());
+            }
+        })
+    };
+
+    let on_toggle = {
+        let enabled = enabled.clone();
+        Callback::from(move |e: Event| {
+            if let Some(input) = e.target().and_then(|t| t.dyn_into::<HtmlInputElement>().ok()) {
+                enabled.set(input.checked());
+            }
+        })
+    };
+
+    let on_save = {
+        let source = source.clone();
+        let enabled = enabled.clone();
+        ui_event_callback.reform(move |_: MouseEvent| KiometUiEvent::SetScript {
+            source: (*source).clone(),
+            enabled: *enabled,
+        })
+    };
+
+    let on_close = ui_event_callback.reform(|_: MouseEvent| KiometUiEvent::ScriptDialog(false));
+
+    html! {
+        <Curtain opacity={127} onclick={on_close.clone()}>
+            <Positioner position={Position::Center}>
+                <div
+                    style="display: flex; flex-direction: column; gap: 1rem; text-align: left; padding: 1rem; background-color: #2c3e50; border-radius: 0.5rem;"
+                    onclick={|e: MouseEvent| e.stop_propagation()}
+                >
+                    <h2 style="margin: 0; font-size: 1.6rem;">{t.script_label()}</h2>
+                    <textarea
+                        class={textarea_style}
+                        spellcheck="false"
+                        value={(*source).clone()}
+                        oninput={on_input}
+                    />
+                    <label>
+                        <input type="checkbox" checked={*enabled} onchange={on_toggle}/>
+                        {" "}
+                        {t.script_enabled_label()}
+                    </label>
+                    if let Some(error) = &props.error {
+                        <pre style="margin: 0; color: #ff6666; white-space: pre-wrap;">{error}</pre>
+                    }
+                    <div style="display: flex; flex-direction: row; gap: 1rem; justify-content: flex-end;">
+                        <button
+                            style="background-color: #34ace0; font-weight: bold;"
+                            class={button_style.clone()}
+                            onclick={on_save}
+                        >{t.script_save_label()}</button>
+                        <button
+                            style="background-color: #4a6784;"
+                            class={button_style}
+                            onclick={on_close}
+                        >{"Return to game"}</button>
+                    </div>
+                </div>
+            </Positioner>
+        </Curtain>
+    }
+}
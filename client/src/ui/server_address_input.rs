@@ -1,45 +1,71 @@
 // SPDX-FileCopyrightText: 2024 Softbear, Inc.
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
-use stylist::yew::styled_component;
-use yew::prelude::*;
-use web_sys::{HtmlInputElement, MouseEvent};
-use wasm_bindgen::JsCast;
+use crate::connection::{probe_latency, validate_server_url, ConnectionState};
+use crate::settings::SavedServers;
+use crate::ui::button::Button;
+use crate::ui::{KiometPhrases, KiometUiEvent};
 use crate::KiometGame;
-use kodiak_client::{use_settings, use_browser_storages};
-use js_sys::Function;
+use kodiak_client::{use_settings, use_translator, use_ui_event_callback};
+use std::collections::HashMap;
+use std::rc::Rc;
+use stylist::yew::styled_component;
 use wasm_bindgen::prelude::*;
-use crate::ui::button::Button;
+use wasm_bindgen::JsCast;
+use web_sys::{HtmlInputElement, MouseEvent};
+use yew::prelude::*;
 
-#[wasm_bindgen]
-extern "C" {
-    #[wasm_bindgen(js_namespace = window)]
-    fn kiomet_set_server_address(server_url: &str) -> bool;
-    
-    #[wasm_bindgen(js_namespace = window)]
-    fn kiomet_connect_to_server() -> bool;
+#[derive(PartialEq, Properties)]
+pub struct ServerAddressInputProps {
+    /// State of the connection to the saved server.
+    pub connection: ConnectionState,
 }
 
-// 添加一个函数来从localStorage获取服务器地址
-fn get_saved_server_address() -> Option<String> {
-    if let Some(window) = web_sys::window() {
-        if let Ok(Some(storage)) = window.local_storage() {
-            if let Ok(Some(address)) = storage.get_item("kiomet_server_address") {
-                return Some(address);
-            }
-        }
+#[derive(Clone, Copy, PartialEq)]
+enum Latency {
+    Probing,
+    /// In milliseconds, [`None`] if unreachable.
+    Measured(Option<f64>),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum SaveError {
+    Invalid(&'static str),
+    Full,
+}
+
+/// Latest probe of each saved server, by URL.
+#[derive(Default, PartialEq)]
+struct Latencies(HashMap<String, Latency>);
+
+impl Reducible for Latencies {
+    type Action = (String, Latency);
+
+    fn reduce(self: Rc<Self>, (url, latency): Self::Action) -> Rc<Self> {
+        let mut latencies = self.0.clone();
+        latencies.insert(url, latency);
+        Rc::new(Self(latencies))
     }
-    None
 }
 
 #[styled_component(ServerAddressInput)]
-pub fn server_address_input() -> Html {
+pub fn server_address_input(props: &ServerAddressInputProps) -> Html {
+    let t = use_translator();
     let input_ref = use_node_ref();
+    let ui_event_callback = use_ui_event_callback::<KiometGame>();
     let settings = use_settings::<KiometGame>();
-    let browser_storages = use_browser_storages();
-    // 默认不显示任何地址，即使localStorage中有保存的地址
-    let server_address = use_state(String::default);
+    let server_address = use_state(|| settings.server_address.clone().unwrap_or_default());
+    let server_name = use_state(|| {
+        settings
+            .server_address
+            .as_deref()
+            .and_then(|url| settings.servers.get(url))
+            .map(|server| server.name.clone())
+            .unwrap_or_default()
+    });
     let saved = use_state(|| false);
+    let error = use_state(|| None::<SaveError>);
+    let latencies = use_reducer(Latencies::default);
     
     let container_css = css!(
         r#"
@@ -80,6 +106,12 @@ pub fn server_address_input() -> Html {
         }
         "#
     );
+
+    let name_input_css = css!(
+        r#"
+        width: 25%;
+        "#
+    );
     
     let saved_css = css!(
         r#"
@@ -92,7 +124,104 @@ pub fn server_address_input() -> Html {
         opacity = if *saved { "1" } else { "0" }
     );
     
+    let error_css = css!(
+        r#"
+        color: #ff6666;
+        font-size: 0.8rem;
+        margin-top: 0.5rem;
+        "#
+    );
+
+    let list_css = css!(
+        r#"
+        display: flex;
+        flex-direction: column;
+        gap: 0.25rem;
+        width: 70%;
+        max-height: 10rem;
+        overflow-y: auto;
+        margin-bottom: 0.5rem;
+        "#
+    );
+
+    let row_css = css!(
+        r#"
+        display: flex;
+        align-items: center;
+        gap: 0.5rem;
+        padding: 0.2rem 0.5rem;
+        border-radius: 0.5rem;
+        background-color: rgba(30, 30, 30, 0.5);
+        font-size: 0.9rem;
+        "#
+    );
+
+    let current_row_css = css!(
+        r#"
+        border: 1px solid rgba(255, 255, 255, 0.5);
+        "#
+    );
+
+    let row_name_css = css!(
+        r#"
+        flex-grow: 1;
+        overflow: hidden;
+        text-overflow: ellipsis;
+        white-space: nowrap;
+        text-align: left;
+        "#
+    );
+
+    let tag_css = css!(
+        r#"
+        color: rgba(255, 255, 255, 0.6);
+        font-size: 0.7rem;
+        "#
+    );
+
+    let latency_css = css!(
+        r#"
+        cursor: pointer;
+        min-width: 4rem;
+        text-align: right;
+        color: rgba(255, 255, 255, 0.7);
+        "#
+    );
+    
     let placeholder = "输入服务器WebSocket地址...";
+
+    let probe = {
+        let latencies = latencies.dispatcher();
+        Callback::from(move |url: String| {
+            latencies.dispatch((url.clone(), Latency::Probing));
+            let latencies = latencies.clone();
+            let on_result = {
+                let url = url.clone();
+                move |latency| latencies.dispatch((url, Latency::Measured(latency)))
+            };
+            probe_latency(&url, on_result);
+        })
+    };
+
+    // 列表变化时重新测量延迟。
+    {
+        let probe = probe.clone();
+        use_effect_with(settings.servers.clone(), move |servers: &SavedServers| {
+            for server in &servers.servers {
+                probe.emit(server.url.clone());
+            }
+        });
+    }
+
+    let on_probe_all = {
+        let probe = probe.clone();
+        let servers = settings.servers.clone();
+        Callback::from(move |_: MouseEvent| {
+            for server in &servers.servers {
+                probe.emit(server.url.clone());
+            }
+        })
+    };
     
     let onchange = {
         let server_address = server_address.clone();
@@ -102,39 +231,144 @@ pub fn server_address_input() -> Html {
             server_address.set(input.value());
         })
     };
+
+    let onchange_name = {
+        let server_name = server_name.clone();
+        Callback::from(move |e: Event| {
+            let target = e.target().unwrap();
+            let input = target.dyn_ref::<HtmlInputElement>().unwrap();
+            server_name.set(input.value());
+        })
+    };
     
     let onclick = {
         let server_address = server_address.clone();
+        let server_name = server_name.clone();
         let saved = saved.clone();
+        let error = error.clone();
+        let ui_event_callback = ui_event_callback.clone();
+        let servers = settings.servers.clone();
         Callback::from(move |_: MouseEvent| {
             let address = (*server_address).clone();
-            if !address.is_empty() {
-                if kiomet_set_server_address(&address) {
-                    saved.set(true);
-                    // 3秒后隐藏保存提示
-                    let saved_clone = saved.clone();
-                    let closure = Closure::once_into_js(move || {
-                        saved_clone.set(false);
-                    });
-                    web_sys::window()
-                        .unwrap()
-                        .set_timeout_with_callback_and_timeout_and_arguments_0(
-                            closure.as_ref().unchecked_ref(),
-                            3000,
-                        )
-                        .unwrap();
+            let url = if address.trim().is_empty() {
+                None
+            } else {
+                match validate_server_url(&address) {
+                    Ok(url) => Some(url.to_owned()),
+                    Err(e) => {
+                        error.set(Some(SaveError::Invalid(e)));
+                        return;
+                    }
+                }
+            };
+            if let Some(url) = &url {
+                if servers.insert(&server_name, url).is_none() {
+                    error.set(Some(SaveError::Full));
+                    return;
                 }
+                ui_event_callback.emit(KiometUiEvent::SaveServer {
+                    name: (*server_name).clone(),
+                    url: url.clone(),
+                });
             }
+            error.set(None);
+            ui_event_callback.emit(KiometUiEvent::SetServerAddress(url));
+            saved.set(true);
+            // 3秒后隐藏保存提示
+            let saved_clone = saved.clone();
+            let closure = Closure::once_into_js(move || {
+                saved_clone.set(false);
+            });
+            web_sys::window()
+                .unwrap()
+                .set_timeout_with_callback_and_timeout_and_arguments_0(
+                    closure.as_ref().unchecked_ref(),
+                    3000,
+                )
+                .unwrap();
         })
     };
-    
-    let onclick_connect = Callback::from(move |_: MouseEvent| {
-        kiomet_connect_to_server();
+
+    let onclick_connect = ui_event_callback.reform(|_: MouseEvent| KiometUiEvent::Reconnect);
+
+    let status_css = css!(
+        r#"
+        color: rgba(255, 255, 255, 0.7);
+        font-size: 0.8rem;
+        margin-top: 0.5rem;
+        "#
+    );
+
+    let server_rows = settings.servers.servers.iter().map(|server| {
+        let current = settings.server_address.as_ref() == Some(&server.url);
+        let last_connected = settings.last_connected_server.as_ref() == Some(&server.url);
+        let latency = latencies.0.get(&server.url).copied();
+
+        let on_select = {
+            let url = server.url.clone();
+            let name = server.name.clone();
+            let server_address = server_address.clone();
+            let server_name = server_name.clone();
+            ui_event_callback.reform(move |_: MouseEvent| {
+                server_address.set(url.clone());
+                server_name.set(name.clone());
+                if current {
+                    KiometUiEvent::Reconnect
+                } else {
+                    KiometUiEvent::SetServerAddress(Some(url.clone()))
+                }
+            })
+        };
+        let on_remove = {
+            let url = server.url.clone();
+            ui_event_callback.reform(move |_: MouseEvent| KiometUiEvent::RemoveServer(url.clone()))
+        };
+        let on_probe = {
+            let url = server.url.clone();
+            probe.reform(move |_: MouseEvent| url.clone())
+        };
+
+        html! {
+            <div
+                key={server.url.clone()}
+                class={classes!(row_css.clone(), current.then(|| current_row_css.clone()))}
+            >
+                <span class={row_name_css.clone()} title={server.url.clone()}>{&server.name}</span>
+                if last_connected {
+                    <span class={tag_css.clone()}>{t.last_connected_label()}</span>
+                }
+                <span class={latency_css.clone()} onclick={on_probe}>
+                    {match latency {
+                        Some(Latency::Measured(latency)) => t.server_latency_label(latency),
+                        Some(Latency::Probing) | None => String::from("…"),
+                    }}
+                </span>
+                <Button onclick={on_select} style="background: #000066; padding: 0.2rem 0.5rem;">
+                    {"连接"}
+                </Button>
+                <Button onclick={on_remove} style="background: #660000; padding: 0.2rem 0.5rem;">
+                    {"✕"}
+                </Button>
+            </div>
+        }
     });
-    
+
     html! {
         <div class={container_css}>
+            if !settings.servers.servers.is_empty() {
+                <div class={list_css}>
+                    {for server_rows}
+                </div>
+            }
             <div class={input_container_css}>
+                <input
+                    type="text"
+                    class={classes!(input_css.clone(), name_input_css)}
+                    placeholder={t.server_name_placeholder()}
+                    maxlength={SavedServers::MAX_NAME_LEN.to_string()}
+                    value={(*server_name).clone()}
+                    onchange={onchange_name}
+                />
                 <input
                     ref={input_ref}
                     type="text"
@@ -149,6 +383,14 @@ pub fn server_address_input() -> Html {
                 >
                     {"✓"}
                 </Button>
+                if !settings.servers.servers.is_empty() {
+                    <Button
+                        onclick={on_probe_all}
+                        style="background: #444444; padding: 0.3rem 0.6rem; margin-left: 0.5rem;"
+                    >
+                        {"↻"}
+                    </Button>
+                }
                 <span class={saved_css}>{"已保存"}</span>
             </div>
             
@@ -158,6 +400,21 @@ pub fn server_address_input() -> Html {
             >
                 {"连接到服务器"}
             </Button>
+            if let Some(e) = *error {
+                <span class={error_css.clone()}>
+                    {match e {
+                        SaveError::Invalid(e) => t.invalid_server_address_error(e),
+                        SaveError::Full => t.server_list_full_error(),
+                    }}
+                </span>
+            }
+            if let ConnectionState::Incompatible(mismatch) = &props.connection {
+                <span class={error_css} title={mismatch.to_string()}>
+                    {t.protocol_mismatch_error()}
+                </span>
+            } else if props.connection != ConnectionState::Idle {
+                <span class={status_css}>{t.connection_state_label(&props.connection)}</span>
+            }
         </div>
     }
 } 
\ No newline at end of file
@@ -24,6 +24,12 @@ use yew::{classes, html, html_nested, Callback, Html, MouseEvent, Properties};
 pub struct TowerOverlayProps {
     pub color: Color,
     pub outgoing_alliance: bool,
+    pub has_room_for_large: bool,
+    /// Target of a [`KiometUiEvent::QueueUpgrade`], if any.
+    #[prop_or(None)]
+    pub queued_upgrade: Option<TowerType>,
+    /// Hides alliance buttons, since spectators have no allies or enemies.
+    pub spectating: bool,
     pub tower_id: TowerId,
     pub tower: Tower,
     pub client_position: IVec2,
@@ -106,6 +112,17 @@ pub fn tower_overlay(props: &TowerOverlayProps) -> Html {
         }
     };
 
+    let on_queue_upgrade_factory = {
+        let send_ui_event = use_ui_event_callback::<KiometGame>();
+
+        move |tower_type: Option<TowerType>| {
+            send_ui_event.reform(move |_: MouseEvent| KiometUiEvent::QueueUpgrade {
+                tower_id,
+                tower_type,
+            })
+        }
+    };
+
     let on_alliance_factory = {
         let send_ui_event = use_ui_event_callback::<KiometGame>();
 
@@ -142,7 +159,7 @@ pub fn tower_overlay(props: &TowerOverlayProps) -> Html {
     let outgoing_alliance = props.outgoing_alliance;
     let is_mine = unit_color == Color::Blue;
     let enemy_player_alias = player_id
-        .filter(|_| !is_mine)
+        .filter(|_| !is_mine && !props.spectating)
         .and_then(|player_id| core_state.player_or_bot(player_id))
         .map(|p| p.alias);
 
@@ -179,7 +196,8 @@ pub fn tower_overlay(props: &TowerOverlayProps) -> Html {
                 {props.tower.tower_type.upgrades().chain((basis != tower_type).then_some(basis)).map(|upgrade| {
                     let locked = locked(upgrade);
                     let downgrade = basis == upgrade;
-                    let upgradable = upgrade.has_prerequisites(&props.tower_counts);
+                    let upgradable = upgrade.has_prerequisites(&props.tower_counts)
+                        && (!upgrade.is_large() || props.has_room_for_large);
                     let color = if downgrade { Color::Red } else { Color::Blue };
                     html_nested!{
                         <div style="display: flex; flex-direction: row; gap: 0.5rem;">
@@ -231,6 +249,30 @@ pub fn tower_overlay(props: &TowerOverlayProps) -> Html {
                         </div>
                     }
                 }).collect::<Html>()}
+                // Queued upgrades happen one step at a time, as soon as prerequisites are met.
+                <div style="display: flex; flex-direction: row; flex-wrap: wrap; gap: 0.25rem;">
+                    {props.tower.tower_type.eventual_upgrades().filter(|&upgrade| !locked(upgrade)).map(|upgrade| {
+                        let queued = props.queued_upgrade == Some(upgrade);
+                        html_nested!{
+                            <Button
+                                onclick={on_queue_upgrade_factory(Some(upgrade))}
+                                title={t.queue_upgrade_to_label(&t.tower_type_label(upgrade))}
+                                style={format!("background-color: {};", Color::Blue.background_color_css())}
+                            >
+                                <TowerIcon tower_type={upgrade} size={"1.5rem"} fill={if queued { Color::Blue } else { Color::Gray }} selected={queued}/>
+                            </Button>
+                        }
+                    }).collect::<Html>()}
+                    if props.queued_upgrade.is_some() {
+                        <Button
+                            onclick={on_queue_upgrade_factory(None)}
+                            title={t.cancel_queued_upgrade_hint()}
+                            style={format!("background-color: {};", Color::Red.background_color_css())}
+                        >
+                            {"✕"}
+                        </Button>
+                    }
+                </div>
             }
             {enemy_player_alias.map(|enemy_player_alias| {
                 let break_alliance = outgoing_alliance;
@@ -2,17 +2,19 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
 use crate::color::Color;
+use crate::game::KiometGame;
 use crate::path::{PathId, SvgCache};
+use crate::ui::button::Button;
 use crate::ui::tower_icon::TowerIcon;
 use crate::ui::unit_icon::UnitIcon;
-use crate::ui::{KiometPhrases, KiometRoute};
+use crate::ui::{KiometPhrases, KiometRoute, KiometUiEvent};
 use common::tower::{TowerArray, TowerType};
 use common::unit::Unit;
 use kodiak_client::glam::UVec2;
-use kodiak_client::{translate, use_translator, NexusDialog};
+use kodiak_client::{translate, use_translator, use_ui_event_callback, NexusDialog};
 use stylist::yew::styled_component;
 use yew::virtual_dom::AttrValue;
-use yew::{classes, html, html_nested, Callback, Html, Properties};
+use yew::{classes, html, html_nested, Callback, Html, MouseEvent, Properties};
 use yew_router::prelude::use_navigator;
 
 #[derive(PartialEq, Properties)]
@@ -109,6 +111,23 @@ pub fn towers_dialog(props: &TowersDialogProps) -> Html {
 
     let t = use_translator();
     let navigator = use_navigator().unwrap();
+    let ui_event_callback = use_ui_event_callback::<KiometGame>();
+
+    let upgrade_all = props
+        .selected
+        .filter(|selected| selected.downgrade().is_some())
+        .map(|selected| {
+            let on_upgrade_all = ui_event_callback
+                .reform(move |_: MouseEvent| KiometUiEvent::UpgradeAllDialog(Some(selected)));
+            html! {
+                <p>
+                    <Button onclick={on_upgrade_all} style="background: #006600; padding: 0.3rem 0.6rem;">
+                        {t.upgrade_all_label()}
+                    </Button>
+                </p>
+            }
+        })
+        .unwrap_or_default();
     let total_depth =
         coord(TowerType::iter().map(|t| t.level()).max().unwrap() as u32 + 1) + TOWER_SCALE - SCALE;
     let total_breadth = coord(do_layout(
@@ -171,6 +190,7 @@ pub fn towers_dialog(props: &TowersDialogProps) -> Html {
                         }
                         {"."}
                     </p>
+                    {upgrade_all}
                 }
                 <p>
                     {"Can contain "}
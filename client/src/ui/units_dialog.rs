@@ -67,6 +67,7 @@ pub fn units_dialog(props: &UnitsDialogProps) -> Html {
             Range::Short => translate!(t, "Has a short range."),
             Range::Medium => translate!(t, "Has a medium range."),
             Range::Long => translate!(t, "Has a long range."),
+            Range::Extreme => translate!(t, "Has an extreme range."),
         })
     }
 
@@ -0,0 +1,105 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use crate::game::KiometGame;
+use crate::ui::button::Button;
+use crate::ui::tower_icon::TowerIcon;
+use crate::ui::{KiometPhrases, KiometUiEvent};
+use common::protocol::Command;
+use common::tower::TowerType;
+use kodiak_client::{use_translator, use_ui_event_callback, Curtain, Position, Positioner};
+use stylist::yew::styled_component;
+use wasm_bindgen::JsCast;
+use web_sys::HtmlInputElement;
+use yew::{html, use_state, Callback, Event, Html, MouseEvent, Properties};
+
+/// How many own towers can be upgraded to a tower type right now. [`KiometUiEvent::UpgradeAll`]
+/// upgrades at most [`Command::MAX_BATCH_LEN`] of them at a time.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct UpgradeCandidates {
+    pub all: u16,
+    /// In the tight viewport.
+    pub in_viewport: u16,
+}
+
+#[derive(PartialEq, Properties)]
+pub struct UpgradeAllDialogProps {
+    pub tower_type: TowerType,
+    pub candidates: UpgradeCandidates,
+}
+
+#[styled_component(UpgradeAllDialog)]
+pub fn upgrade_all_dialog(props: &UpgradeAllDialogProps) -> Html {
+    let t = use_translator();
+    let ui_event_callback = use_ui_event_callback::<KiometGame>();
+    let in_viewport = use_state(|| false);
+
+    let tower_type = props.tower_type;
+    let Some(downgrade) = tower_type.downgrade() else {
+        return Html::default();
+    };
+    let in_viewport_value = *in_viewport;
+    let total = if in_viewport_value {
+        props.candidates.in_viewport
+    } else {
+        props.candidates.all
+    };
+    let count = total.min(Command::MAX_BATCH_LEN as u16);
+    let downgrade_label = t.tower_type_label(downgrade);
+    let preview = if total > count {
+        t.upgrade_all_partial_preview(count, total, &downgrade_label)
+    } else {
+        t.upgrade_all_preview(count, &downgrade_label)
+    };
+
+    let on_toggle = Callback::from(move |e: Event| {
+        if let Some(input) = e
+            .target()
+            .and_then(|t| t.dyn_into::<HtmlInputElement>().ok())
+        {
+            in_viewport.set(input.checked());
+        }
+    });
+    let on_upgrade_all = ui_event_callback.reform(move |_: MouseEvent| KiometUiEvent::UpgradeAll {
+        tower_type,
+        in_viewport: in_viewport_value,
+    });
+    let on_close = ui_event_callback.reform(|_: MouseEvent| KiometUiEvent::UpgradeAllDialog(None));
+
+    html! {
+        <Curtain opacity={127} onclick={on_close.clone()}>
+            <Positioner position={Position::Center}>
+                <div
+                    style="display: flex; flex-direction: column; gap: 1rem; text-align: left; padding: 1rem; min-width: 16rem; max-width: 20rem; background-color: #2c3e50; border-radius: 0.5rem;"
+                    onclick={|e: MouseEvent| e.stop_propagation()}
+                >
+                    <h2 style="margin: 0; font-size: 1.6rem;">
+                        <TowerIcon {tower_type} size={"2rem"}/>
+                        {" "}
+                        {t.upgrade_all_label()}
+                    </h2>
+                    <p style="margin: 0;">
+                        {preview}
+                    </p>
+                    <label>
+                        <input type="checkbox" checked={in_viewport_value} onchange={on_toggle}/>
+                        {" "}
+                        {t.upgrade_all_viewport_label()}
+                    </label>
+                    <div style="display: flex; gap: 0.5rem;">
+                        <Button
+                            onclick={on_upgrade_all}
+                            disabled={count == 0}
+                            style="background: #006600; padding: 0.3rem 0.6rem;"
+                        >
+                            {t.upgrade_all_label()}
+                        </Button>
+                        <Button onclick={on_close} style="background: #4a6784; padding: 0.3rem 0.6rem;">
+                            {"Return to game"}
+                        </Button>
+                    </div>
+                </div>
+            </Positioner>
+        </Curtain>
+    }
+}
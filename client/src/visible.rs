@@ -32,7 +32,8 @@ impl Visible {
         self.ticked = true;
     }
 
-    pub fn update(&mut self, world: &World, me: PlayerId, all_visible: bool) {
+    /// `me` is [`None`] when spectating, in which case `all_visible` should be true.
+    pub fn update(&mut self, world: &World, me: Option<PlayerId>, all_visible: bool) {
         // Towers can only change every tick.
         if !std::mem::take(&mut self.ticked) {
             return;
@@ -41,7 +42,7 @@ impl Visible {
         let iter = world
             .chunk
             .iter_towers()
-            .filter(|(_, t)| all_visible || t.player_id == Some(me));
+            .filter(|(_, t)| all_visible || (me.is_some() && t.player_id == me));
 
         let mut min = U16Vec2::splat(WorldChunks::SIZE as u16 - 1);
         let mut max = U16Vec2::ZERO;
@@ -1,11 +1,17 @@
 // SPDX-FileCopyrightText: 2024 Softbear, Inc.
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
-use crate::tower::TowerId;
+use crate::info::Info;
+use crate::tower::{Tower, TowerId};
+use crate::unit::Unit;
+use crate::world::World;
 use flagset::{flags, FlagSet};
+use kodiak_common::actor_model::Map;
 use kodiak_common::bitcode::{self, *};
+use kodiak_common::PlayerId;
 
 #[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Alerts {
     /// Approximate ruler position, if known.
     pub ruler_position: Option<TowerId>,
@@ -15,6 +21,16 @@ pub struct Alerts {
     pub full: Option<TowerId>,
     /// Zombies are attacking this tower.
     pub zombies: Option<TowerId>,
+    /// An enemy nuke or missile is headed to this tower.
+    pub incoming_nuke: Option<TowerId>,
+    /// An enemy EMP is headed to this tower.
+    pub incoming_emp: Option<TowerId>,
+    /// The supply line of this tower was halted. Lasts until it is set again.
+    pub supply_line_halted: Option<TowerId>,
+    /// This tower was disabled by an EMP. Lasts until it recovers.
+    pub emped: Option<TowerId>,
+    /// This player requested an alliance that we haven't requested back.
+    pub alliance_request: Option<PlayerId>,
     /// Packed bit flags. TODO don't gamma.
     flags: u8,
 }
@@ -26,9 +42,79 @@ impl Alerts {
         self.full = None;
         self.overflowing = None;
         self.zombies = None;
+        self.incoming_nuke = None;
+        self.incoming_emp = None;
+        self.alliance_request = None;
         self.set_flags(self.flags() - (AlertFlag::RulerUnderAttack | AlertFlag::RulerNotSafe));
     }
 
+    /// Recalculates the ephemeral alerts that depend on one of `player_id`'s towers. Call after
+    /// [`Self::reset_ephemeral`] for every tower they own.
+    pub fn update_tower(
+        &mut self,
+        world: &World,
+        player_id: PlayerId,
+        tower_id: TowerId,
+        tower: &Tower,
+    ) {
+        for force in &tower.inbound_forces {
+            if force.player_id.map_or(false, |p| {
+                p == player_id || world.have_alliance(p, player_id)
+            }) {
+                continue;
+            }
+            if force.units.contains(Unit::Nuke) || force.units.contains(Unit::Missile) {
+                self.incoming_nuke = Some(tower_id);
+            }
+            if force.units.contains(Unit::Emp) {
+                self.incoming_emp = Some(tower_id);
+            }
+        }
+    }
+
+    /// Recalculates the alerts that don't depend on a specific tower and expires the ones caused
+    /// by [`Info`]s. Call once per tick after [`Self::reset_ephemeral`].
+    pub fn update(&mut self, world: &World, player_id: PlayerId) {
+        let owned = |tower_id: TowerId| {
+            world
+                .chunk
+                .get(tower_id)
+                .filter(|t| t.player_id == Some(player_id))
+        };
+        self.supply_line_halted = self
+            .supply_line_halted
+            .filter(|&tower_id| owned(tower_id).map_or(false, |t| t.supply_line.is_none()));
+        self.emped = self
+            .emped
+            .filter(|&tower_id| owned(tower_id).map_or(false, |t| t.delay.is_some()));
+
+        let Some(player) = Map::get(&world.player, player_id) else {
+            return;
+        };
+        self.alliance_request = Map::iter(&world.player)
+            .find(|&(other, state)| {
+                other != player_id
+                    && state.actor.allies.contains(&player_id)
+                    && !player.actor.allies.contains(&other)
+            })
+            .map(|(other, _)| other);
+    }
+
+    /// Sets alerts caused by `info`, if it concerns `player_id`.
+    pub fn on_info(&mut self, info: &Info, player_id: PlayerId) {
+        match *info {
+            Info::SupplyLineHalted {
+                tower_id,
+                player_id: p,
+            } if p == player_id => self.supply_line_halted = Some(tower_id),
+            Info::TowerEmped {
+                tower_id,
+                player_id: p,
+            } if p == player_id => self.emped = Some(tower_id),
+            _ => {}
+        }
+    }
+
     pub fn flags(&self) -> FlagSet<AlertFlag> {
         FlagSet::new_truncated(self.flags)
     }
@@ -48,3 +134,170 @@ flags! {
         UnsetAnySupplyLine,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::alerts::Alerts;
+    use crate::chunk::ChunkInput;
+    use crate::force::{Force, Path};
+    use crate::info::Info;
+    use crate::player::Player;
+    use crate::tower::{Tower, TowerId};
+    use crate::unit::Unit;
+    use crate::units::Units;
+    use crate::world::World;
+    use kodiak_common::actor_model::Map;
+    use kodiak_common::PlayerId;
+    use std::num::{NonZeroU16, NonZeroU32};
+
+    fn player_ids() -> [PlayerId; 2] {
+        [1, 2].map(|i| PlayerId(NonZeroU32::new(i).unwrap()))
+    }
+
+    /// A world with both players and a tower owned by the first.
+    fn world() -> (World, TowerId) {
+        let mut world = World::new();
+        for player_id in player_ids() {
+            Map::insert(&mut world.player, player_id, Player::default().into());
+        }
+        let tower_id = World::CENTER;
+        let mut tower = Tower::new(tower_id);
+        tower.set_player_id(Some(player_ids()[0]));
+        let (chunk_id, relative_tower_id) = tower_id.split();
+        let chunk = &mut Map::get_mut(&mut world.chunk, chunk_id).unwrap().actor;
+        chunk.insert(relative_tower_id, tower);
+        (world, tower_id)
+    }
+
+    fn tower_mut(world: &mut World, tower_id: TowerId) -> &mut Tower {
+        let (chunk_id, relative_tower_id) = tower_id.split();
+        &mut Map::get_mut(&mut world.chunk, chunk_id).unwrap().actor[relative_tower_id]
+    }
+
+    fn ally(world: &mut World, a: PlayerId, b: PlayerId) {
+        let player = &mut Map::get_mut(&mut world.player, a).unwrap().actor;
+        player.allies.insert(b);
+    }
+
+    #[test]
+    fn incoming_nuke_and_emp() {
+        let [a, b] = player_ids();
+        let (mut world, tower_id) = world();
+        let src = tower_id.neighbors().next().unwrap();
+        for unit in [Unit::Nuke, Unit::Emp] {
+            let mut units = Units::default();
+            units.add(unit, 1);
+            let force = Force::new(b, units, Path::new(vec![src, tower_id]));
+            tower_mut(&mut world, tower_id).inbound_forces.push(force);
+        }
+
+        let update = |world: &World| {
+            let mut alerts = Alerts::default();
+            alerts.reset_ephemeral();
+            alerts.update_tower(world, a, tower_id, world.chunk.get(tower_id).unwrap());
+            alerts
+        };
+        let alerts = update(&world);
+        assert_eq!(alerts.incoming_nuke, Some(tower_id));
+        assert_eq!(alerts.incoming_emp, Some(tower_id));
+
+        // Allies aren't a threat.
+        ally(&mut world, a, b);
+        ally(&mut world, b, a);
+        let alerts = update(&world);
+        assert_eq!(alerts.incoming_nuke, None);
+        assert_eq!(alerts.incoming_emp, None);
+
+        let mut alerts = Alerts {
+            incoming_nuke: Some(tower_id),
+            incoming_emp: Some(tower_id),
+            ..Default::default()
+        };
+        alerts.reset_ephemeral();
+        assert_eq!(alerts, Alerts::default());
+    }
+
+    #[test]
+    fn supply_line_halted() {
+        let [a, b] = player_ids();
+        let (mut world, tower_id) = world();
+        let info = Info::SupplyLineHalted {
+            tower_id,
+            player_id: a,
+        };
+
+        let mut alerts = Alerts::default();
+        alerts.on_info(&info, b);
+        assert_eq!(alerts.supply_line_halted, None);
+        alerts.on_info(&info, a);
+        assert_eq!(alerts.supply_line_halted, Some(tower_id));
+
+        // Lasts while the supply line is unset.
+        alerts.reset_ephemeral();
+        alerts.update(&world, a);
+        assert_eq!(alerts.supply_line_halted, Some(tower_id));
+
+        let dst = tower_id.neighbors().next().unwrap();
+        let (chunk_id, relative_tower_id) = tower_id.split();
+        world.dispatch_chunk_input(
+            chunk_id,
+            ChunkInput::SetSupplyLine {
+                tower_id: relative_tower_id,
+                path: Some(Path::new(vec![tower_id, dst])),
+            },
+            &mut |_| {},
+        );
+        alerts.update(&world, a);
+        assert_eq!(alerts.supply_line_halted, None);
+    }
+
+    #[test]
+    fn emped() {
+        let [a, b] = player_ids();
+        let (mut world, tower_id) = world();
+        tower_mut(&mut world, tower_id).delay = NonZeroU16::new(3);
+        let info = Info::TowerEmped {
+            tower_id,
+            player_id: a,
+        };
+
+        let mut alerts = Alerts::default();
+        alerts.on_info(&info, b);
+        assert_eq!(alerts.emped, None);
+        alerts.on_info(&info, a);
+        assert_eq!(alerts.emped, Some(tower_id));
+
+        // Lasts until the tower recovers.
+        alerts.reset_ephemeral();
+        alerts.update(&world, a);
+        assert_eq!(alerts.emped, Some(tower_id));
+
+        tower_mut(&mut world, tower_id).delay = None;
+        alerts.update(&world, a);
+        assert_eq!(alerts.emped, None);
+    }
+
+    #[test]
+    fn alliance_request() {
+        let [a, b] = player_ids();
+        let (mut world, _) = world();
+
+        let mut alerts = Alerts::default();
+        alerts.update(&world, a);
+        assert_eq!(alerts.alliance_request, None);
+
+        ally(&mut world, b, a);
+        alerts.update(&world, a);
+        assert_eq!(alerts.alliance_request, Some(b));
+
+        // Our own request doesn't alert the requester.
+        let mut theirs = Alerts::default();
+        theirs.update(&world, b);
+        assert_eq!(theirs.alliance_request, None);
+
+        ally(&mut world, a, b);
+        alerts.reset_ephemeral();
+        alerts.update(&world, a);
+        assert_eq!(alerts.alliance_request, None);
+    }
+}
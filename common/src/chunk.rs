@@ -11,28 +11,44 @@ use crate::tower::Tower;
 use crate::tower::TowerId;
 use crate::unit::Unit;
 use kodiak_common::bitcode::{self, *};
-use kodiak_common::{PlayerId, TicksRepr};
-use std::num::NonZeroU8;
+use kodiak_common::PlayerId;
+use std::num::NonZeroU16;
 use std::ops::{Index, IndexMut};
 
 #[cfg(test)]
 mod benchmark;
+mod derived;
 mod event;
 mod halt;
 mod id;
 mod maintenance;
+mod path_index;
+mod player_index;
 mod rectangle;
 
+use derived::Derived;
 pub use event::*;
 pub use halt::ChunkHaltEvent;
 pub use id::{ChunkId, RelativeTowerId};
 pub use maintenance::ChunkMaintenance;
+pub use path_index::PathIndex;
+pub use player_index::PlayerIndex;
 pub use rectangle::ChunkRectangle;
 
 #[derive(Clone, Debug, Hash, PartialEq, Eq, Encode, Decode)]
 pub struct Chunk {
     towers: Box<[Option<Tower>; Self::AREA]>,
     pub(crate) chunk_id: ChunkId, // Temporary hack to get chunk_id inside Apply. TODO C: WithId<ChunkId>.
+    /// Set by [`ChunkMaintenance::Destroy`] and cleared once halts are detected in the same tick,
+    /// so that paths only need to be checked when towers were actually destroyed.
+    #[bitcode(skip)]
+    pub(crate) towers_destroyed: bool,
+    /// Chunks that paths in this chunk lead through.
+    #[bitcode(skip)]
+    paths: Derived<PathIndex>,
+    /// Players with towers or forces in this chunk.
+    #[bitcode(skip)]
+    players: Derived<PlayerIndex>,
 }
 
 impl Index<RelativeTowerId> for Chunk {
@@ -48,6 +64,58 @@ impl IndexMut<RelativeTowerId> for Chunk {
     }
 }
 
+/// Only serializes present towers. Indices are rebuilt when deserializing.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Chunk {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        struct Towers<'a>(&'a Chunk);
+
+        impl serde::Serialize for Towers<'_> {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.collect_map(
+                    self.0
+                        .towers
+                        .iter()
+                        .enumerate()
+                        .filter_map(|(i, t)| t.as_ref().map(|t| (RelativeTowerId(i as u8), t))),
+                )
+            }
+        }
+
+        let mut state = serializer.serialize_struct("Chunk", 2)?;
+        state.serialize_field("chunk_id", &self.chunk_id)?;
+        state.serialize_field("towers", &Towers(self))?;
+        state.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Chunk {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(serde::Deserialize)]
+        #[serde(rename = "Chunk")]
+        struct ChunkRepr {
+            chunk_id: ChunkId,
+            towers: fxhash::FxHashMap<RelativeTowerId, Tower>,
+        }
+
+        let ChunkRepr { chunk_id, towers } = ChunkRepr::deserialize(deserializer)?;
+        let mut chunk = Self {
+            towers: Box::new([(); Self::AREA].map(|_| None)),
+            chunk_id,
+            towers_destroyed: false,
+            paths: Derived::default(),
+            players: Derived::default(),
+        };
+        for (tower_id, tower) in towers {
+            chunk.insert(tower_id, tower);
+        }
+        Ok(chunk)
+    }
+}
+
 impl Chunk {
     pub const SIZE: usize = 16;
     pub const AREA: usize = Self::SIZE * Self::SIZE;
@@ -57,6 +125,9 @@ impl Chunk {
         Self {
             towers: Box::new([(); Self::AREA].map(|_| None)),
             chunk_id,
+            towers_destroyed: false,
+            paths: Derived::default(),
+            players: Derived::default(),
         }
     }
 
@@ -70,7 +141,10 @@ impl Chunk {
     ///
     /// If there is already a [`Tower`] at `tower_id`.
     pub fn insert(&mut self, tower_id: RelativeTowerId, tower: Tower) {
-        let old = std::mem::replace(&mut self.towers[tower_id.0 as usize], Some(tower));
+        let (towers, paths, players) = self.split_mut();
+        paths.add_tower(&tower);
+        players.add_tower(&tower);
+        let old = std::mem::replace(&mut towers[tower_id.0 as usize], Some(tower));
         assert!(old.is_none());
     }
 
@@ -80,7 +154,11 @@ impl Chunk {
     ///
     /// If the [`Tower`] does not exist.
     pub fn remove(&mut self, tower_id: RelativeTowerId) -> Tower {
-        std::mem::take(&mut self.towers[tower_id.0 as usize]).unwrap()
+        let (towers, paths, players) = self.split_mut();
+        let tower = std::mem::take(&mut towers[tower_id.0 as usize]).unwrap();
+        paths.remove_tower(&tower);
+        players.remove_tower(&tower);
+        tower
     }
 
     pub fn iter(&self, chunk_id: ChunkId) -> impl Iterator<Item = (TowerId, &Tower)> + Clone {
@@ -100,14 +178,80 @@ impl Chunk {
             })
     }
 
+    /// Iterates distinct [`PlayerId`]s with towers or forces in this chunk.
     pub fn iter_player_ids(&self) -> impl Iterator<Item = PlayerId> + '_ {
-        // TODO cache.
-        self.towers.iter().flatten().flat_map(|t| {
-            t.player_id
-                .into_iter()
-                .chain(t.inbound_forces.iter().filter_map(|f| f.player_id))
-                .chain(t.outbound_forces.iter().filter_map(|f| f.player_id))
-        })
+        self.player_index().iter()
+    }
+
+    /// Returns true if `player_id` has towers or forces in this chunk.
+    pub fn contains_player(&self, player_id: PlayerId) -> bool {
+        self.player_index().contains(player_id)
+    }
+
+    pub fn player_index(&self) -> &PlayerIndex {
+        self.players
+            .get_or_init(|| PlayerIndex::new(self.towers.iter().flatten()))
+    }
+
+    /// Mutable access to the [`PlayerIndex`] for inputs and events that add or remove players.
+    pub(crate) fn players_mut(&mut self) -> &mut PlayerIndex {
+        self.split_mut().2
+    }
+
+    pub fn path_index(&self) -> &PathIndex {
+        self.paths
+            .get_or_init(|| PathIndex::new(self.towers.iter().flatten()))
+    }
+
+    /// Mutable access to the [`PathIndex`] for inputs and events that change inbound forces or
+    /// supply lines.
+    pub(crate) fn paths_mut(&mut self) -> &mut PathIndex {
+        self.split_mut().1
+    }
+
+    /// Like [`Self::iter_mut`], but also borrows the indices.
+    pub(crate) fn iter_mut_with_indices(
+        &mut self,
+        chunk_id: ChunkId,
+    ) -> (
+        impl Iterator<Item = (TowerId, &mut Tower)>,
+        &mut PathIndex,
+        &mut PlayerIndex,
+    ) {
+        let (towers, paths, players) = self.split_mut();
+        let towers = towers.iter_mut().enumerate().filter_map(move |(i, t)| {
+            t.as_mut()
+                .map(|t| (RelativeTowerId(i as u8).upgrade(chunk_id), t))
+        });
+        (towers, paths, players)
+    }
+
+    /// Like [`Self::paths_mut`], but also borrows a tower whose paths are being changed.
+    pub(crate) fn tower_and_paths_mut(
+        &mut self,
+        tower_id: RelativeTowerId,
+    ) -> (&mut Tower, &mut PathIndex) {
+        let (towers, paths, _) = self.split_mut();
+        (towers[tower_id.0 as usize].as_mut().unwrap(), paths)
+    }
+
+    /// Borrows the towers and the indices separately, building the indices if they haven't been
+    /// yet (e.g. after decoding).
+    fn split_mut(
+        &mut self,
+    ) -> (
+        &mut [Option<Tower>; Self::AREA],
+        &mut PathIndex,
+        &mut PlayerIndex,
+    ) {
+        let towers = &self.towers;
+        let paths = self
+            .paths
+            .get_mut_or_init(|| PathIndex::new(towers.iter().flatten()));
+        let players = self
+            .players
+            .get_mut_or_init(|| PlayerIndex::new(towers.iter().flatten()));
+        (&mut self.towers, paths, players)
     }
 
     pub fn tick<'a>(
@@ -139,27 +283,29 @@ impl Chunk {
             }
         };
 
-        // TODO better random tick offset (maybe per tower).
-        let tick_offset = Ticks::from_repr(u16::from_le_bytes([chunk_id.x, chunk_id.y]));
+        // Stagger periodic events between chunks.
+        let tick_offset = Ticks::from_repr(singleton.rng.chunk(chunk_id).next_u16());
         let tick = singleton.tick.wrapping_add(tick_offset);
-        let downgrade = tick.every(Ticks::from_whole_secs(60));
+        let timing = &singleton.timing;
+        let downgrade = tick.every(timing.downgrade_period);
 
-        for (tower_id, tower) in self.iter_mut(chunk_id) {
+        let (towers, paths, player_index) = self.iter_mut_with_indices(chunk_id);
+        for (tower_id, tower) in towers {
             // Un-owned towers must not have rulers.
             debug_assert!(tower.player_id.is_some() || !tower.units.has_ruler());
 
             let mut deploy = false;
-            if tick.every(Ticks::from_whole_secs(if tower.player_id.is_some() {
-                30
+            if tick.every(if tower.player_id.is_some() {
+                timing.owned_diminish_period
             } else {
-                10
-            })) {
+                timing.unowned_diminish_period
+            }) {
                 deploy |= tower.diminish_units_if_dead_or_overflow() != 0 && tower.active();
             }
 
             // Either delay or generate/decay, but not both.
             if let Some(delay) = tower.delay {
-                tower.delay = NonZeroU8::new(delay.get() - 1);
+                tower.delay = NonZeroU16::new(delay.get() - 1);
             } else if tower.player_id.is_some() {
                 for unit in Unit::iter() {
                     if let Some(period) = tower.tower_type.unit_generation(unit) {
@@ -294,12 +440,20 @@ impl Chunk {
                             }
 
                             inbound_survived &= winner == Some(CombatSide::Attacker);
-                            winner == Some(CombatSide::Defender)
+                            let outbound_survived = winner == Some(CombatSide::Defender);
+                            if !outbound_survived {
+                                player_index.remove(outbound_force.player_id);
+                            }
+                            outbound_survived
                         } else {
                             true
                         }
                     });
 
+                    if !inbound_survived {
+                        paths.remove_force(inbound_force);
+                        player_index.remove(inbound_force.player_id);
+                    }
                     inbound_survived
                 });
             }
@@ -308,6 +462,10 @@ impl Chunk {
 
             // Force vs. tower.
             for mut force in tower.inbound_forces.extract_if(|f| f.tick(tower_id)) {
+                // Forces that move on are added back by events.
+                paths.remove_arrived_force(&force);
+                player_index.remove(force.player_id);
+
                 let tower_player_id = tower.player_id;
                 if tower_player_id.is_some() || !tower.units.is_empty() {
                     let force_player_id = force.player_id;
@@ -333,14 +491,16 @@ impl Chunk {
                         );
 
                         if tower_emped {
-                            let emp_delay = NonZeroU8::new(
-                                Ticks::from_whole_secs(Unit::EMP_SECONDS as TicksRepr)
-                                    .0
-                                    .try_into()
-                                    .unwrap(),
-                            )
-                            .unwrap();
-                            tower.delay = tower.delay.max(Some(emp_delay));
+                            tower.delay = tower.delay.max(NonZeroU16::new(timing.emp_delay.0));
+                            if let Some(player_id) = tower_player_id {
+                                context(InfoEvent {
+                                    position,
+                                    info: Info::TowerEmped {
+                                        tower_id,
+                                        player_id,
+                                    },
+                                });
+                            }
                         }
 
                         if winner != Some(CombatSide::Attacker) {
@@ -386,6 +546,9 @@ impl Chunk {
 
                             // Don't crash when zombies are nuked.
                             if tower.player_id.is_some() || new_player_id.is_some() {
+                                paths.remove_supply_line(tower.supply_line.as_ref());
+                                player_index.remove(tower.player_id);
+                                player_index.add(new_player_id);
                                 Tower::set_player_id_inner(
                                     &mut tower.player_id,
                                     &tower.units,
@@ -419,6 +582,7 @@ impl Chunk {
                     });
 
                     // Cannot borrow so manually inline functions.
+                    player_index.add(Some(force_player_id));
                     Tower::set_player_id_inner(
                         &mut tower.player_id,
                         &tower.units,
@@ -441,6 +605,15 @@ impl Chunk {
                             position: tower_id.as_vec2(),
                             info: Info::LostForce(player_id),
                         });
+                        if force.units.has_ruler() {
+                            context(InfoEvent {
+                                position: tower_id.as_vec2(),
+                                info: Info::LostRuler {
+                                    player_id,
+                                    reason: LostRulerReason::ForceExpired,
+                                },
+                            });
+                        }
                     }
                 } else if matches!(relationship, Relationship::Ally | Relationship::Comrade)
                     && force.try_move_on(
@@ -456,7 +629,7 @@ impl Chunk {
                             .iter()
                             .filter(|f| f.current_destination() == force.current_destination())
                             .count() as u32
-                            >= 8
+                            >= timing.max_outbound_forces as u32
                     {
                         // Cramming.
                         if let Some(player_id) = force.player_id {
@@ -464,6 +637,15 @@ impl Chunk {
                                 position: tower_id.as_vec2(),
                                 info: Info::LostForce(player_id),
                             });
+                            if force.units.has_ruler() {
+                                context(InfoEvent {
+                                    position: tower_id.as_vec2(),
+                                    info: Info::LostRuler {
+                                        player_id,
+                                        reason: LostRulerReason::ForceExpired,
+                                    },
+                                });
+                            }
                         }
                     } else {
                         let (chunk_id, tower_id) = force.current_source().split();
@@ -486,7 +668,7 @@ impl Chunk {
                             position,
                             info: Info::LostRuler {
                                 player_id,
-                                reason: LostRulerReason::KilledBy(None, Unit::Shield),
+                                reason: LostRulerReason::ForceExpired,
                             },
                         })
                     } else {
@@ -495,9 +677,13 @@ impl Chunk {
                 }
             }
 
-            tower
-                .outbound_forces
-                .retain_mut(|force| !force.raw_tick(None));
+            tower.outbound_forces.retain_mut(|force| {
+                let arrived = force.raw_tick(None);
+                if arrived {
+                    player_index.remove(force.player_id);
+                }
+                !arrived
+            });
 
             shrink_vec(&mut tower.inbound_forces);
             shrink_vec(&mut tower.outbound_forces);
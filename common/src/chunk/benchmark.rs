@@ -6,11 +6,12 @@ use crate::{
     force::{Force, Path},
     info::InfoEvent,
     player::Player,
+    rng::SimRng,
     singleton::Singleton,
     tower::Tower,
     units::Units,
 };
-use kodiak_common::rand::{thread_rng, Rng};
+use kodiak_common::rand::Rng;
 use kodiak_common::PlayerId;
 use std::num::NonZeroU32;
 use test::Bencher;
@@ -34,7 +35,7 @@ fn bench(b: &mut Bencher) {
             }
         }
     }
-    let mut rng = thread_rng();
+    let mut rng = SimRng::new(0);
     for (src, dst) in paths {
         for _ in 0..rng.gen_range(0..8) {
             let mut force = Force::new(
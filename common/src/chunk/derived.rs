@@ -0,0 +1,36 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use std::hash::{Hash, Hasher};
+use std::sync::OnceLock;
+
+/// Data derived from the rest of a [`Chunk`][`super::Chunk`], such as an index. It isn't encoded
+/// (`#[bitcode(skip)]` decodes it as empty), so it is built on first use, and then kept up to date
+/// incrementally. It doesn't take part in comparisons or hashing, since it adds no information.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Derived<T>(OnceLock<T>);
+
+impl<T> Derived<T> {
+    pub fn get_or_init(&self, init: impl FnOnce() -> T) -> &T {
+        self.0.get_or_init(init)
+    }
+
+    pub fn get_mut_or_init(&mut self, init: impl FnOnce() -> T) -> &mut T {
+        if self.0.get().is_none() {
+            let _ = self.0.set(init());
+        }
+        self.0.get_mut().unwrap()
+    }
+}
+
+impl<T> Hash for Derived<T> {
+    fn hash<H: Hasher>(&self, _: &mut H) {}
+}
+
+impl<T> PartialEq for Derived<T> {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl<T> Eq for Derived<T> {}
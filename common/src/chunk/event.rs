@@ -12,7 +12,7 @@ use crate::world::Apply;
 use kodiak_common::actor_model::*;
 use kodiak_common::bitcode::{self, *};
 use kodiak_common::{define_on, PlayerId, RankNumber};
-use std::num::NonZeroU8;
+use std::num::NonZeroU16;
 
 /// A [`ChunkEvent`] with it's destination [`ChunkId`].
 pub struct AddressedChunkEvent {
@@ -77,6 +77,7 @@ impl Tower {
 }
 
 #[derive(Clone, Debug, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ChunkInput {
     // Only used for debugging with chonk.
     AddInboundForce {
@@ -113,6 +114,8 @@ impl Apply<ChunkInput, OnChunkEvent<'_, OnInfo<'_>>> for Chunk {
     fn apply(&mut self, u: &ChunkInput, context: &mut OnChunkEvent<'_, OnInfo<'_>>) {
         match u.clone() {
             ChunkInput::AddInboundForce { tower_id, force } => {
+                self.players_mut().add(force.player_id);
+                self.paths_mut().add_force(&force);
                 self[tower_id].inbound_forces.push(force);
             }
             ChunkInput::ClearZombies { tower_id } => {
@@ -132,13 +135,19 @@ impl Apply<ChunkInput, OnChunkEvent<'_, OnInfo<'_>>> for Chunk {
                     self.insert(tower_id, Tower::new(tower_id.upgrade(self.chunk_id)));
                 }
             }
-            ChunkInput::SetSupplyLine { tower_id, path } => self[tower_id].supply_line = path,
+            ChunkInput::SetSupplyLine { tower_id, path } => {
+                let (tower, paths) = self.tower_and_paths_mut(tower_id);
+                paths.remove_supply_line(tower.supply_line.as_ref());
+                paths.add_supply_line(path.as_ref());
+                tower.supply_line = path;
+            }
             ChunkInput::Spawn {
                 tower_id,
                 player_id,
                 rank,
             } => {
                 let chunk_id = self.chunk_id;
+                self.players_mut().add(Some(player_id));
                 let tower = &mut self[tower_id];
                 let tower_id = tower_id.upgrade(chunk_id);
 
@@ -197,16 +206,17 @@ impl Apply<ChunkInput, OnChunkEvent<'_, OnInfo<'_>>> for Chunk {
                 tower_id,
                 tower_type,
             } => {
-                let tower = &mut self[tower_id];
+                let (tower, paths) = self.tower_and_paths_mut(tower_id);
                 tower.tower_type = tower_type;
 
                 // The upgrade will temporarily suspend this tower.
-                tower.delay = NonZeroU8::new(tower_type.delay().0.try_into().unwrap());
+                tower.delay = NonZeroU16::new(tower_type.delay().0.try_into().unwrap());
 
                 // The new tower may have different unit capacities.
                 tower.reconcile_units();
 
                 if tower.supply_line.is_some() && !tower.generates_mobile_units() {
+                    paths.remove_supply_line(tower.supply_line.as_ref());
                     tower.supply_line = None;
                 }
             }
@@ -215,6 +225,7 @@ impl Apply<ChunkInput, OnChunkEvent<'_, OnInfo<'_>>> for Chunk {
 }
 
 #[derive(Clone, Debug, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ChunkEvent {
     AddInboundForce {
         tower_id: RelativeTowerId,
@@ -238,9 +249,12 @@ impl<C: ?Sized> Apply<ChunkEvent, C> for Chunk {
                 if self.get(tower_id).is_none() {
                     panic!("missing dst, {tower_id:?}, {force:?}");
                 }
+                self.players_mut().add(force.player_id);
+                self.paths_mut().add_force(&force);
                 self[tower_id].inbound_forces.push(force)
             }
             ChunkEvent::AddOutboundForce { tower_id, force } => {
+                self.players_mut().add(force.player_id);
                 self[tower_id].outbound_forces.push(force)
             }
         }
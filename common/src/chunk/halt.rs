@@ -7,6 +7,7 @@ use kodiak_common::actor_model::Message;
 use kodiak_common::bitcode::{self, *};
 
 #[derive(Clone, Copy, Debug, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ChunkHaltEvent {
     Force(RelativeTowerId, u32),
     SupplyLine(RelativeTowerId),
@@ -18,10 +19,15 @@ impl<C: ?Sized> Apply<ChunkHaltEvent, C> for Chunk {
     fn apply(&mut self, u: &ChunkHaltEvent, _context: &mut C) {
         match *u {
             ChunkHaltEvent::Force(relative_tower_id, index) => {
-                self[relative_tower_id].inbound_forces[index as usize].halt();
+                let (tower, paths) = self.tower_and_paths_mut(relative_tower_id);
+                let force = &mut tower.inbound_forces[index as usize];
+                paths.remove_force(force);
+                force.halt();
             }
             ChunkHaltEvent::SupplyLine(relative_tower_id) => {
-                self[relative_tower_id].supply_line = None;
+                let (tower, paths) = self.tower_and_paths_mut(relative_tower_id);
+                paths.remove_supply_line(tower.supply_line.as_ref());
+                tower.supply_line = None;
             }
         }
     }
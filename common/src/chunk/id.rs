@@ -50,6 +50,33 @@ impl ChunkId {
     }
 }
 
+/// Human-readable representation, e.g. `{"x": 1, "y": 2}`.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename = "ChunkId")]
+struct ChunkIdRepr {
+    x: u8,
+    y: u8,
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for ChunkId {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        ChunkIdRepr {
+            x: self.x,
+            y: self.y,
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for ChunkId {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        ChunkIdRepr::deserialize(deserializer).map(|ChunkIdRepr { x, y }| Self::new(x, y))
+    }
+}
+
 impl Deref for ChunkId {
     type Target = U8Vec2;
 
@@ -75,6 +102,8 @@ impl From<TowerId> for ChunkId {
 
 /// A [`TowerId`] relative to a [`ChunkId`]. Only 1 byte instead of 4.
 #[derive(Copy, Clone, Debug, Default, Hash, Eq, PartialEq, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct RelativeTowerId(pub u8);
 
 impl From<TowerId> for RelativeTowerId {
@@ -112,4 +141,20 @@ mod tests {
         let (chunk_id, relative_id) = tower_id.split();
         assert_eq!(tower_id, relative_id.upgrade(chunk_id))
     }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde() {
+        let tower_id = TowerId::new(123, 456);
+        let json = serde_json::to_string(&tower_id).unwrap();
+        assert_eq!(json, r#"{"x":123,"y":456}"#);
+        assert_eq!(serde_json::from_str::<TowerId>(&json).unwrap(), tower_id);
+
+        let chunk_id = tower_id.split().0;
+        let json = serde_json::to_string(&chunk_id).unwrap();
+        assert_eq!(
+            serde_json::from_str::<crate::chunk::ChunkId>(&json).unwrap(),
+            chunk_id
+        );
+    }
 }
@@ -13,6 +13,7 @@ use kodiak_common::PlayerId;
 /// The first input that runs each tick. Things that can't be done properly while `ChunkEvent`s are
 /// in flight.
 #[derive(Clone, Debug, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ChunkMaintenance {
     /// If `ChunkEvent`s are in flight, might destroy `Tower` that has incoming units.
     Destroy { tower_ids: Vec<RelativeTowerId> },
@@ -29,13 +30,16 @@ impl Apply<ChunkMaintenance, Dst<'_, ChunkId, OnInfo<'_>>> for Chunk {
                 for tower_id in tower_ids {
                     let tower = self.remove(tower_id);
                     debug_assert!(tower.can_destroy());
+                    self.towers_destroyed = true;
                 }
             }
             ChunkMaintenance::KillPlayer { player_id } => {
-                for (tower_id, tower) in self.iter_mut(self.chunk_id) {
+                let (towers, paths, _) = self.iter_mut_with_indices(self.chunk_id);
+                for (tower_id, tower) in towers {
                     if tower.player_id == Some(player_id) {
                         tower.units.subtract(Unit::Ruler, usize::MAX);
                         tower.units.subtract(Unit::Shield, usize::MAX);
+                        paths.remove_supply_line(tower.supply_line.as_ref());
                         tower.set_player_id(None);
 
                         // Don't trigger LostRulerEvents.
@@ -48,13 +52,18 @@ impl Apply<ChunkMaintenance, Dst<'_, ChunkId, OnInfo<'_>>> for Chunk {
                             },
                         });
                     }
-                    tower
-                        .inbound_forces
-                        .retain(|force| force.player_id != Some(player_id));
+                    tower.inbound_forces.retain(|force| {
+                        let keep = force.player_id != Some(player_id);
+                        if !keep {
+                            paths.remove_force(force);
+                        }
+                        keep
+                    });
                     tower
                         .outbound_forces
                         .retain(|force| force.player_id != Some(player_id));
                 }
+                self.players_mut().remove_all(player_id);
             }
         }
     }
@@ -0,0 +1,151 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use super::ChunkId;
+use crate::force::{Force, Path};
+use crate::tower::Tower;
+
+/// Multiset of [`ChunkId`]s that the paths of a [`Chunk`][`super::Chunk`]'s inbound forces and
+/// supply lines lead through. Only those chunks can cause its paths to halt.
+///
+/// An inbound force only counts the part of its path after its current destination, since that is
+/// the only part that can halt.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PathIndex {
+    counts: Vec<(ChunkId, u32)>,
+}
+
+impl PathIndex {
+    /// Builds an index from scratch.
+    pub(crate) fn new<'a>(towers: impl IntoIterator<Item = &'a Tower>) -> Self {
+        let mut ret = Self::default();
+        for tower in towers {
+            ret.add_tower(tower);
+        }
+        ret
+    }
+
+    pub fn contains(&self, chunk_id: ChunkId) -> bool {
+        self.search(chunk_id).is_ok()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// Iterates distinct [`ChunkId`]s in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = ChunkId> + '_ {
+        self.counts.iter().map(|&(chunk_id, _)| chunk_id)
+    }
+
+    pub(crate) fn add_force(&mut self, force: &Force) {
+        Self::for_each_in_force(force.path(), 2, |chunk_id| self.add(chunk_id));
+    }
+
+    pub(crate) fn remove_force(&mut self, force: &Force) {
+        Self::for_each_in_force(force.path(), 2, |chunk_id| self.remove(chunk_id));
+    }
+
+    /// Like [`Self::remove_force`], but after [`Force::tick`] popped the tower it arrived at.
+    pub(crate) fn remove_arrived_force(&mut self, force: &Force) {
+        Self::for_each_in_force(force.path(), 1, |chunk_id| self.remove(chunk_id));
+    }
+
+    pub(crate) fn add_supply_line(&mut self, supply_line: Option<&Path>) {
+        Self::for_each_in_supply_line(supply_line, |chunk_id| self.add(chunk_id));
+    }
+
+    pub(crate) fn remove_supply_line(&mut self, supply_line: Option<&Path>) {
+        Self::for_each_in_supply_line(supply_line, |chunk_id| self.remove(chunk_id));
+    }
+
+    pub(crate) fn add_tower(&mut self, tower: &Tower) {
+        for force in &tower.inbound_forces {
+            self.add_force(force);
+        }
+        self.add_supply_line(tower.supply_line.as_ref());
+    }
+
+    pub(crate) fn remove_tower(&mut self, tower: &Tower) {
+        for force in &tower.inbound_forces {
+            self.remove_force(force);
+        }
+        self.remove_supply_line(tower.supply_line.as_ref());
+    }
+
+    fn for_each_in_force(path: &Path, skip: usize, f: impl FnMut(ChunkId)) {
+        path.iter().skip(skip).map(ChunkId::from).for_each(f)
+    }
+
+    fn for_each_in_supply_line(supply_line: Option<&Path>, f: impl FnMut(ChunkId)) {
+        supply_line
+            .into_iter()
+            .flat_map(Path::iter)
+            .map(ChunkId::from)
+            .for_each(f)
+    }
+
+    fn add(&mut self, chunk_id: ChunkId) {
+        match self.search(chunk_id) {
+            Ok(i) => self.counts[i].1 += 1,
+            Err(i) => self.counts.insert(i, (chunk_id, 1)),
+        }
+    }
+
+    fn remove(&mut self, chunk_id: ChunkId) {
+        if let Ok(i) = self.search(chunk_id) {
+            let count = &mut self.counts[i].1;
+            *count -= 1;
+            if *count == 0 {
+                self.counts.remove(i);
+            }
+        } else {
+            debug_assert!(false, "{chunk_id:?} wasn't in index");
+        }
+    }
+
+    fn search(&self, chunk_id: ChunkId) -> Result<usize, usize> {
+        self.counts.binary_search_by_key(&chunk_id, |&(c, _)| c)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chunk::path_index::PathIndex;
+    use crate::chunk::ChunkId;
+    use crate::force::{Force, Path};
+    use crate::tower::{Tower, TowerId, TowerType};
+    use crate::unit::Unit;
+    use crate::units::Units;
+    use kodiak_common::PlayerId;
+
+    #[test]
+    fn path_index() {
+        let a = TowerId::new(1, 1);
+        let b = TowerId::new(1, 20);
+        let c = TowerId::new(20, 20);
+        let path = Path::new(vec![a, b, c]);
+
+        let mut tower = Tower::with_type(TowerType::Village);
+        tower.set_player_id(Some(PlayerId::SOLO_OFFLINE));
+        tower.supply_line = Some(path.clone());
+
+        let mut index = PathIndex::new([&tower]);
+        assert_eq!(
+            index.iter().collect::<Vec<_>>(),
+            [a, b, c].map(ChunkId::from)
+        );
+
+        // Only the part after the force's destination counts.
+        let mut units = Units::default();
+        units.add(Unit::Soldier, 1);
+        let mut force = Force::new(PlayerId::SOLO_OFFLINE, units, path);
+        index.add_force(&force);
+        index.remove_supply_line(tower.supply_line.as_ref());
+        assert_eq!(index.iter().collect::<Vec<_>>(), [ChunkId::from(c)]);
+
+        while !force.tick(b) {}
+        index.remove_arrived_force(&force);
+        assert!(index.is_empty());
+    }
+}
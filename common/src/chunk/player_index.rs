@@ -0,0 +1,140 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use crate::tower::Tower;
+use kodiak_common::PlayerId;
+
+/// Multiset of [`PlayerId`]s present in a [`Chunk`][`super::Chunk`], i.e. owning a tower or
+/// having an inbound or outbound force. Few players are in the same chunk, so a sorted [`Vec`] is
+/// faster than a map.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PlayerIndex {
+    counts: Vec<(PlayerId, u32)>,
+}
+
+impl PlayerIndex {
+    /// Builds an index from scratch.
+    pub(crate) fn new<'a>(towers: impl IntoIterator<Item = &'a Tower>) -> Self {
+        let mut ret = Self::default();
+        for tower in towers {
+            ret.add_tower(tower);
+        }
+        ret
+    }
+
+    pub fn contains(&self, player_id: PlayerId) -> bool {
+        self.search(player_id).is_ok()
+    }
+
+    /// Returns how many towers and forces belong to `player_id`.
+    pub fn count(&self, player_id: PlayerId) -> u32 {
+        self.search(player_id)
+            .map(|i| self.counts[i].1)
+            .unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// Iterates distinct [`PlayerId`]s in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = PlayerId> + '_ {
+        self.counts.iter().map(|&(player_id, _)| player_id)
+    }
+
+    pub(crate) fn add(&mut self, player_id: Option<PlayerId>) {
+        let Some(player_id) = player_id else {
+            return;
+        };
+        match self.search(player_id) {
+            Ok(i) => self.counts[i].1 += 1,
+            Err(i) => self.counts.insert(i, (player_id, 1)),
+        }
+    }
+
+    pub(crate) fn remove(&mut self, player_id: Option<PlayerId>) {
+        let Some(player_id) = player_id else {
+            return;
+        };
+        if let Ok(i) = self.search(player_id) {
+            let count = &mut self.counts[i].1;
+            *count -= 1;
+            if *count == 0 {
+                self.counts.remove(i);
+            }
+        } else {
+            debug_assert!(false, "{player_id:?} wasn't in index");
+        }
+    }
+
+    /// Removes all occurrences of `player_id`.
+    pub(crate) fn remove_all(&mut self, player_id: PlayerId) {
+        if let Ok(i) = self.search(player_id) {
+            self.counts.remove(i);
+        }
+    }
+
+    pub(crate) fn add_tower(&mut self, tower: &Tower) {
+        Self::for_each_in_tower(tower, |player_id| self.add(Some(player_id)));
+    }
+
+    pub(crate) fn remove_tower(&mut self, tower: &Tower) {
+        Self::for_each_in_tower(tower, |player_id| self.remove(Some(player_id)));
+    }
+
+    fn for_each_in_tower(tower: &Tower, f: impl FnMut(PlayerId)) {
+        tower
+            .player_id
+            .into_iter()
+            .chain(tower.inbound_forces.iter().filter_map(|f| f.player_id))
+            .chain(tower.outbound_forces.iter().filter_map(|f| f.player_id))
+            .for_each(f)
+    }
+
+    fn search(&self, player_id: PlayerId) -> Result<usize, usize> {
+        self.counts.binary_search_by_key(&player_id, |&(p, _)| p)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chunk::player_index::PlayerIndex;
+    use crate::tower::{Tower, TowerType};
+    use kodiak_common::PlayerId;
+    use std::num::NonZeroU32;
+
+    #[test]
+    fn player_index() {
+        let a = PlayerId(NonZeroU32::new(1).unwrap());
+        let b = PlayerId(NonZeroU32::new(2).unwrap());
+
+        let mut index = PlayerIndex::default();
+        index.add(Some(b));
+        index.add(Some(a));
+        index.add(Some(b));
+        index.add(None);
+        assert_eq!(index.iter().collect::<Vec<_>>(), vec![a, b]);
+        assert_eq!(index.count(b), 2);
+
+        index.remove(Some(b));
+        assert!(index.contains(b));
+        index.remove(Some(b));
+        assert!(!index.contains(b));
+
+        let mut tower = Tower::with_type(TowerType::Village);
+        tower.set_player_id(Some(b));
+        index.add_tower(&tower);
+        assert_eq!(
+            index,
+            PlayerIndex::new([&tower, &{
+                let mut tower = Tower::with_type(TowerType::Village);
+                tower.set_player_id(Some(a));
+                tower
+            }])
+        );
+
+        index.remove_all(a);
+        index.remove_tower(&tower);
+        assert!(index.is_empty());
+    }
+}
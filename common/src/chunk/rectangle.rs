@@ -7,6 +7,7 @@ use kodiak_common::bitcode::{self, *};
 use kodiak_common::U8Vec2;
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ChunkRectangle {
     pub bottom_left: ChunkId,
     pub top_right: ChunkId,
@@ -17,6 +17,7 @@ pub enum CombatInfo {
     Emp(CombatSide),
     NuclearExplosion,
     ShellExplosion,
+    BeamBurn,
 }
 
 impl CombatInfo {
@@ -43,6 +44,7 @@ impl CombatInfo {
                 },
                 Self::NuclearExplosion => Info::NuclearExplosion,
                 Self::ShellExplosion => Info::ShellExplosion,
+                Self::BeamBurn => Info::BeamBurn,
             },
         }
     }
@@ -131,6 +133,7 @@ impl<'a> Combatants<'a> {
         let mut emped = false;
         let mut nuked = false;
         let mut shelled = false;
+        let mut burned = false;
 
         let mut replace_unit = |me: &mut Self,
                                 my_last: &mut Option<Unit>,
@@ -153,7 +156,7 @@ impl<'a> Combatants<'a> {
                             on_info(CombatInfo::Emp(side));
                         }
                     }
-                    Unit::Nuke => {
+                    Unit::Nuke | Unit::Missile => {
                         // Don't do 2 explosions if nukes collide.
                         if !std::mem::replace(&mut nuked, true) {
                             on_info(CombatInfo::NuclearExplosion);
@@ -165,6 +168,11 @@ impl<'a> Combatants<'a> {
                             on_info(CombatInfo::ShellExplosion);
                         }
                     }
+                    Unit::Beam => {
+                        if !std::mem::replace(&mut burned, true) {
+                            on_info(CombatInfo::BeamBurn);
+                        }
+                    }
                     _ => {}
                 }
 
@@ -227,6 +235,10 @@ impl<'a> Combatants<'a> {
                 if let Some(tower_type) = enemy.tower_type {
                     // Nukes don't 1 shot silos.
                     unit_damage = tower_type.ranged_damage(unit_damage);
+                    // Beams burn through units in the open, not fortifications.
+                    if unit == Unit::Beam {
+                        unit_damage /= 4;
+                    }
                 }
             }
 
@@ -395,10 +407,10 @@ mod tests {
 
     #[test]
     fn fuzz() {
-        use kodiak_common::rand::{thread_rng, Rng};
-        fn random_units(shield: bool) -> Units {
+        use crate::rng::SimRng;
+        use kodiak_common::rand::Rng;
+        fn random_units(rng: &mut SimRng, shield: bool) -> Units {
             let mut ret = Units::default();
-            let mut rng = thread_rng();
             for unit in Unit::iter() {
                 let max = match unit {
                     Unit::Shield => {
@@ -421,6 +433,8 @@ mod tests {
                     Unit::Emp => 1,
                     Unit::Nuke => 1,
                     Unit::Ruler => 1,
+                    Unit::Beam => rng.gen_range(0..=3),
+                    Unit::Missile => 1,
                 };
                 if max > 0 && rng.gen_bool(0.5) {
                     ret.add(unit, rng.gen_range(1..=max));
@@ -429,8 +443,8 @@ mod tests {
             ret
         }
 
-        fn random_unit_pair(shield: bool) -> [Units; 2] {
-            let units = random_units(shield);
+        fn random_unit_pair(rng: &mut SimRng, shield: bool) -> [Units; 2] {
+            let units = random_units(rng, shield);
             [units.clone(), units]
         }
 
@@ -438,22 +452,26 @@ mod tests {
             units.each_mut().map(move |units| Combatants::force(units))
         }
 
-        fn random_tower_pair<'a>(units: &'a mut [Units; 2]) -> [Combatants<'a>; 2] {
-            let tower_type = thread_rng().gen();
+        fn random_tower_pair<'a>(
+            rng: &mut SimRng,
+            units: &'a mut [Units; 2],
+        ) -> [Combatants<'a>; 2] {
+            let tower_type = rng.gen();
             units.each_mut().map(move |units| {
                 units.reconcile(tower_type, true);
                 Combatants::tower(tower_type, units)
             })
         }
 
+        let mut rng = SimRng::new(0);
         for _ in 0..1000 {
-            let mut first_pair = random_unit_pair(false);
+            let mut first_pair = random_unit_pair(&mut rng, false);
             let [mut attacker_1, mut defender_2] = force_pair(&mut first_pair);
 
-            let is_tower = thread_rng().gen_bool(0.5);
-            let mut pair = random_unit_pair(is_tower);
+            let is_tower = rng.gen_bool(0.5);
+            let mut pair = random_unit_pair(&mut rng, is_tower);
             let [mut defender_1, mut attacker_2] = if is_tower {
-                random_tower_pair(&mut pair)
+                random_tower_pair(&mut rng, &mut pair)
             } else {
                 force_pair(&mut pair)
             };
@@ -471,6 +489,31 @@ mod tests {
         }
     }
 
+    #[test]
+    fn beam_weak_against_towers() {
+        let mut beam = make_force();
+        let mut soldiers = make_force();
+        let mut info = vec![];
+
+        beam.add(Unit::Beam, 1);
+        soldiers.add(Unit::Soldier, 10);
+
+        let winner = Combatants::fight(&mut beam, &mut soldiers, |i| info.push(i));
+        assert_eq!(winner, None);
+        assert_eq!(soldiers.units.len(), 0);
+        assert_eq!(info, [CombatInfo::BeamBurn]);
+
+        let mut beam = make_force();
+        let mut tower = make_tower(TowerType::Barracks);
+
+        beam.add(Unit::Beam, 1);
+        assert_eq!(tower.add(Unit::Soldier, 10), 10);
+
+        let winner = Combatants::fight(&mut beam, &mut tower, |_| {});
+        assert_eq!(winner, Some(CombatSide::Defender));
+        assert!(tower.units.available(Unit::Soldier) >= 4);
+    }
+
     #[test]
     fn bomber_wins_against_ruler_either_way() {
         let (mut tower, mut force) = make_tower_force();
@@ -1,15 +1,86 @@
 // SPDX-FileCopyrightText: 2024 Softbear, Inc.
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
+use crate::info::LostRulerReason;
 use crate::unit::Unit;
 use kodiak_common::bitcode::{self, *};
-use kodiak_common::PlayerAlias;
+use kodiak_common::{PlayerAlias, PlayerId};
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DeathReason {
     RulerKilled {
         /// Is [`None`] if was killed by zombies.
         alias: Option<PlayerAlias>,
         unit: Unit,
     },
+    /// Ruler was lost while travelling in a force that ran out of fuel or was crammed.
+    ForceExpired,
+    /// Gave up with [`Command::Surrender`][`crate::protocol::Command::Surrender`].
+    Surrendered,
+    /// Lost connection for too long.
+    Disconnected,
+    /// Didn't do anything for too long.
+    Idle,
+}
+
+impl DeathReason {
+    /// `alias` looks up the alias of the killer.
+    pub fn from_lost_ruler(
+        reason: LostRulerReason,
+        alias: impl FnOnce(PlayerId) -> Option<PlayerAlias>,
+    ) -> Self {
+        match reason {
+            LostRulerReason::KilledBy(player_id, unit) => Self::RulerKilled {
+                alias: player_id.and_then(alias),
+                unit,
+            },
+            LostRulerReason::ForceExpired => Self::ForceExpired,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::death_reason::DeathReason;
+    use crate::info::LostRulerReason;
+    use crate::unit::Unit;
+    use kodiak_common::PlayerId;
+    use std::num::NonZeroU32;
+
+    #[test]
+    fn from_lost_ruler() {
+        let killer = PlayerId(NonZeroU32::new(1).unwrap());
+        let mut looked_up = None;
+        assert_eq!(
+            DeathReason::from_lost_ruler(
+                LostRulerReason::KilledBy(Some(killer), Unit::Soldier),
+                |player_id| {
+                    looked_up = Some(player_id);
+                    None
+                }
+            ),
+            DeathReason::RulerKilled {
+                alias: None,
+                unit: Unit::Soldier
+            }
+        );
+        assert_eq!(looked_up, Some(killer));
+
+        // Zombies have no alias to look up.
+        assert_eq!(
+            DeathReason::from_lost_ruler(LostRulerReason::KilledBy(None, Unit::Tank), |_| {
+                unreachable!()
+            }),
+            DeathReason::RulerKilled {
+                alias: None,
+                unit: Unit::Tank
+            }
+        );
+
+        assert_eq!(
+            DeathReason::from_lost_ruler(LostRulerReason::ForceExpired, |_| unreachable!()),
+            DeathReason::ForceExpired
+        );
+    }
 }
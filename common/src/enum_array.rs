@@ -82,3 +82,56 @@ where
         &mut self.values[Self::to_idx(index)]
     }
 }
+
+/// Human-readable representation, a map from `K` to `V`, e.g. `{"Village": 3}`.
+#[cfg(feature = "serde")]
+impl<K, V, const N: usize> serde::Serialize for EnumArray<K, V, N>
+where
+    K: IntoEnumIterator + serde::Serialize,
+    u8: From<K>,
+    <K as IntoEnumIterator>::Iterator: DoubleEndedIterator + ExactSizeIterator,
+    V: serde::Serialize,
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.iter())
+    }
+}
+
+/// Missing keys are [`Default`].
+#[cfg(feature = "serde")]
+impl<'de, K, V, const N: usize> serde::Deserialize<'de> for EnumArray<K, V, N>
+where
+    K: IntoEnumIterator + Copy + serde::Deserialize<'de>,
+    u8: From<K>,
+    V: Default + serde::Deserialize<'de>,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor<K, V, const N: usize>(PhantomData<(K, V)>);
+
+        impl<'de, K, V, const N: usize> serde::de::Visitor<'de> for Visitor<K, V, N>
+        where
+            K: IntoEnumIterator + Copy + serde::Deserialize<'de>,
+            u8: From<K>,
+            V: Default + serde::Deserialize<'de>,
+        {
+            type Value = EnumArray<K, V, N>;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a map")
+            }
+
+            fn visit_map<A: serde::de::MapAccess<'de>>(
+                self,
+                mut map: A,
+            ) -> Result<Self::Value, A::Error> {
+                let mut ret = EnumArray::default();
+                while let Some((k, v)) = map.next_entry::<K, V>()? {
+                    ret[k] = v;
+                }
+                Ok(ret)
+            }
+        }
+
+        deserializer.deserialize_map(Visitor(PhantomData))
+    }
+}
@@ -17,6 +17,25 @@ pub struct Path {
     path: Vec<TowerId>, // In reverse order of input.
 }
 
+/// Human-readable representation, a list of [`TowerId`]s from first to last.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Path {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Path {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let path = Vec::<TowerId>::deserialize(deserializer)?;
+        if path.len() < 2 {
+            return Err(serde::de::Error::invalid_length(path.len(), &"at least 2 towers"));
+        }
+        Ok(Self::new(path))
+    }
+}
+
 impl Path {
     pub fn new(mut path: Vec<TowerId>) -> Self {
         assert!(path.len() >= 2);
@@ -117,6 +136,7 @@ impl Path {
 }
 
 #[derive(Clone, Debug, Hash, Eq, PartialEq, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Force {
     /// Invariant: Always has at least two items, most likely source and destination.
     path: Path,
@@ -369,6 +389,17 @@ mod tests {
         size_of!(Force);
     }
 
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde() {
+        let path = Path::new(vec![TowerId::new(1, 2), TowerId::new(2, 2), TowerId::new(3, 3)]);
+        let json = serde_json::to_string(&path).unwrap();
+        assert_eq!(json, r#"[{"x":1,"y":2},{"x":2,"y":2},{"x":3,"y":3}]"#);
+        assert_eq!(serde_json::from_str::<Path>(&json).unwrap(), path);
+
+        assert!(serde_json::from_str::<Path>(r#"[{"x":1,"y":2}]"#).is_err());
+    }
+
     #[test]
     fn chopper_carry() {
         let path = Path::new(vec![TowerId::new(0, 0), TowerId::new(0, 1)]);
@@ -25,6 +25,7 @@ pub struct InfoEvent {
 }
 
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Info {
     GainedTower {
         tower_id: TowerId,
@@ -46,14 +47,32 @@ pub enum Info {
     Emp(Option<PlayerId>),
     NuclearExplosion,
     ShellExplosion,
+    /// A supply line was halted because a tower in its path was lost or became an ally's.
+    SupplyLineHalted {
+        tower_id: TowerId,
+        /// Owner of the supply line.
+        player_id: PlayerId,
+    },
+    /// A tower was disabled by an EMP.
+    TowerEmped {
+        tower_id: TowerId,
+        /// Owner of the tower.
+        player_id: PlayerId,
+    },
+    BeamBurn,
 }
 
 #[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum LostRulerReason {
     KilledBy(Option<PlayerId>, Unit),
+    /// The force carrying the ruler ran out of fuel, was crammed, or couldn't enter its
+    /// destination.
+    ForceExpired,
 }
 
 #[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum GainedTowerReason {
     CapturedFrom(Option<PlayerId>),
     Explored,
@@ -61,6 +80,7 @@ pub enum GainedTowerReason {
 }
 
 #[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum LostTowerReason {
     CapturedBy(Option<PlayerId>),
     DestroyedBy(Option<PlayerId>),
@@ -33,11 +33,14 @@ pub mod force;
 pub mod info;
 pub mod player;
 pub mod protocol;
+pub mod rng;
 pub mod singleton;
 pub mod ticks;
 pub mod tower;
 pub mod unit;
 pub mod units;
+#[cfg(any(test, feature = "server"))]
+pub mod upgrade_queue;
 pub mod world;
 
 pub const KIOMET_CONSTANTS: &'static GameConstants = &GameConstants {
@@ -13,6 +13,45 @@ pub struct Player {
     pub new_alliances: Hashable<FxHashSet<PlayerId>>,
 }
 
+/// Serializes sets as sorted lists, so the representation is stable.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename = "Player")]
+struct PlayerRepr {
+    allies: Vec<PlayerId>,
+    new_alliances: Vec<PlayerId>,
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Player {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let sorted = |set: &FxHashSet<PlayerId>| {
+            let mut vec: Vec<_> = set.iter().copied().collect();
+            vec.sort_unstable();
+            vec
+        };
+        PlayerRepr {
+            allies: sorted(&self.allies),
+            new_alliances: sorted(&self.new_alliances),
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Player {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let PlayerRepr {
+            allies,
+            new_alliances,
+        } = PlayerRepr::deserialize(deserializer)?;
+        let mut player = Self::default();
+        player.allies.extend(allies);
+        player.new_alliances.extend(new_alliances);
+        Ok(player)
+    }
+}
+
 impl Actor for Player {
     type Id = PlayerId;
     /// Players are based on chunks which already have a keepalive.
@@ -23,6 +62,7 @@ impl Actor for Player {
 }
 
 #[derive(Clone, Debug, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PlayerInput {
     Died,
     /// Single direction alliance request.
@@ -58,6 +98,7 @@ impl<C> Apply<PlayerInput, C> for Player {
 }
 
 #[derive(Clone, Debug, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PlayerMaintainance {
     Died,
     RemoveDeadAlly(PlayerId),
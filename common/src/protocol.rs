@@ -6,10 +6,111 @@ use crate::chunk::ChunkRectangle;
 use crate::death_reason::DeathReason;
 use crate::force::Path;
 use crate::tower::{TowerArray, TowerId, TowerRectangle, TowerType};
+use crate::unit::Unit;
 use kodiak_common::bitcode::{self, *};
 use kodiak_common::{PlayerAlias, PlayerId};
+use std::fmt::{self, Display, Formatter};
+use std::mem::variant_count;
+use std::sync::LazyLock;
+use strum::IntoEnumIterator;
+
+/// Bump when changing the encoding of [`Command`] or [`Update`] in a way that
+/// [`protocol_version`] can't detect (e.g. changing the type of a field), and update the `layout`
+/// test to match.
+///
+/// 2: Tower delays are [`NonZeroU16`][`std::num::NonZeroU16`] and chunks no longer encode their
+/// indices.
+const PROTOCOL_REVISION: u32 = 2;
+
+/// Identifies the encoded shape of [`Command`], [`Update`], [`TowerType`] and [`Unit`]. Clients
+/// and servers with different versions can't understand each other.
+pub fn protocol_version() -> u32 {
+    static VERSION: LazyLock<u32> = LazyLock::new(|| {
+        // Use 32 bit fnv hash because it's deterministic across platforms.
+        let mut hash = 2166136261u32;
+        let mut write = |bytes: &[u8]| {
+            for &b in bytes {
+                hash ^= b as u32;
+                hash = hash.wrapping_mul(16777619);
+            }
+        };
+        for n in [
+            PROTOCOL_REVISION,
+            variant_count::<Command>() as u32,
+            variant_count::<TowerType>() as u32,
+            variant_count::<Unit>() as u32,
+        ] {
+            write(&n.to_le_bytes());
+        }
+        // Variant names change when types are added, removed, or reordered.
+        for tower_type in TowerType::iter() {
+            write(format!("{tower_type:?}").as_bytes());
+        }
+        for unit in Unit::iter() {
+            write(format!("{unit:?}").as_bytes());
+        }
+        // Field names of the non actor part of the update.
+        write(format!("{:?}", NonActor::default()).as_bytes());
+        hash
+    });
+    *VERSION
+}
+
+/// Exchanged upon connecting, before any [`Command`] or [`Update`], so that incompatible clients
+/// and servers fail with a clear error instead of a decode error.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Handshake {
+    pub protocol_version: u32,
+}
+
+impl Handshake {
+    pub fn new() -> Self {
+        Self {
+            protocol_version: protocol_version(),
+        }
+    }
+
+    /// Checks that `theirs`, received from the other side, is compatible with ours.
+    pub fn check(self, theirs: Self) -> Result<(), ProtocolMismatch> {
+        if self.protocol_version == theirs.protocol_version {
+            Ok(())
+        } else {
+            Err(ProtocolMismatch {
+                ours: self.protocol_version,
+                theirs: theirs.protocol_version,
+            })
+        }
+    }
+}
+
+impl Default for Handshake {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Returned by [`Handshake::check`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ProtocolMismatch {
+    pub ours: u32,
+    pub theirs: u32,
+}
+
+impl Display for ProtocolMismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "protocol version {:08x} is incompatible with {:08x}",
+            self.theirs, self.ours
+        )
+    }
+}
+
+impl std::error::Error for ProtocolMismatch {}
 
 #[derive(Clone, Debug, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Command {
     Alliance {
         with: PlayerId,
@@ -19,19 +120,170 @@ pub enum Command {
         tower_id: TowerId,
         path: Path,
     },
+    /// Upgrades a tower one step at a time, as soon as possible, until it reaches `tower_type`.
+    /// [`None`] cancels the queued upgrade.
+    QueueUpgrade {
+        tower_id: TowerId,
+        tower_type: Option<TowerType>,
+    },
     SetSupplyLine {
         tower_id: TowerId,
         path: Option<Path>,
     },
     SetViewport(ChunkRectangle),
     Spawn(PlayerAlias),
+    /// Gives up, losing all towers and forces.
+    Surrender,
+    /// Starts (if true) or stops spectating. Spectators don't occupy a [`PlayerId`] in the world,
+    /// see everything, and may [`Command::SetViewport`] anywhere. Ignored while alive.
+    Spectate(bool),
     Upgrade {
         tower_id: TowerId,
         tower_type: TowerType,
     },
+    /// Applies all of the commands in the same tick, or none of them (see
+    /// [`Command::validate_batch`]). Can't contain [`Command::Batch`], [`Command::SetViewport`],
+    /// [`Command::Spawn`], [`Command::Spectate`] or [`Command::Surrender`].
+    Batch(Vec<Command>),
 }
 
 impl Command {
+    /// Maximum number of commands in a [`Command::Batch`].
+    pub const MAX_BATCH_LEN: usize = 64;
+
+    /// Combines `commands` into as few commands as possible. Returns [`None`] if `commands` is
+    /// empty.
+    pub fn batch(mut commands: Vec<Command>) -> Option<Self> {
+        match commands.len() {
+            0 => None,
+            1 => commands.pop(),
+            _ => Some(Self::Batch(commands)),
+        }
+    }
+
+    /// Returns the tower that the command acts on, if any.
+    pub fn tower_id(&self) -> Option<TowerId> {
+        match self {
+            Self::DeployForce { tower_id, .. }
+            | Self::QueueUpgrade { tower_id, .. }
+            | Self::SetSupplyLine { tower_id, .. }
+            | Self::Upgrade { tower_id, .. } => Some(*tower_id),
+            Self::Alliance { .. }
+            | Self::Batch(_)
+            | Self::SetViewport(_)
+            | Self::Spawn(_)
+            | Self::Spectate(_)
+            | Self::Surrender => None,
+        }
+    }
+
+    /// Checks that a [`Command::Batch`] is well formed, i.e. not empty, not too long, and only
+    /// containing commands allowed in a batch. Other commands are always well formed. Doesn't need
+    /// the world, so clients can check before sending.
+    pub fn check_batch(&self) -> Result<(), &'static str> {
+        let Self::Batch(commands) = self else {
+            return Ok(());
+        };
+        if commands.is_empty() {
+            return Err("empty batch");
+        }
+        if commands.len() > Self::MAX_BATCH_LEN {
+            return Err("batch too long");
+        }
+        for command in commands {
+            match command {
+                Self::Batch(_) => return Err("nested batch"),
+                Self::SetViewport(_) | Self::Spawn(_) | Self::Spectate(_) | Self::Surrender => {
+                    return Err("not allowed in batch")
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks a [`Command::Batch`] from `player_id`, whose towers are counted by `tower_counts`,
+    /// before any of its commands are applied, and returns them in order. A non-batch command is
+    /// returned as is.
+    ///
+    /// Batches are atomic: the whole batch is rejected if it isn't well formed (see
+    /// [`Self::check_batch`]) or if any of its commands would fail, taking the earlier commands
+    /// into account (e.g. two neighbors can't both be upgraded to large towers). Otherwise, all of
+    /// them must be applied in the same tick.
+    #[cfg(feature = "server")]
+    pub fn validate_batch(
+        self,
+        chunks: &crate::world::WorldChunks,
+        player_id: PlayerId,
+        tower_counts: &TowerArray<u16>,
+    ) -> Result<Vec<Command>, &'static str> {
+        self.check_batch()?;
+        let Self::Batch(commands) = self else {
+            return Ok(vec![self]);
+        };
+        let mut deployed = Vec::new();
+        let mut upgraded = Vec::<(TowerId, TowerType)>::new();
+        for command in &commands {
+            if matches!(command, Self::Alliance { with, .. } if *with == player_id) {
+                return Err("alliance with self");
+            }
+            let Some(tower_id) = command.tower_id() else {
+                continue;
+            };
+            let Some(tower) = chunks
+                .get(tower_id)
+                .filter(|t| t.player_id == Some(player_id))
+            else {
+                return Err("tower not owned");
+            };
+            let max_edge_distance = tower.tower_type.ranged_distance();
+            match command {
+                Self::DeployForce { path, .. } => {
+                    if deployed.contains(&tower_id) {
+                        return Err("duplicate deploy");
+                    }
+                    deployed.push(tower_id);
+                    path.clone().validate(chunks, tower_id, max_edge_distance)?;
+                }
+                Self::SetSupplyLine {
+                    path: Some(path), ..
+                } => {
+                    path.clone().validate(chunks, tower_id, max_edge_distance)?;
+                }
+                Self::QueueUpgrade {
+                    tower_type: Some(target),
+                    ..
+                } => {
+                    if tower.tower_type.next_upgrade_towards(*target).is_none() {
+                        return Err("unreachable upgrade");
+                    }
+                }
+                &Self::Upgrade { tower_type, .. } => {
+                    if upgraded.iter().any(|&(id, _)| id == tower_id) {
+                        return Err("duplicate upgrade");
+                    }
+                    if !tower.active()
+                        || !tower.tower_type.can_upgrade_to(tower_type)
+                        || !tower_type.has_prerequisites(tower_counts)
+                    {
+                        return Err("cannot upgrade");
+                    }
+                    if tower_type.is_large()
+                        && (!chunks.has_room_for_large(tower_id)
+                            || upgraded.iter().any(|&(id, other)| {
+                                other.is_large() && tower_id.neighbors().any(|n| n == id)
+                            }))
+                    {
+                        return Err("no room for large");
+                    }
+                    upgraded.push((tower_id, tower_type));
+                }
+                _ => {}
+            }
+        }
+        Ok(commands)
+    }
+
     pub fn deploy_force_from_path(path: Vec<TowerId>) -> Self {
         Self::DeployForce {
             tower_id: path[0],
@@ -42,9 +294,12 @@ impl Command {
 
 /// Non actor model data that the client needs. Diffed for efficiency.
 #[derive(Debug, Default, Decode, Encode)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NonActor {
     /// Is alive?
     pub alive: bool,
+    /// Is spectating (see [`Command::Spectate`])? Never true while alive.
+    pub spectating: bool,
     /// Alerts.
     pub alerts: Alerts,
     /// Clamped to u16::MAX. Doesn't count upgrading towers.
@@ -53,10 +308,13 @@ pub struct NonActor {
     pub death_reason: Option<DeathReason>,
     /// An approximation of inhabited towers.
     pub bounding_rectangle: TowerRectangle,
+    /// Targets of [`Command::QueueUpgrade`] that haven't been reached yet.
+    pub queued_upgrades: Vec<(TowerId, TowerType)>,
 }
 
 /// Game server to game client update.
 #[derive(Debug, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Update {
     /// Actor model update.
     pub actor_update: crate::world::ActorUpdate,
@@ -64,3 +322,326 @@ pub struct Update {
     /// contains many small signed/unsigned integers.
     pub non_actor: NonActor,
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::chunk::ChunkRectangle;
+    use crate::protocol::{protocol_version, Command, Handshake, ProtocolMismatch};
+    use crate::tower::{Tower, TowerArray, TowerId, TowerType};
+    use crate::world::World;
+    use kodiak_common::PlayerId;
+    use std::num::NonZeroU32;
+
+    /// Changing the type of an encoded field doesn't change [`protocol_version`], so this pins them.
+    /// If it fails to compile, bump [`PROTOCOL_REVISION`] and update it.
+    #[test]
+    fn layout() {
+        use crate::alerts::Alerts;
+        use crate::chunk::{ChunkInput, RelativeTowerId};
+        use crate::death_reason::DeathReason;
+        use crate::force::{Force, Path};
+        use crate::protocol::{NonActor, PROTOCOL_REVISION};
+        use crate::tower::TowerRectangle;
+        use crate::units::Units;
+        use kodiak_common::{PlayerAlias, RankNumber};
+        use std::num::NonZeroU16;
+
+        fn is<T>(_: &T) {}
+
+        assert_eq!(PROTOCOL_REVISION, 2);
+
+        let _ = |command: Command| match command {
+            Command::Batch(commands) => is::<Vec<Command>>(&commands),
+            Command::Alliance {
+                with,
+                break_alliance,
+            } => {
+                is::<PlayerId>(&with);
+                is::<bool>(&break_alliance);
+            }
+            Command::DeployForce { tower_id, path } => {
+                is::<TowerId>(&tower_id);
+                is::<Path>(&path);
+            }
+            Command::QueueUpgrade {
+                tower_id,
+                tower_type,
+            } => {
+                is::<TowerId>(&tower_id);
+                is::<Option<TowerType>>(&tower_type);
+            }
+            Command::SetSupplyLine { tower_id, path } => {
+                is::<TowerId>(&tower_id);
+                is::<Option<Path>>(&path);
+            }
+            Command::SetViewport(viewport) => is::<ChunkRectangle>(&viewport),
+            Command::Spawn(alias) => is::<PlayerAlias>(&alias),
+            Command::Surrender => {}
+            Command::Spectate(spectate) => is::<bool>(&spectate),
+            Command::Upgrade {
+                tower_id,
+                tower_type,
+            } => {
+                is::<TowerId>(&tower_id);
+                is::<TowerType>(&tower_type);
+            }
+        };
+
+        let _ = |input: ChunkInput| match input {
+            ChunkInput::AddInboundForce { tower_id, force } => {
+                is::<RelativeTowerId>(&tower_id);
+                is::<Force>(&force);
+            }
+            ChunkInput::ClearZombies { tower_id } => is::<RelativeTowerId>(&tower_id),
+            ChunkInput::DeployForce { tower_id, path } => {
+                is::<RelativeTowerId>(&tower_id);
+                is::<Path>(&path);
+            }
+            ChunkInput::Generate { tower_ids } => is::<Vec<RelativeTowerId>>(&tower_ids),
+            ChunkInput::SetSupplyLine { tower_id, path } => {
+                is::<RelativeTowerId>(&tower_id);
+                is::<Option<Path>>(&path);
+            }
+            ChunkInput::Spawn {
+                tower_id,
+                player_id,
+                rank,
+            } => {
+                is::<RelativeTowerId>(&tower_id);
+                is::<PlayerId>(&player_id);
+                is::<Option<RankNumber>>(&rank);
+            }
+            ChunkInput::UpgradeTower {
+                tower_id,
+                tower_type,
+            } => {
+                is::<RelativeTowerId>(&tower_id);
+                is::<TowerType>(&tower_type);
+            }
+        };
+
+        let NonActor {
+            alive,
+            spectating,
+            alerts,
+            tower_counts,
+            death_reason,
+            bounding_rectangle,
+            queued_upgrades,
+        } = NonActor::default();
+        is::<bool>(&alive);
+        is::<bool>(&spectating);
+        is::<Alerts>(&alerts);
+        is::<TowerArray<u16>>(&tower_counts);
+        is::<Option<DeathReason>>(&death_reason);
+        is::<TowerRectangle>(&bounding_rectangle);
+        is::<Vec<(TowerId, TowerType)>>(&queued_upgrades);
+
+        // Alerts also has private flags.
+        let Alerts {
+            ruler_position,
+            overflowing,
+            full,
+            zombies,
+            incoming_nuke,
+            incoming_emp,
+            supply_line_halted,
+            emped,
+            alliance_request,
+            ..
+        } = alerts;
+        for tower_id in [
+            ruler_position,
+            overflowing,
+            full,
+            zombies,
+            incoming_nuke,
+            incoming_emp,
+            supply_line_halted,
+            emped,
+        ] {
+            is::<Option<TowerId>>(&tower_id);
+        }
+        is::<Option<PlayerId>>(&alliance_request);
+
+        let Tower {
+            player_id,
+            units,
+            tower_type,
+            delay,
+            inbound_forces,
+            outbound_forces,
+            supply_line,
+        } = Tower::with_type(TowerType::Village);
+        is::<Option<PlayerId>>(&player_id);
+        is::<Units>(&units);
+        is::<TowerType>(&tower_type);
+        is::<Option<NonZeroU16>>(&delay);
+        is::<Vec<Force>>(&inbound_forces);
+        is::<Vec<Force>>(&outbound_forces);
+        is::<Option<Path>>(&supply_line);
+
+        // Force also has a private path.
+        let _ = |force: Force| {
+            let Force {
+                path_progress,
+                fuel,
+                player_id,
+                units,
+                ..
+            } = force;
+            is::<u8>(&path_progress);
+            is::<u8>(&fuel);
+            is::<Option<PlayerId>>(&player_id);
+            is::<Units>(&units);
+        };
+    }
+
+    #[test]
+    fn handshake() {
+        assert_eq!(protocol_version(), protocol_version());
+
+        let ours = Handshake::new();
+        assert_eq!(ours.check(ours), Ok(()));
+
+        let theirs = Handshake {
+            protocol_version: ours.protocol_version.wrapping_add(1),
+        };
+        assert_eq!(
+            ours.check(theirs),
+            Err(ProtocolMismatch {
+                ours: ours.protocol_version,
+                theirs: theirs.protocol_version
+            })
+        );
+    }
+
+    fn upgrade(x: u16) -> Command {
+        Command::Upgrade {
+            tower_id: TowerId::new(x, 0),
+            tower_type: TowerType::Barracks,
+        }
+    }
+
+    #[test]
+    fn batch() {
+        assert!(Command::batch(vec![]).is_none());
+        assert!(matches!(
+            Command::batch(vec![upgrade(0)]),
+            Some(Command::Upgrade { .. })
+        ));
+        assert!(matches!(
+            Command::batch(vec![upgrade(0), upgrade(1)]),
+            Some(Command::Batch(commands)) if commands.len() == 2
+        ));
+    }
+
+    #[test]
+    fn validate_batch() {
+        let world = World::new();
+        let player_id = PlayerId(NonZeroU32::new(1).unwrap());
+        let counts = TowerArray::new();
+        let validate = |command: Command| command.validate_batch(&world.chunk, player_id, &counts);
+
+        assert_eq!(validate(upgrade(0)).map(|c| c.len()), Ok(1));
+        assert_eq!(validate(Command::Batch(vec![])).unwrap_err(), "empty batch");
+        let viewport = Command::SetViewport(ChunkRectangle::default());
+        assert_eq!(
+            validate(Command::Batch(vec![viewport; 2])).unwrap_err(),
+            "not allowed in batch"
+        );
+        assert_eq!(
+            validate(Command::Batch(vec![upgrade(0), Command::Surrender])).unwrap_err(),
+            "not allowed in batch"
+        );
+        assert_eq!(
+            validate(Command::Batch(vec![Command::Batch(vec![upgrade(0)])])).unwrap_err(),
+            "nested batch"
+        );
+        assert_eq!(
+            validate(Command::Batch(vec![upgrade(0); Command::MAX_BATCH_LEN + 1])).unwrap_err(),
+            "batch too long"
+        );
+        // Nobody owns any towers.
+        assert_eq!(
+            validate(Command::Batch(vec![upgrade(0), upgrade(1)])).unwrap_err(),
+            "tower not owned"
+        );
+    }
+
+    #[test]
+    fn validate_batch_atomic() {
+        use kodiak_common::actor_model::Map;
+
+        let player_id = PlayerId(NonZeroU32::new(1).unwrap());
+        let mut world = World::new();
+        let a = World::CENTER;
+        let b = a.neighbors().next().unwrap();
+        for tower_id in [a, b] {
+            let mut tower = Tower::with_type(TowerType::City);
+            tower.set_player_id(Some(player_id));
+            let (chunk_id, relative_tower_id) = tower_id.split();
+            Map::get_mut(&mut world.chunk, chunk_id)
+                .unwrap()
+                .actor
+                .insert(relative_tower_id, tower);
+        }
+        let mut counts = TowerArray::new();
+        counts.iter_mut().for_each(|(_, count)| *count = u16::MAX);
+        let validate = |commands: Vec<Command>| {
+            Command::Batch(commands).validate_batch(&world.chunk, player_id, &counts)
+        };
+        let upgrade = |tower_id: TowerId, tower_type: TowerType| Command::Upgrade {
+            tower_id,
+            tower_type,
+        };
+
+        let batch = vec![
+            upgrade(a, TowerType::Metropolis),
+            Command::QueueUpgrade {
+                tower_id: b,
+                tower_type: Some(TowerType::Metropolis),
+            },
+        ];
+        assert_eq!(validate(batch).map(|c| c.len()), Ok(2));
+
+        // One bad command rejects the whole batch.
+        assert_eq!(
+            validate(vec![
+                upgrade(a, TowerType::Metropolis),
+                upgrade(b, TowerType::Town)
+            ])
+            .unwrap_err(),
+            "cannot upgrade"
+        );
+        assert_eq!(
+            validate(vec![
+                upgrade(a, TowerType::Metropolis),
+                upgrade(a, TowerType::Metropolis)
+            ])
+            .unwrap_err(),
+            "duplicate upgrade"
+        );
+        // Each would have room on its own, but not together.
+        assert_eq!(
+            validate(vec![
+                upgrade(a, TowerType::Metropolis),
+                upgrade(b, TowerType::Metropolis)
+            ])
+            .unwrap_err(),
+            "no room for large"
+        );
+        assert_eq!(
+            validate(vec![Command::QueueUpgrade {
+                tower_id: a,
+                tower_type: Some(TowerType::Village),
+            }])
+            .unwrap_err(),
+            "unreachable upgrade"
+        );
+        assert_eq!(
+            validate(vec![Command::deploy_force_from_path(vec![a, a])]).unwrap_err(),
+            "duplicate tower in path"
+        );
+    }
+}
@@ -0,0 +1,100 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use crate::chunk::ChunkId;
+use kodiak_common::bitcode::{self, *};
+
+/// Deterministic, splittable pseudo random number generator (SplitMix64) for simulation code.
+/// Given the same seed, the server, clients, replays, and tests all get the same numbers.
+#[derive(Clone, Debug, Default, Hash, PartialEq, Eq, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SimRng {
+    state: u64,
+}
+
+impl SimRng {
+    const GOLDEN_GAMMA: u64 = 0x9e3779b97f4a7c15;
+
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// Derives an independent generator from this one and a `key`, without advancing this one.
+    pub fn split(&self, key: u64) -> Self {
+        Self::new(Self::mix(
+            self.state ^ Self::mix(key.wrapping_add(Self::GOLDEN_GAMMA)),
+        ))
+    }
+
+    /// A generator that is constant for `chunk_id`. Useful for staggering periodic events.
+    pub fn chunk(&self, chunk_id: ChunkId) -> Self {
+        self.split(u16::from_le_bytes([chunk_id.x, chunk_id.y]) as u64)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(Self::GOLDEN_GAMMA);
+        Self::mix(self.state)
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    pub fn next_u16(&mut self) -> u16 {
+        (self.next_u64() >> 48) as u16
+    }
+
+    fn mix(mut z: u64) -> u64 {
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+}
+
+#[cfg(any(test, feature = "server"))]
+impl kodiak_common::rand::RngCore for SimRng {
+    fn next_u32(&mut self) -> u32 {
+        SimRng::next_u32(self)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        SimRng::next_u64(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = SimRng::next_u64(self).to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), kodiak_common::rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chunk::ChunkId;
+    use crate::rng::SimRng;
+
+    #[test]
+    fn deterministic() {
+        let mut a = SimRng::new(42);
+        let mut b = SimRng::new(42);
+        for _ in 0..100 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+
+        let rng = SimRng::new(42);
+        let chunk_id = ChunkId::new(3, 4);
+        assert_eq!(rng.chunk(chunk_id), rng.chunk(chunk_id));
+        assert_ne!(rng.chunk(chunk_id), rng.chunk(ChunkId::new(4, 3)));
+
+        // Splitting doesn't advance.
+        let mut c = rng.clone();
+        let _ = c.split(1);
+        assert_eq!(c.next_u64(), rng.clone().next_u64());
+    }
+}
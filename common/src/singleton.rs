@@ -1,10 +1,13 @@
 // SPDX-FileCopyrightText: 2024 Softbear, Inc.
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
+use crate::rng::SimRng;
 use crate::ticks::Ticks;
+use crate::unit::Unit;
 use crate::world::Apply;
 use kodiak_common::actor_model::*;
 use kodiak_common::bitcode::{self, *};
+use kodiak_common::TicksRepr;
 
 #[derive(Copy, Clone, Debug, Hash, PartialEq, PartialOrd, Encode, Decode)]
 pub struct SingletonId;
@@ -15,8 +18,81 @@ impl ActorId for SingletonId {
 }
 
 #[derive(Clone, Debug, Default, Hash, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Singleton {
+    #[cfg_attr(feature = "serde", serde(with = "crate::ticks::serde_ticks"))]
     pub tick: Ticks,
+    /// Part of the actor model so that clients simulate with the same timing as the server.
+    pub timing: GameTiming,
+    /// Root of all simulation randomness. It is only ever split (see [`SimRng::chunk`]), never
+    /// advanced, so that derived generators like per-chunk tick offsets stay constant.
+    pub rng: SimRng,
+}
+
+/// World timing constants. Changing them allows faster (blitz) or slower (strategic) games.
+///
+/// The tick rate itself isn't configurable here, since it is the `4` in [`Ticks`], which kodiak
+/// uses to schedule ticks on both the client and the server. Scale the periods instead.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GameTiming {
+    /// How often unowned upgraded towers downgrade.
+    #[cfg_attr(feature = "serde", serde(with = "crate::ticks::serde_ticks"))]
+    pub downgrade_period: Ticks,
+    /// How often owned towers lose overflowing units.
+    #[cfg_attr(feature = "serde", serde(with = "crate::ticks::serde_ticks"))]
+    pub owned_diminish_period: Ticks,
+    /// How often unowned towers lose units.
+    #[cfg_attr(feature = "serde", serde(with = "crate::ticks::serde_ticks"))]
+    pub unowned_diminish_period: Ticks,
+    /// How long an EMP disables a tower.
+    #[cfg_attr(feature = "serde", serde(with = "crate::ticks::serde_ticks"))]
+    pub emp_delay: Ticks,
+    /// How many forces can be sent along the same road before the rest are lost to cramming.
+    pub max_outbound_forces: u8,
+}
+
+impl Default for GameTiming {
+    fn default() -> Self {
+        Self {
+            downgrade_period: Ticks::from_whole_secs(60),
+            owned_diminish_period: Ticks::from_whole_secs(30),
+            unowned_diminish_period: Ticks::from_whole_secs(10),
+            emp_delay: Ticks::from_whole_secs(Unit::EMP_SECONDS as TicksRepr),
+            max_outbound_forces: 8,
+        }
+    }
+}
+
+impl GameTiming {
+    /// Multiplies all periods by `numerator / denominator` e.g. `scaled(1, 2)` for a blitz game.
+    pub fn scaled(self, numerator: u16, denominator: u16) -> Self {
+        let scale = |ticks: Ticks| {
+            Ticks::from_repr(
+                (ticks.0 as u32 * numerator as u32 / denominator.max(1) as u32)
+                    .clamp(1, TicksRepr::MAX as u32) as TicksRepr,
+            )
+        };
+        Self {
+            downgrade_period: scale(self.downgrade_period),
+            owned_diminish_period: scale(self.owned_diminish_period),
+            unowned_diminish_period: scale(self.unowned_diminish_period),
+            emp_delay: scale(self.emp_delay),
+            ..self
+        }
+    }
+
+    /// Periods must be non-zero (since they are used with [`Ticks::every`]).
+    pub fn is_valid(&self) -> bool {
+        [
+            self.downgrade_period,
+            self.owned_diminish_period,
+            self.unowned_diminish_period,
+        ]
+        .into_iter()
+        .all(|period| period.0 != 0)
+            && self.max_outbound_forces != 0
+    }
 }
 
 impl Actor for Singleton {
@@ -24,10 +100,42 @@ impl Actor for Singleton {
 }
 
 #[derive(Clone, Debug, Encode, Decode)]
-pub enum SingletonInput {}
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SingletonInput {
+    SetTiming(GameTiming),
+}
 
 impl Message for SingletonInput {}
 
 impl<C> Apply<SingletonInput, C> for Singleton {
-    fn apply(&mut self, _: &SingletonInput, _: &mut C) {}
+    fn apply(&mut self, u: &SingletonInput, _: &mut C) {
+        match u {
+            SingletonInput::SetTiming(timing) => {
+                debug_assert!(timing.is_valid(), "{timing:?}");
+                if timing.is_valid() {
+                    self.timing = *timing;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::singleton::GameTiming;
+    use crate::ticks::Ticks;
+
+    #[test]
+    fn timing() {
+        let timing = GameTiming::default();
+        assert!(timing.is_valid());
+        assert_eq!(timing.scaled(1, 1), timing);
+
+        let blitz = timing.scaled(1, 2);
+        assert!(blitz.is_valid());
+        assert_eq!(blitz.downgrade_period, Ticks::from_whole_secs(30));
+        assert_eq!(blitz.max_outbound_forces, timing.max_outbound_forces);
+
+        assert!(timing.scaled(0, 1).is_valid());
+    }
 }
@@ -2,3 +2,19 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
 pub type Ticks = kodiak_common::GenTicks<4>;
+
+/// (De)serializes [`Ticks`] as a whole number of ticks, for use with `#[serde(with = "...")]`.
+#[cfg(feature = "serde")]
+pub(crate) mod serde_ticks {
+    use super::Ticks;
+    use kodiak_common::TicksRepr;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(ticks: &Ticks, serializer: S) -> Result<S::Ok, S::Error> {
+        ticks.0.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Ticks, D::Error> {
+        TicksRepr::deserialize(deserializer).map(Ticks::from_repr)
+    }
+}
@@ -14,7 +14,7 @@ pub use map::TowerMap;
 use num_enum::{IntoPrimitive, TryFromPrimitive};
 pub use rectangle::TowerRectangle;
 pub use set::TowerSet;
-use std::num::NonZeroU8;
+use std::num::{NonZeroU16, NonZeroU8};
 use strum::{Display, EnumIter, EnumString, IntoEnumIterator};
 
 #[cfg(any(test, feature = "server"))]
@@ -47,12 +47,13 @@ pub(crate) fn fast_integer_sqrt(y: u64) -> u32 {
 }
 
 #[derive(Clone, Debug, Hash, Eq, PartialEq, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Tower {
     pub player_id: Option<PlayerId>,
     pub units: Units,
     pub tower_type: TowerType,
     /// Delay until usable in ticks. Currently used to implement upgrading.
-    pub delay: Option<NonZeroU8>,
+    pub delay: Option<NonZeroU16>,
     /// These forces will eventually arrive and be processed.
     pub inbound_forces: Vec<Force>,
     /// Mirrors inbound forces of opposing tower. When they would arrive, they are discarded.
@@ -224,6 +225,7 @@ impl Tower {
     TryFromPrimitive,
     TowerTypeData,
 )]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 #[tower(sensor_radius = 12)]
 #[capacity(Ruler = 1)]
@@ -251,10 +253,10 @@ pub enum TowerType {
     #[prerequisite(Mine, 30, Headquarters = 1, Ews = 1)]
     #[capacity(Soldier = 6, Shield = 40)]
     Bunker,
-    //#[prerequisite(Headquarters, 80, Bunker = 10, Headquarters = 15, Projector = 20)]
-    //#[capacity(Soldier = 8, Tank = 2, Shield = 60)]
-    //#[generate(Shield = 3)]
-    //Capitol,
+    #[prerequisite(Headquarters, 80, Bunker = 10, Headquarters = 15, Projector = 20)]
+    #[capacity(Soldier = 8, Tank = 2, Shield = 60)]
+    #[generate(Shield = 3)]
+    Capitol,
     #[prerequisite(Factory, 30, Mine = 3)]
     #[capacity(Soldier = 4, Tank = 2, Shield = 15)]
     Centrifuge,
@@ -281,24 +283,24 @@ pub enum TowerType {
     #[capacity(Chopper = 3, Soldier = 4, Tank = 2, Shield = 15)]
     #[generate(Chopper = 30)]
     Helipad,
-    //#[tower(sensor_radius = 48)]
-    //#[prerequisite(Silo, 180, City = 25, Silo = 15, Rocket = 15)]
-    //#[capacity(Shield = 40)]
-    //#[generate(Shield = 3)]
-    //Icbm,
-    //#[tower(sensor_radius = 48)]
-    //#[prerequisite(Reactor, 180, City = 25, Reactor = 15, Satellite = 15)]
-    //#[capacity(Shield = 40)]
-    //#[generate(Shield = 3)]
-    //Laser,
+    #[tower(sensor_radius = 48)]
+    #[prerequisite(Silo, 180, City = 25, Silo = 15, Rocket = 15)]
+    #[capacity(Missile = 1, Shield = 40)]
+    #[generate(Missile = 240, Shield = 3)]
+    Icbm,
+    #[tower(sensor_radius = 48)]
+    #[prerequisite(Reactor, 180, City = 25, Reactor = 15, Satellite = 15)]
+    #[capacity(Beam = 3, Shield = 40)]
+    #[generate(Beam = 20, Shield = 3)]
+    Laser,
     #[prerequisite(Rocket, 30, Airfield = 2)]
     #[capacity(Emp = 1, Shield = 15)]
     #[generate(Emp = 80)]
     Launcher,
-    //#[tower(score_weight = 12)]
-    //#[prerequisite(City, 80, City = 10, Town = 15, Village = 20)]
-    //#[capacity(Fighter = 2, Soldier = 6, Tank = 2, Shield = 20)]
-    //Metropolis,
+    #[tower(score_weight = 12)]
+    #[prerequisite(City, 80, City = 10, Town = 15, Village = 20)]
+    #[capacity(Fighter = 2, Soldier = 6, Tank = 2, Shield = 20)]
+    Metropolis,
     #[tower(score_weight = 2)]
     #[capacity(Soldier = 4, Tank = 2, Shield = 15)]
     Mine,
@@ -350,12 +352,13 @@ pub enum TowerType {
 pub type TowerArray<V> = EnumArray<TowerType, V, { std::mem::variant_count::<TowerType>() }>;
 
 impl TowerType {
+    /// Large towers are drawn at twice the scale, so they may not neighbor each other (see
+    /// [`WorldChunks::has_room_for_large`][`crate::world::WorldChunks::has_room_for_large`]).
     pub fn is_large(self) -> bool {
-        false
-        //matches!(
-        //    self,
-        //    Self::Capitol | Self::Icbm | Self::Laser | Self::Metropolis
-        //)
+        matches!(
+            self,
+            Self::Capitol | Self::Icbm | Self::Laser | Self::Metropolis
+        )
     }
 
     pub fn scale(self) -> u8 {
@@ -370,6 +373,23 @@ impl TowerType {
         Self::iter().filter(move |&other| self.can_upgrade_to(other))
     }
 
+    /// Returns the next step of the upgrade chain from this tower type to `target`, or [`None`] if
+    /// `target` isn't reachable by upgrading.
+    pub fn next_upgrade_towards(self, mut target: Self) -> Option<Self> {
+        while let Some(downgrade) = target.downgrade() {
+            if downgrade == self {
+                return Some(target);
+            }
+            target = downgrade;
+        }
+        None
+    }
+
+    /// Like [`Self::upgrades`] but includes upgrades that take more than one step.
+    pub fn eventual_upgrades(self) -> impl Iterator<Item = Self> + 'static {
+        Self::iter().filter(move |&other| self.next_upgrade_towards(other).is_some())
+    }
+
     pub fn prerequisites(self) -> impl Iterator<Item = (Self, u8)> {
         TowerType::iter().filter_map(move |tower_type| {
             NonZeroU8::new(self.prerequisite(tower_type)).map(|u| (tower_type, u.get()))
@@ -403,8 +423,8 @@ impl TowerType {
         use TowerType::*;
         // Division by 3 should optimize to mul + shr
         match self {
-            Bunker /* | Capitol */ => damage / 3,
-            Headquarters /* | Icbm | Laser */ => damage * 2 / 3,
+            Bunker | Capitol => damage / 3,
+            Headquarters | Icbm | Laser => damage * 2 / 3,
             _ => damage,
         }
     }
@@ -473,9 +493,11 @@ impl Distribution<TowerType> for kodiak_common::rand::distributions::Standard {
 
 #[cfg(test)]
 mod tests {
+    use crate::rng::SimRng;
     use crate::tower::{fast_integer_sqrt, integer_sqrt, Tower, TowerId, TowerType};
     use crate::unit::Unit;
-    use kodiak_common::rand::{thread_rng, Rng};
+    use kodiak_common::rand::Rng;
+    use std::num::NonZeroU16;
     use test::{black_box, Bencher};
 
     #[test]
@@ -509,12 +531,62 @@ mod tests {
             TowerType::Silo.ranged_distance(),
             Unit::Nuke.ranged_distance()
         );
+        assert_eq!(
+            TowerType::Icbm.ranged_distance(),
+            Unit::Missile.ranged_distance()
+        );
+        assert_eq!(
+            TowerType::Laser.ranged_distance(),
+            Unit::Beam.ranged_distance()
+        );
         assert_eq!(
             TowerType::Town.ranged_distance(),
             Unit::Soldier.ranged_distance()
         );
     }
 
+    #[test]
+    fn large() {
+        for tower_type in TowerType::iter() {
+            if tower_type.is_large() {
+                assert_eq!(tower_type.scale(), 2);
+                // Large towers are never generated, only upgraded to.
+                assert!(tower_type.downgrade().is_some());
+                // Fits in a tower delay.
+                assert!(NonZeroU16::new(tower_type.delay().0.try_into().unwrap()).is_some());
+            } else {
+                assert_eq!(tower_type.scale(), 1);
+            }
+        }
+    }
+
+    #[test]
+    fn next_upgrade_towards() {
+        assert_eq!(
+            TowerType::Village.next_upgrade_towards(TowerType::City),
+            Some(TowerType::Town)
+        );
+        assert_eq!(
+            TowerType::Town.next_upgrade_towards(TowerType::City),
+            Some(TowerType::City)
+        );
+        assert_eq!(TowerType::City.next_upgrade_towards(TowerType::City), None);
+        assert_eq!(
+            TowerType::Village.next_upgrade_towards(TowerType::Silo),
+            None
+        );
+
+        for tower_type in TowerType::iter() {
+            for upgrade in tower_type.upgrades() {
+                assert_eq!(tower_type.next_upgrade_towards(upgrade), Some(upgrade));
+            }
+            for upgrade in tower_type.eventual_upgrades() {
+                assert_eq!(upgrade.basis(), tower_type.basis());
+                assert!(upgrade.level() > tower_type.level());
+            }
+        }
+    }
+
     #[test]
     fn test_integer_sqrt() {
         assert_eq!(integer_sqrt(u64::MAX), u32::MAX);
@@ -549,7 +621,8 @@ mod tests {
     }
 
     fn sqrt_test_data() -> [u32; 64] {
-        [(); 64].map(|_| thread_rng().gen())
+        let mut rng = SimRng::new(0);
+        [(); 64].map(|_| rng.gen())
     }
 
     #[bench]
@@ -65,8 +65,9 @@ mod tests {
 
     #[test]
     fn test_connecitivity() {
+        use crate::rng::SimRng;
         use kodiak_common::rand::prelude::*;
-        let mut rng = thread_rng();
+        let mut rng = SimRng::new(0);
 
         for _ in 0..1000 {
             let mut tower_id = TowerId(
@@ -235,6 +235,33 @@ impl TowerId {
     }
 }
 
+/// Human-readable representation, e.g. `{"x": 12, "y": 34}`.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+#[serde(rename = "TowerId")]
+struct TowerIdRepr {
+    x: u16,
+    y: u16,
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for TowerId {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        TowerIdRepr {
+            x: self.x,
+            y: self.y,
+        }
+        .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for TowerId {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        TowerIdRepr::deserialize(deserializer).map(|TowerIdRepr { x, y }| Self::new(x, y))
+    }
+}
+
 impl Deref for TowerId {
     type Target = U16Vec2;
 
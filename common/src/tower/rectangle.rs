@@ -8,6 +8,7 @@ use kodiak_common::bitcode::{self, *};
 use kodiak_common::U16Vec2;
 
 #[derive(Copy, Clone, Debug, Eq, PartialEq, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TowerRectangle {
     pub bottom_left: TowerId,
     pub top_right: TowerId,
@@ -26,6 +26,7 @@ use strum::{EnumIter, IntoEnumIterator};
     IntoPrimitive,
     TryFromPrimitive,
 )]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[repr(u8)]
 pub enum Unit {
     /// Shield is least flexible, so consume it first.
@@ -48,9 +49,15 @@ pub enum Unit {
     Emp,
     /// Nuke is a last resort.
     Nuke,
-    /// Ruler is a last resort (note: never in the same combatants as nuke).
-    /// Is [`Unit::LAST`] because it's the last unit in the enum.
+    /// Ruler is a last resort (note: never in the same combatants as nuke or missile).
     Ruler,
+    // Newer units are appended to keep the encoding of existing units stable. Single units are
+    // never combined, so their order only matters for `replaces_single`.
+    /// Beams burn through regular units, but are weak against towers.
+    Beam,
+    /// Missile is a longer range nuke.
+    /// Is [`Unit::LAST`] because it's the last unit in the enum.
+    Missile,
 }
 
 /// [`Unit`]s are into several categories:
@@ -89,6 +96,8 @@ pub enum Range {
     Short,
     Medium,
     Long,
+    /// Only for ultimate weapons.
+    Extreme,
 }
 
 impl Range {
@@ -98,6 +107,7 @@ impl Range {
             Self::Short => 5,
             Self::Medium => 8,
             Self::Long => 11,
+            Self::Extreme => 16,
         }) * World::MAX_ROAD_LENGTH
     }
 }
@@ -107,7 +117,7 @@ impl Unit {
     pub const FIRST_ALWAYS: Unit = Unit::Shield;
     pub const FIRST_MANY: Unit = Unit::Fighter;
     pub const FIRST_SINGLE: Unit = Unit::Shell;
-    pub const LAST: Unit = Unit::Ruler;
+    pub const LAST: Unit = Unit::Missile;
     pub const INFINITE_DAMAGE: u8 = 31; // Units can do 1..=30 damage or infinite.
     pub const EMP_SECONDS: u8 = 60;
 
@@ -123,6 +133,14 @@ impl Unit {
         }
     }
 
+    /// Returns true if this single unit may replace `other`, which must also be single. Rulers
+    /// are never replaced.
+    pub(crate) fn replaces_single(self, other: Self) -> bool {
+        debug_assert_eq!(self.category(), UnitCategory::Single);
+        debug_assert_eq!(other.category(), UnitCategory::Single);
+        self == Self::Ruler || (other != Self::Ruler && self >= other)
+    }
+
     /// Can this unit overflow a tower's capacity (temporarily).
     pub fn max_overflow(self) -> usize {
         match self {
@@ -148,7 +166,8 @@ impl Unit {
             // TODO: Should only do 2 air damage
             // (https://discord.com/channels/847143438939717663/933850279537967204/1018971807979688078)
             Self::Chopper if field == Field::Air => 3,
-            Self::Nuke => Self::INFINITE_DAMAGE,
+            Self::Nuke | Self::Missile => Self::INFINITE_DAMAGE,
+            Self::Beam => 20,
             Self::Shell => 3, // TODO shell shouldn't hit regular units.
             _ => 1,
         }
@@ -177,7 +196,14 @@ impl Unit {
     pub fn field(self, overflow: bool, in_force: bool, any_air: bool) -> Field {
         match self {
             Self::Shield if any_air => Field::Air, // Shield's field is max field in group.
-            Self::Bomber | Self::Chopper | Self::Fighter | Self::Shell | Self::Emp | Self::Nuke
+            Self::Bomber
+            | Self::Chopper
+            | Self::Fighter
+            | Self::Shell
+            | Self::Beam
+            | Self::Emp
+            | Self::Nuke
+            | Self::Missile
                 if overflow || in_force =>
             {
                 Field::Air
@@ -203,6 +229,7 @@ impl Unit {
         Some(match self {
             Self::Nuke | Self::Shell => Range::Short,
             Self::Emp => Range::Medium,
+            Self::Beam | Self::Missile => Range::Extreme,
             _ => return None,
         })
     }
@@ -220,8 +247,9 @@ impl Unit {
     /// 0 means immobile.
     pub fn speed(self, tower_type: Option<TowerType>) -> Speed {
         match self {
-            Self::Bomber | Self::Fighter | Self::Chopper | Self::Shell => Speed::Fast,
-            Self::Nuke | Self::Tank => Speed::Slow,
+            Self::Bomber | Self::Fighter | Self::Chopper | Self::Shell | Self::Beam => Speed::Fast,
+            // Slow enough to be intercepted, despite its range.
+            Self::Nuke | Self::Missile | Self::Tank => Speed::Slow,
             Self::Shield => {
                 if matches!(tower_type, None | Some(TowerType::Projector)) {
                     Speed::Fast
@@ -277,8 +305,10 @@ mod tests {
         assert_eq!(Unit::Tank.category(), UnitCategory::Many);
         assert_eq!(Unit::Soldier.category(), UnitCategory::Many);
         assert_eq!(Unit::Shell.category(), UnitCategory::Single);
+        assert_eq!(Unit::Beam.category(), UnitCategory::Single);
         assert_eq!(Unit::Emp.category(), UnitCategory::Single);
         assert_eq!(Unit::Nuke.category(), UnitCategory::Single);
+        assert_eq!(Unit::Missile.category(), UnitCategory::Single);
         assert_eq!(Unit::Ruler.category(), UnitCategory::Single);
     }
 }
@@ -42,7 +42,30 @@ impl UnitsEither {
     }
 }
 
-// TODO serde.
+/// Human-readable representation, e.g. `{"Shield": 10, "Soldier": 3}`.
+#[cfg(feature = "serde")]
+impl serde::Serialize for Units {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Units {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // Sorted because single units must be added in order.
+        let map = std::collections::BTreeMap::<Unit, usize>::deserialize(deserializer)?;
+        let mut units = Self::default();
+        for (unit, count) in map {
+            if count != 0 && units.add(unit, count) != count {
+                return Err(serde::de::Error::custom(format_args!(
+                    "cannot hold {count} {unit:?}"
+                )));
+            }
+        }
+        Ok(units)
+    }
+}
 
 impl Units {
     pub const CAPACITY: usize = u8::MAX as usize;
@@ -91,8 +114,7 @@ impl Units {
                     self.either = UnitsEither::Single(unit, added_u8)
                 } else {
                     if let UnitsEither::Single(u, c) = &mut self.either {
-                        debug_assert!(unit >= *u);
-                        debug_assert!(*u != Unit::Ruler);
+                        debug_assert!(unit.replaces_single(*u));
                         if *u == unit {
                             // Add to same unit.
                             *c += added_u8;
@@ -179,7 +201,11 @@ impl Units {
             }
             UnitCategory::Single => {
                 // Singles can't override other singles of higher priority.
-                if self.either.single().is_some_and(|(u, _)| u > unit) {
+                if self
+                    .either
+                    .single()
+                    .is_some_and(|(u, _)| !unit.replaces_single(u))
+                {
                     return 0;
                 }
             }
@@ -346,17 +372,31 @@ impl IntoIterator for Units {
 
 #[cfg(test)]
 mod tests {
+    use crate::rng::SimRng;
     use crate::tower::TowerType;
     use crate::unit::Unit;
     use crate::units::Units;
     use kodiak_common::rand::prelude::IteratorRandom;
-    use kodiak_common::rand::{thread_rng, Rng};
+    use kodiak_common::rand::Rng;
 
     #[test]
     fn size_of() {
         size_of!(Units)
     }
 
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serde() {
+        let mut units = Units::default();
+        units.add(Unit::Shield, 10);
+        units.add(Unit::Soldier, 3);
+        let json = serde_json::to_string(&units).unwrap();
+        assert_eq!(json, r#"{"Shield":10,"Soldier":3}"#);
+        assert_eq!(serde_json::from_str::<Units>(&json).unwrap(), units);
+
+        assert!(serde_json::from_str::<Units>(r#"{"Shield":1000}"#).is_err());
+    }
+
     #[test]
     fn serialized_size() {
         serialized_size_value!("Units(default)", Units::default());
@@ -377,6 +417,17 @@ mod tests {
         assert_eq!(units.add(Unit::Soldier, 5), 5);
         assert_eq!(units.add(Unit::Ruler, 1), 1);
         assert_eq!(units.available(Unit::Soldier), 0);
+
+        // Units after the ruler in the enum still can't replace it.
+        for unit in [Unit::Beam, Unit::Missile] {
+            assert_eq!(units.add(unit, 1), 0);
+            assert!(units.has_ruler());
+        }
+
+        let mut units = Units::default();
+        assert_eq!(units.add(Unit::Beam, 2), 2);
+        assert_eq!(units.add(Unit::Ruler, 1), 1);
+        assert_eq!(units.available(Unit::Beam), 0);
     }
 
     #[test]
@@ -405,7 +456,7 @@ mod tests {
 
     #[test]
     fn fuzz() {
-        let mut rng = thread_rng();
+        let mut rng = SimRng::new(0);
         for _ in 0..1000 {
             let mut units = Units::default();
             for _ in 0..20 {
@@ -0,0 +1,290 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use crate::chunk::{ChunkId, ChunkInput};
+use crate::tower::{TowerArray, TowerId, TowerType};
+use crate::world::WorldChunks;
+use fxhash::FxHashMap;
+use kodiak_common::PlayerId;
+
+/// A player's queued upgrades. Each tower is upgraded one step at a time towards its target
+/// [`TowerType`], as soon as it is active and the next step has its prerequisites.
+/// Reported to the player in [`NonActor::queued_upgrades`][`crate::protocol::NonActor::queued_upgrades`].
+#[derive(Debug, Default)]
+pub struct UpgradeQueue {
+    targets: FxHashMap<TowerId, TowerType>,
+}
+
+impl UpgradeQueue {
+    /// Prevents players from using unbounded memory.
+    pub const MAX_LEN: usize = 128;
+
+    /// Sets (or clears if [`None`]) the target of `tower_id`. Returns false if the queue is full.
+    pub fn set(&mut self, tower_id: TowerId, target: Option<TowerType>) -> bool {
+        if let Some(target) = target {
+            if self.targets.len() >= Self::MAX_LEN && !self.targets.contains_key(&tower_id) {
+                return false;
+            }
+            self.targets.insert(tower_id, target);
+        } else {
+            self.targets.remove(&tower_id);
+        }
+        true
+    }
+
+    pub fn get(&self, tower_id: TowerId) -> Option<TowerType> {
+        self.targets.get(&tower_id).copied()
+    }
+
+    pub fn clear(&mut self) {
+        self.targets.clear();
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.targets.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.targets.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (TowerId, TowerType)> + '_ {
+        self.targets.iter().map(|(&k, &v)| (k, v))
+    }
+
+    /// Issues an [`ChunkInput::UpgradeTower`] for every queued tower that can take its next step.
+    /// Forgets targets that were reached or became unreachable (e.g. the tower was lost).
+    ///
+    /// Must be called at most once per tick, since the issued inputs haven't been applied yet.
+    pub fn poll(
+        &mut self,
+        chunks: &WorldChunks,
+        player_id: PlayerId,
+        tower_counts: &TowerArray<u16>,
+        mut issue: impl FnMut(ChunkId, ChunkInput),
+    ) {
+        self.targets.retain(|&tower_id, &mut target| {
+            let Some(tower) = chunks.get(tower_id) else {
+                return false;
+            };
+            if tower.player_id != Some(player_id) {
+                return false;
+            }
+            let Some(next) = tower.tower_type.next_upgrade_towards(target) else {
+                // Reached (or can never reach) the target.
+                return false;
+            };
+            if tower.active()
+                && next.has_prerequisites(tower_counts)
+                && (!next.is_large() || chunks.has_room_for_large(tower_id))
+            {
+                let (chunk_id, tower_id) = tower_id.split();
+                issue(
+                    chunk_id,
+                    ChunkInput::UpgradeTower {
+                        tower_id,
+                        tower_type: next,
+                    },
+                );
+            }
+            true
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::chunk::ChunkInput;
+    use crate::tower::{Tower, TowerArray, TowerId, TowerType};
+    use crate::upgrade_queue::UpgradeQueue;
+    use crate::world::{World, WorldChunks};
+    use kodiak_common::actor_model::Map;
+    use kodiak_common::PlayerId;
+    use std::num::{NonZeroU16, NonZeroU32};
+
+    fn set_tower(chunks: &mut WorldChunks, tower_id: TowerId, tower: Option<Tower>) {
+        let (chunk_id, relative_tower_id) = tower_id.split();
+        let chunk = &mut Map::get_mut(chunks, chunk_id).unwrap().actor;
+        if chunk.get(relative_tower_id).is_some() {
+            chunk.remove(relative_tower_id);
+        }
+        if let Some(tower) = tower {
+            chunk.insert(relative_tower_id, tower);
+        }
+    }
+
+    fn owned(tower_type: TowerType, player_id: PlayerId) -> Tower {
+        let mut tower = Tower::with_type(tower_type);
+        tower.set_player_id(Some(player_id));
+        tower
+    }
+
+    fn poll(
+        queue: &mut UpgradeQueue,
+        chunks: &WorldChunks,
+        counts: &TowerArray<u16>,
+    ) -> Vec<TowerType> {
+        let player_id = PlayerId(NonZeroU32::new(1).unwrap());
+        let mut issued = vec![];
+        queue.poll(chunks, player_id, counts, |_, input| {
+            let ChunkInput::UpgradeTower { tower_type, .. } = input else {
+                panic!("unexpected {input:?}");
+            };
+            issued.push(tower_type);
+        });
+        issued
+    }
+
+    #[test]
+    fn prerequisites() {
+        let player_id = PlayerId(NonZeroU32::new(1).unwrap());
+        let mut world = World::new();
+        let tower_id = World::CENTER;
+        set_tower(
+            &mut world.chunk,
+            tower_id,
+            Some(owned(TowerType::Village, player_id)),
+        );
+
+        let mut queue = UpgradeQueue::default();
+        assert!(queue.set(tower_id, Some(TowerType::City)));
+
+        // Town needs a Generator and 3 Villages.
+        let mut counts = TowerArray::<u16>::default();
+        assert_eq!(poll(&mut queue, &world.chunk, &counts), vec![]);
+        assert_eq!(queue.get(tower_id), Some(TowerType::City));
+
+        counts[TowerType::Generator] = 1;
+        counts[TowerType::Village] = 3;
+        assert_eq!(
+            poll(&mut queue, &world.chunk, &counts),
+            vec![TowerType::Town]
+        );
+
+        // Waits while the upgrade is in progress.
+        let mut town = owned(TowerType::Town, player_id);
+        town.delay = NonZeroU16::new(1);
+        set_tower(&mut world.chunk, tower_id, Some(town));
+        counts = TowerArray::new();
+        counts.iter_mut().for_each(|(_, count)| *count = u16::MAX);
+        assert_eq!(poll(&mut queue, &world.chunk, &counts), vec![]);
+
+        set_tower(
+            &mut world.chunk,
+            tower_id,
+            Some(owned(TowerType::Town, player_id)),
+        );
+        assert_eq!(
+            poll(&mut queue, &world.chunk, &counts),
+            vec![TowerType::City]
+        );
+
+        // Reached.
+        set_tower(
+            &mut world.chunk,
+            tower_id,
+            Some(owned(TowerType::City, player_id)),
+        );
+        assert_eq!(poll(&mut queue, &world.chunk, &counts), vec![]);
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn tower_lost() {
+        let player_id = PlayerId(NonZeroU32::new(1).unwrap());
+        let other = PlayerId(NonZeroU32::new(2).unwrap());
+        let mut world = World::new();
+        let tower_id = World::CENTER;
+        set_tower(
+            &mut world.chunk,
+            tower_id,
+            Some(owned(TowerType::Village, other)),
+        );
+
+        let mut queue = UpgradeQueue::default();
+        queue.set(tower_id, Some(TowerType::Town));
+        assert_eq!(
+            poll(&mut queue, &world.chunk, &TowerArray::default()),
+            vec![]
+        );
+        assert!(queue.is_empty());
+
+        // Destroyed.
+        set_tower(&mut world.chunk, tower_id, None);
+        queue.set(tower_id, Some(TowerType::Town));
+        assert_eq!(
+            poll(&mut queue, &world.chunk, &TowerArray::default()),
+            vec![]
+        );
+        assert!(queue.is_empty());
+
+        // Can't ever upgrade to target.
+        set_tower(
+            &mut world.chunk,
+            tower_id,
+            Some(owned(TowerType::Village, player_id)),
+        );
+        queue.set(tower_id, Some(TowerType::Silo));
+        assert_eq!(
+            poll(&mut queue, &world.chunk, &TowerArray::default()),
+            vec![]
+        );
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn max_len() {
+        let mut queue = UpgradeQueue::default();
+        for x in 0..UpgradeQueue::MAX_LEN as u16 {
+            assert!(queue.set(TowerId::new(x, 0), Some(TowerType::Town)));
+        }
+        assert_eq!(queue.len(), UpgradeQueue::MAX_LEN);
+
+        let extra = TowerId::new(UpgradeQueue::MAX_LEN as u16, 0);
+        assert!(!queue.set(extra, Some(TowerType::Town)));
+        assert_eq!(queue.get(extra), None);
+
+        // Changing or clearing existing targets is still allowed.
+        assert!(queue.set(TowerId::new(0, 0), Some(TowerType::City)));
+        assert_eq!(queue.get(TowerId::new(0, 0)), Some(TowerType::City));
+        assert!(queue.set(TowerId::new(0, 0), None));
+        assert!(queue.set(extra, Some(TowerType::Town)));
+        assert_eq!(queue.len(), UpgradeQueue::MAX_LEN);
+    }
+
+    #[test]
+    fn large_room() {
+        let player_id = PlayerId(NonZeroU32::new(1).unwrap());
+        let mut world = World::new();
+        let tower_id = World::CENTER;
+        let neighbor = tower_id.neighbors().next().unwrap();
+        set_tower(
+            &mut world.chunk,
+            tower_id,
+            Some(owned(TowerType::City, player_id)),
+        );
+        set_tower(
+            &mut world.chunk,
+            neighbor,
+            Some(owned(TowerType::Capitol, player_id)),
+        );
+
+        let mut counts = TowerArray::new();
+        counts.iter_mut().for_each(|(_, count)| *count = u16::MAX);
+
+        let mut queue = UpgradeQueue::default();
+        queue.set(tower_id, Some(TowerType::Metropolis));
+        assert_eq!(poll(&mut queue, &world.chunk, &counts), vec![]);
+        assert_eq!(queue.get(tower_id), Some(TowerType::Metropolis));
+
+        set_tower(
+            &mut world.chunk,
+            neighbor,
+            Some(owned(TowerType::Village, player_id)),
+        );
+        assert_eq!(
+            poll(&mut queue, &world.chunk, &counts),
+            vec![TowerType::Metropolis]
+        );
+    }
+}
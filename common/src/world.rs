@@ -2,8 +2,10 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
 use crate::chunk::*;
+use crate::force::Path;
 use crate::info::*;
 use crate::player::*;
+use crate::rng::SimRng;
 use crate::singleton::*;
 use crate::tower::{integer_sqrt, TowerId};
 use kodiak_common::actor_model::*;
@@ -12,15 +14,18 @@ use kodiak_common::{
     apply, apply_inputs, define_actor_state, define_events, define_world, singleton, singleton_mut,
     PlayerId,
 };
-use std::collections::BTreeMap;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
+mod sparse;
 mod towers;
+pub use sparse::SparseChunkMap;
 pub use towers::{ChunkMap, WorldChunks};
 
 // TODO find better spot for this.
 impl ActorId for ChunkId {
     type DenseMap<T> = ChunkMap<T>;
-    type SparseMap<T> = BTreeMap<Self, T>; // TODO SparseChunkMap
+    type SparseMap<T> = SparseChunkMap<T>;
     type Map<T> = SortedVecMap<Self, T>;
 }
 
@@ -29,27 +34,201 @@ impl Actor for Chunk {
     const KEEPALIVE: u8 = 16;
 }
 
-define_events!(Chunk, Server, ChunkMaintenance, ChunkInput; Encode, Decode);
-define_events!(Chunk, ChunkId, ChunkHaltEvent, ChunkEvent; Encode, Decode);
-define_actor_state!(Chunk, Server, ChunkId; Encode, Decode);
-define_events!(Player, Server, PlayerMaintainance, PlayerInput; Encode, Decode);
-define_actor_state!(Player, Server; Encode, Decode);
-define_events!(Singleton, Server, SingletonInput; Encode, Decode);
-define_actor_state!(Singleton, Server; Encode, Decode);
-define_world!((), Chunk, Player, Singleton; Encode, Decode); // todo cksum
+/// Defines the actor model's generated types (such as [`ActorUpdate`]) with `$derive`s.
+macro_rules! define_actor_model {
+    ($($derive:ident),*) => {
+        define_events!(Chunk, Server, ChunkMaintenance, ChunkInput; $($derive),*);
+        define_events!(Chunk, ChunkId, ChunkHaltEvent, ChunkEvent; $($derive),*);
+        define_actor_state!(Chunk, Server, ChunkId; $($derive),*);
+        define_events!(Player, Server, PlayerMaintainance, PlayerInput; $($derive),*);
+        define_actor_state!(Player, Server; $($derive),*);
+        define_events!(Singleton, Server, SingletonInput; $($derive),*);
+        define_actor_state!(Singleton, Server; $($derive),*);
+        define_world!((), Chunk, Player, Singleton; $($derive),*); // todo cksum
+    };
+}
+
+#[cfg(not(feature = "serde"))]
+define_actor_model!(Encode, Decode);
+#[cfg(feature = "serde")]
+define_actor_model!(Encode, Decode, Serialize, Deserialize);
 
 impl WorldTick<OnInfo<'_>> for World {
     fn tick_before_inputs(&mut self, context: &mut OnInfo<'_>) {
+        self.tick_before_inputs_inner(cfg!(feature = "parallel"), context);
+    }
+
+    fn tick_after_inputs(&mut self, context: &mut OnInfo<'_>) {
+        // We apply chunk events after inputs since `ChunkInput`s may create `ChunkEvent`s.
+        // TODO detect if events weren't applied in debug mode.
+        apply!(self, Chunk, ChunkId, ChunkEvent, context);
+    }
+
+    fn tick_client(&mut self, context: &mut OnInfo<'_>) {
+        apply_inputs!(self, Chunk, ChunkMaintenance, context);
+        apply_inputs!(self, Player, PlayerMaintainance, context);
+        self.tick_before_inputs(context);
+        {
+            let mut context = OnChunkEvent::new(&mut *context);
+            apply_inputs!(self, Chunk, ChunkInput, &mut context);
+            self.extend(context.into_events());
+        }
+        apply_inputs!(self, Player, PlayerInput, context);
+        apply_inputs!(self, Singleton, SingletonInput, context);
+        self.tick_after_inputs(context);
+    }
+}
+
+impl World {
+    pub const MAX_ROAD_LENGTH: u32 = 5;
+    pub const MAX_ROAD_LENGTH_SQUARED: u64 = (Self::MAX_ROAD_LENGTH as u64 + 1).pow(2) - 1;
+    pub const MAX_PATH_ROADS: usize = 16;
+
+    pub const CENTER: TowerId =
+        TowerId::new(WorldChunks::SIZE as u16 / 2, WorldChunks::SIZE as u16 / 2);
+
+    /// [`WorldTick::tick_before_inputs`], ticking chunks on a thread pool if `parallel`, which
+    /// requires the `parallel` feature.
+    fn tick_before_inputs_inner(&mut self, parallel: bool, context: &mut OnInfo<'_>) {
         let Some(singleton) = singleton_mut!(self) else {
             return;
         };
         singleton.tick = singleton.tick.next();
 
         // TODO move to halt.rs
+        let halt_events = self.halt_events();
+        self.halted_supply_line_infos(&halt_events, context);
+        self.extend(halt_events);
+        apply!(self, Chunk, ChunkId, ChunkHaltEvent, context);
+
+        for (_, state) in Map::iter_mut(&mut self.player) {
+            state.actor.new_alliances.clear();
+        }
+        for (_, state) in Map::iter_mut(&mut self.chunk) {
+            state.actor.towers_destroyed = false;
+        }
+
+        #[cfg(feature = "parallel")]
+        let chunk_events = if parallel {
+            self.tick_chunks_parallel(context)
+        } else {
+            self.tick_chunks_serial(context)
+        };
+        #[cfg(not(feature = "parallel"))]
+        let chunk_events = {
+            debug_assert!(!parallel);
+            self.tick_chunks_serial(context)
+        };
+        self.extend(chunk_events);
+    }
+
+    /// Ticks every chunk in order, returning their events.
+    fn tick_chunks_serial(
+        &mut self,
+        context: &mut OnInfo<'_>,
+    ) -> Vec<(ChunkId, (ChunkId, ChunkEvent))> {
+        let singleton = singleton!(self).unwrap();
+        let mut chunk_events = vec![];
+        for (chunk_id, state) in Map::iter_mut(&mut self.chunk) {
+            let chunk: &mut Chunk = &mut state.actor;
+            chunk.tick(
+                chunk_id,
+                |player_id| Self::player_inner(&self.player, player_id),
+                singleton,
+                |dst, e| chunk_events.push((dst, (chunk_id, e))),
+                context,
+            )
+        }
+        chunk_events
+    }
+
+    /// Like [`Self::tick_chunks_serial`], but on a thread pool.
+    #[cfg(feature = "parallel")]
+    fn tick_chunks_parallel(
+        &mut self,
+        context: &mut OnInfo<'_>,
+    ) -> Vec<(ChunkId, (ChunkId, ChunkEvent))> {
+        use rayon::prelude::*;
+
+        let singleton = singleton!(self).unwrap();
+        let players = &self.player;
+
+        // Chunks only affect each other via addressed events, so they can be ticked
+        // independently. Events and infos are merged in `ChunkId` order (`OrdIter`) so the
+        // result is identical to ticking serially.
+        let ticked: Vec<_> = Map::iter_mut(&mut self.chunk)
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .map(|(chunk_id, state): (ChunkId, &mut ChunkState)| {
+                let mut events = vec![];
+                let mut infos = vec![];
+                state.actor.tick(
+                    chunk_id,
+                    |player_id| Self::player_inner(players, player_id),
+                    singleton,
+                    |dst, e| events.push((dst, (chunk_id, e))),
+                    &mut |info| infos.push(info),
+                );
+                (events, infos)
+            })
+            .collect();
+
+        let mut chunk_events = vec![];
+        for (events, infos) in ticked {
+            chunk_events.extend(events);
+            for info in infos {
+                context(info);
+            }
+        }
+        chunk_events
+    }
+
+    /// Finds forces and supply lines that must halt because a tower in their path was destroyed
+    /// or a new alliance was formed with the owner of a tower in their path.
+    ///
+    /// Paths can only become invalid when one of those happens, so most ticks, this doesn't have
+    /// to look at any paths. Otherwise, only chunks whose [`PathIndex`] leads through a chunk with
+    /// destroyed towers, or that contain a player with new alliances, are checked.
+    fn halt_events(&self) -> Vec<(ChunkId, (ChunkId, ChunkHaltEvent))> {
         let mut halt_events = vec![];
+
+        // Index of chunks whose paths must be checked.
+        let destroyed: Vec<ChunkId> = Map::iter(&self.chunk)
+            .filter(|(_, state)| state.actor.towers_destroyed)
+            .map(|(chunk_id, _)| chunk_id)
+            .collect();
+        let new_alliances: Vec<PlayerId> = Map::iter(&self.player)
+            .filter(|(_, state)| !state.actor.new_alliances.is_empty())
+            .map(|(player_id, _)| player_id)
+            .collect();
+        if destroyed.is_empty() && new_alliances.is_empty() {
+            return halt_events;
+        }
+
+        // Avoids iterating `path` if it can't possibly need to halt.
+        let may_halt = |path: &Path, skip: usize, player: &Player| -> bool {
+            !player.new_alliances.is_empty()
+                || path
+                    .iter()
+                    .skip(skip)
+                    .any(|tower_id| destroyed.contains(&ChunkId::from(tower_id)))
+        };
+
         for (upstream_chunk_id, state) in Map::iter(&self.chunk) {
             let upstream_chunk: &Chunk = &state.actor;
 
+            // Skip chunks whose paths can't possibly need to halt.
+            if !upstream_chunk
+                .path_index()
+                .iter()
+                .any(|chunk_id| destroyed.contains(&chunk_id))
+                && !new_alliances
+                    .iter()
+                    .any(|&player_id| upstream_chunk.contains_player(player_id))
+            {
+                continue;
+            }
+
             for (upstream_tower_id, upstream_tower) in upstream_chunk.iter(upstream_chunk_id) {
                 for (i, force) in upstream_tower.inbound_forces.iter().enumerate() {
                     let Some(upstream_player_id) = force.player_id else {
@@ -58,8 +237,13 @@ impl WorldTick<OnInfo<'_>> for World {
                     };
                     let upstream_player = Self::player_inner(&self.player, upstream_player_id);
 
+                    if !may_halt(force.path(), 2, upstream_player) {
+                        continue;
+                    }
                     let remaining_path = force.path().iter().skip(2);
-                    for downstream_chunk_id in self.halt_path(remaining_path, upstream_player) {
+                    for downstream_chunk_id in
+                        self.halt_path(remaining_path, upstream_player, &destroyed)
+                    {
                         halt_events.push((
                             upstream_chunk_id,
                             (
@@ -79,8 +263,13 @@ impl WorldTick<OnInfo<'_>> for World {
                     continue;
                 };
                 let upstream_player = Self::player_inner(&self.player, player_id);
+                if !may_halt(supply_line, 0, upstream_player) {
+                    continue;
+                }
 
-                for downstream_chunk_id in self.halt_path(supply_line.iter(), upstream_player) {
+                for downstream_chunk_id in
+                    self.halt_path(supply_line.iter(), upstream_player, &destroyed)
+                {
                     halt_events.push((
                         upstream_chunk_id,
                         (
@@ -91,63 +280,44 @@ impl WorldTick<OnInfo<'_>> for World {
                 }
             }
         }
-
-        self.extend(halt_events);
-        apply!(self, Chunk, ChunkId, ChunkHaltEvent, context);
-
-        for (_, state) in Map::iter_mut(&mut self.player) {
-            state.actor.new_alliances.clear();
-        }
-
-        let singleton = singleton!(self).unwrap();
-        let mut chunk_events = vec![];
-        for (chunk_id, state) in Map::iter_mut(&mut self.chunk) {
-            let chunk: &mut Chunk = &mut state.actor;
-            chunk.tick(
-                chunk_id,
-                |player_id| Self::player_inner(&self.player, player_id),
-                singleton,
-                |dst, e| chunk_events.push((dst, (chunk_id, e))),
-                context,
-            )
-        }
-        self.extend(chunk_events);
-    }
-
-    fn tick_after_inputs(&mut self, context: &mut OnInfo<'_>) {
-        // We apply chunk events after inputs since `ChunkInput`s may create `ChunkEvent`s.
-        // TODO detect if events weren't applied in debug mode.
-        apply!(self, Chunk, ChunkId, ChunkEvent, context);
+        halt_events
     }
 
-    fn tick_client(&mut self, context: &mut OnInfo<'_>) {
-        apply_inputs!(self, Chunk, ChunkMaintenance, context);
-        apply_inputs!(self, Player, PlayerMaintainance, context);
-        self.tick_before_inputs(context);
-        {
-            let mut context = OnChunkEvent::new(&mut *context);
-            apply_inputs!(self, Chunk, ChunkInput, &mut context);
-            self.extend(context.into_events());
+    /// Fires [`Info::SupplyLineHalted`] once per halted supply line.
+    fn halted_supply_line_infos(
+        &self,
+        halt_events: &[(ChunkId, (ChunkId, ChunkHaltEvent))],
+        context: &mut OnInfo<'_>,
+    ) {
+        let mut halted = vec![];
+        for &(chunk_id, (_, event)) in halt_events {
+            let ChunkHaltEvent::SupplyLine(relative_tower_id) = event else {
+                continue;
+            };
+            let tower_id = relative_tower_id.upgrade(chunk_id);
+            if halted.contains(&tower_id) {
+                continue;
+            }
+            halted.push(tower_id);
+
+            if let Some(player_id) = self.chunk.get(tower_id).and_then(|t| t.player_id) {
+                context(InfoEvent {
+                    position: tower_id.as_vec2(),
+                    info: Info::SupplyLineHalted {
+                        tower_id,
+                        player_id,
+                    },
+                });
+            }
         }
-        apply_inputs!(self, Player, PlayerInput, context);
-        apply_inputs!(self, Singleton, SingletonInput, context);
-        self.tick_after_inputs(context);
     }
-}
-
-impl World {
-    pub const MAX_ROAD_LENGTH: u32 = 5;
-    pub const MAX_ROAD_LENGTH_SQUARED: u64 = (Self::MAX_ROAD_LENGTH as u64 + 1).pow(2) - 1;
-    pub const MAX_PATH_ROADS: usize = 16;
-
-    pub const CENTER: TowerId =
-        TowerId::new(WorldChunks::SIZE as u16 / 2, WorldChunks::SIZE as u16 / 2);
 
     /// Returns an iterator of chunks that send halt events to `path`.
     fn halt_path<'a>(
         &'a self,
         path: impl Iterator<Item = TowerId> + 'a,
         player: &'a Player,
+        destroyed: &'a [ChunkId],
     ) -> impl Iterator<Item = ChunkId> + 'a {
         let no_new_alliances = player.new_alliances.is_empty();
         let mut dedup = vec![];
@@ -158,6 +328,9 @@ impl World {
 
         path.filter_map(move |tower_id| {
             let (chunk_id, tower_id) = tower_id.split();
+            if no_new_alliances && !destroyed.contains(&chunk_id) {
+                return None; // Optimization (avoid looking up chunk).
+            }
             let Some(chunk) = Map::get(&self.chunk, chunk_id) else {
                 // Chunk not visible.
                 return None;
@@ -221,13 +394,21 @@ impl World {
 
     #[cfg(feature = "server")]
     pub fn new() -> Self {
+        use kodiak_common::rand::{thread_rng, Rng};
+        Self::with_seed(thread_rng().gen())
+    }
+
+    /// Creates a world whose simulation is reproducible given the same `seed` and inputs.
+    #[cfg(feature = "server")]
+    pub fn with_seed(seed: u64) -> Self {
         Self {
             chunk: ChunkMap::from_fn(|id| Some(Chunk::new(id).into())),
             player: Default::default(),
             singleton: Some((
                 SingletonId,
                 Singleton {
-                    tick: Default::default(),
+                    rng: SimRng::new(seed),
+                    ..Default::default()
                 }
                 .into(),
             )),
@@ -247,6 +428,23 @@ impl World {
             .apply_owned(input, on_info);
     }
 
+    /// Dispatches [`ChunkMaintenance::KillPlayer`] to every chunk that `player_id` is in, e.g.
+    /// when they [`Surrender`][`crate::protocol::Command::Surrender`].
+    #[cfg(feature = "server")]
+    pub fn kill_player(&mut self, player_id: PlayerId, on_info: &mut OnInfo) {
+        let chunk_ids: Vec<ChunkId> = Map::iter(&self.chunk)
+            .filter(|(_, state)| state.actor.contains_player(player_id))
+            .map(|(chunk_id, _)| chunk_id)
+            .collect();
+        for chunk_id in chunk_ids {
+            self.dispatch_chunk_maintenance(
+                chunk_id,
+                ChunkMaintenance::KillPlayer { player_id },
+                on_info,
+            );
+        }
+    }
+
     #[cfg(feature = "server")]
     pub fn dispatch_chunk_input(
         &mut self,
@@ -254,6 +452,18 @@ impl World {
         input: ChunkInput,
         on_info: &mut OnInfo,
     ) {
+        // Clients only check large towers against their (possibly stale) view of the world, and
+        // not against earlier upgrades in the same batch.
+        if let ChunkInput::UpgradeTower {
+            tower_id,
+            tower_type,
+        } = &input
+        {
+            if tower_type.is_large() && !self.chunk.has_room_for_large(tower_id.upgrade(chunk_id)) {
+                return;
+            }
+        }
+
         let mut context = OnChunkEvent::new(on_info);
 
         Map::get_mut(&mut self.chunk, chunk_id)
@@ -419,8 +629,10 @@ impl<I> OnChunkEvent for InputContext<I> {
 
 #[cfg(test)]
 mod tests {
-    use crate::tower::integer_sqrt;
+    use crate::chunk::ChunkInput;
+    use crate::tower::{integer_sqrt, Tower, TowerType};
     use crate::world::World;
+    use kodiak_common::actor_model::Map;
 
     #[test]
     fn max_edge_distance() {
@@ -433,4 +645,361 @@ mod tests {
             )
         }
     }
+
+    /// Ticking chunks in parallel must produce the same state, infos, and event order as ticking
+    /// them serially, otherwise clients (which tick serially) would desync.
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_matches_serial() {
+        use crate::force::{Force, Path};
+        use crate::info::InfoEvent;
+        use crate::player::Player;
+        use crate::rng::SimRng;
+        use crate::units::Units;
+        use kodiak_common::actor_model::WorldTick;
+        use kodiak_common::rand::Rng;
+        use kodiak_common::PlayerId;
+        use std::num::NonZeroU32;
+
+        fn world(seed: u64) -> World {
+            let mut world = World::with_seed(seed);
+            let mut rng = SimRng::new(seed);
+            let player_ids: Vec<_> = (1..=3)
+                .map(|i| PlayerId(NonZeroU32::new(i).unwrap()))
+                .collect();
+            for &player_id in &player_ids {
+                Map::insert(&mut world.player, player_id, Player::default().into());
+            }
+
+            // Spans several chunks, so forces cross chunk boundaries.
+            let tower_ids: Vec<_> = World::CENTER.iter_radius(120).collect();
+            for &tower_id in &tower_ids {
+                let mut tower = Tower::new(tower_id);
+                if rng.gen_bool(0.7) {
+                    tower.set_player_id(Some(player_ids[rng.gen_range(0..3)]));
+                    tower.units = Units::random_units(rng.gen_range(1..32), false, rng.gen());
+                }
+                let (chunk_id, relative_tower_id) = tower_id.split();
+                let chunk = &mut Map::get_mut(&mut world.chunk, chunk_id).unwrap().actor;
+                chunk.insert(relative_tower_id, tower);
+            }
+            for &src in &tower_ids {
+                for dst in src.neighbors() {
+                    if world.chunk.get(dst).is_none() || !rng.gen_bool(0.3) {
+                        continue;
+                    }
+                    let player_id = player_ids[rng.gen_range(0..3)];
+                    let mut force = Force::new(
+                        player_id,
+                        Units::random_units(rng.gen_range(1..32), false, rng.gen()),
+                        Path::new(vec![src, dst]),
+                    );
+                    force.path_progress = rng.gen_range(0..10);
+                    for (tower_id, outbound) in [(src, true), (dst, false)] {
+                        let (chunk_id, relative_tower_id) = tower_id.split();
+                        let chunk = &mut Map::get_mut(&mut world.chunk, chunk_id).unwrap().actor;
+                        chunk.players_mut().add(player_id);
+                        let tower = &mut chunk[relative_tower_id];
+                        if outbound {
+                            tower.outbound_forces.push(force.clone());
+                        } else {
+                            tower.inbound_forces.push(force.clone());
+                        }
+                    }
+                }
+            }
+            world
+        }
+
+        fn tick(world: &mut World, parallel: bool) -> Vec<String> {
+            let mut infos = vec![];
+            let on_info = &mut |info: InfoEvent| infos.push(format!("{info:?}"));
+            world.tick_before_inputs_inner(parallel, on_info);
+            world.tick_after_inputs(on_info);
+            infos
+        }
+
+        let mut serial = world(42);
+        let mut parallel = world(42);
+        for i in 0..40 {
+            assert_eq!(
+                tick(&mut serial, false),
+                tick(&mut parallel, true),
+                "infos differ at tick {i}"
+            );
+
+            // Chunk events are applied in the order they were emitted, so a different order would
+            // show up as e.g. differently ordered inbound forces.
+            for ((serial_id, serial), (parallel_id, parallel)) in
+                Map::iter(&serial.chunk).zip(Map::iter(&parallel.chunk))
+            {
+                assert_eq!(serial_id, parallel_id);
+                assert_eq!(
+                    serial.actor, parallel.actor,
+                    "chunk {serial_id:?} differs at tick {i}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn large_towers_not_adjacent() {
+        let mut world = World::new();
+        let a = World::CENTER;
+        let b = a.neighbors().next().unwrap();
+        for tower_id in [a, b] {
+            let (chunk_id, relative_tower_id) = tower_id.split();
+            let chunk = &mut Map::get_mut(&mut world.chunk, chunk_id).unwrap().actor;
+            chunk.insert(relative_tower_id, Tower::with_type(TowerType::City));
+        }
+
+        // Like a batch, the second upgrade sees the first.
+        for tower_id in [a, b] {
+            let (chunk_id, relative_tower_id) = tower_id.split();
+            world.dispatch_chunk_input(
+                chunk_id,
+                ChunkInput::UpgradeTower {
+                    tower_id: relative_tower_id,
+                    tower_type: TowerType::Metropolis,
+                },
+                &mut |_| {},
+            );
+        }
+        assert_eq!(
+            world.chunk.get(a).unwrap().tower_type,
+            TowerType::Metropolis
+        );
+        assert_eq!(world.chunk.get(b).unwrap().tower_type, TowerType::City);
+    }
+
+    /// Random towers with random supply lines and inbound forces whose paths lead through several
+    /// chunks.
+    fn world_with_paths(seed: u64) -> World {
+        use crate::chunk::ChunkEvent;
+        use crate::force::{Force, Path};
+        use crate::player::Player;
+        use crate::rng::SimRng;
+        use crate::tower::TowerId;
+        use crate::unit::Unit;
+        use crate::units::Units;
+        use kodiak_common::actor_model::Apply;
+        use kodiak_common::rand::Rng;
+        use kodiak_common::PlayerId;
+        use std::num::NonZeroU32;
+
+        fn walk(
+            world: &World,
+            mut tower_id: TowerId,
+            len: usize,
+            rng: &mut SimRng,
+        ) -> Vec<TowerId> {
+            let mut path = vec![];
+            for _ in 0..len {
+                let neighbors: Vec<_> = tower_id
+                    .neighbors()
+                    .filter(|&n| world.chunk.get(n).is_some() && !path.contains(&n))
+                    .collect();
+                if neighbors.is_empty() {
+                    break;
+                }
+                tower_id = neighbors[rng.gen_range(0..neighbors.len())];
+                path.push(tower_id);
+            }
+            path
+        }
+
+        let mut world = World::with_seed(seed);
+        let mut rng = SimRng::new(seed);
+        let player_ids: Vec<_> = (1..=3)
+            .map(|i| PlayerId(NonZeroU32::new(i).unwrap()))
+            .collect();
+        for &player_id in &player_ids {
+            Map::insert(&mut world.player, player_id, Player::default().into());
+        }
+
+        let tower_ids: Vec<_> = World::CENTER.iter_radius(120).collect();
+        for &tower_id in &tower_ids {
+            let mut tower = Tower::new(tower_id);
+            if rng.gen_bool(0.7) {
+                tower.set_player_id(Some(player_ids[rng.gen_range(0..3)]));
+            }
+            let (chunk_id, relative_tower_id) = tower_id.split();
+            let chunk = &mut Map::get_mut(&mut world.chunk, chunk_id).unwrap().actor;
+            chunk.insert(relative_tower_id, tower);
+        }
+
+        for &tower_id in &tower_ids {
+            let (chunk_id, relative_tower_id) = tower_id.split();
+            if world.chunk.get(tower_id).unwrap().player_id.is_some() && rng.gen_bool(0.3) {
+                let mut path = vec![tower_id];
+                path.extend(walk(&world, tower_id, rng.gen_range(1..8), &mut rng));
+                world.dispatch_chunk_input(
+                    chunk_id,
+                    ChunkInput::SetSupplyLine {
+                        tower_id: relative_tower_id,
+                        path: Some(Path::new(path)),
+                    },
+                    &mut |_| {},
+                );
+            }
+            if rng.gen_bool(0.3) {
+                let Some(&src) = walk(&world, tower_id, 1, &mut rng).first() else {
+                    continue;
+                };
+                let mut path = vec![src, tower_id];
+                path.extend(walk(&world, tower_id, rng.gen_range(0..8), &mut rng));
+                let mut units = Units::default();
+                units.add(Unit::Soldier, 1);
+                let force = Force::new(player_ids[rng.gen_range(0..3)], units, Path::new(path));
+                let chunk = &mut Map::get_mut(&mut world.chunk, chunk_id).unwrap().actor;
+                chunk.apply(
+                    &ChunkEvent::AddInboundForce {
+                        tower_id: relative_tower_id,
+                        force,
+                    },
+                    &mut (),
+                );
+            }
+        }
+        world
+    }
+
+    /// Destroys some towers and forms an alliance, like maintenance would.
+    fn destroy_and_ally(world: &mut World, seed: u64) {
+        use crate::chunk::ChunkMaintenance;
+        use crate::rng::SimRng;
+        use kodiak_common::rand::Rng;
+        use kodiak_common::PlayerId;
+        use std::num::NonZeroU32;
+
+        let mut rng = SimRng::new(seed);
+        let destroy: Vec<_> = World::CENTER
+            .iter_radius(120)
+            .filter(|&tower_id| {
+                world.chunk.get(tower_id).is_some_and(|t| t.can_destroy()) && rng.gen_bool(0.05)
+            })
+            .collect();
+        for tower_id in destroy {
+            let (chunk_id, relative_tower_id) = tower_id.split();
+            world.dispatch_chunk_maintenance(
+                chunk_id,
+                ChunkMaintenance::Destroy {
+                    tower_ids: vec![relative_tower_id],
+                },
+                &mut |_| {},
+            );
+        }
+
+        if rng.gen_bool(0.5) {
+            let [a, b] = [1, 2].map(|i| PlayerId(NonZeroU32::new(i).unwrap()));
+            let player = &mut Map::get_mut(&mut world.player, a).unwrap().actor;
+            player.allies.insert(b);
+            player.new_alliances.insert(b);
+        }
+    }
+
+    /// Skipping chunks with the [`PathIndex`] must find the same halts as checking every path.
+    #[test]
+    fn halts_match_full_scan() {
+        use crate::chunk::{ChunkHaltEvent, ChunkId};
+
+        fn full_scan(world: &World) -> Vec<(ChunkId, (ChunkId, ChunkHaltEvent))> {
+            // Treating every chunk as destroyed checks every tower in every path.
+            let all: Vec<_> = Map::iter(&world.chunk).map(|(id, _)| id).collect();
+            let mut halt_events = vec![];
+            for (upstream_chunk_id, state) in Map::iter(&world.chunk) {
+                for (tower_id, tower) in state.actor.iter(upstream_chunk_id) {
+                    for (i, force) in tower.inbound_forces.iter().enumerate() {
+                        let player = world.player(force.player_id.unwrap());
+                        let path = force.path().iter().skip(2);
+                        for downstream_chunk_id in world.halt_path(path, player, &all) {
+                            halt_events.push((
+                                upstream_chunk_id,
+                                (
+                                    downstream_chunk_id,
+                                    ChunkHaltEvent::Force(tower_id.into(), i as u32),
+                                ),
+                            ));
+                        }
+                    }
+                    let Some(supply_line) = &tower.supply_line else {
+                        continue;
+                    };
+                    let player = world.player(tower.player_id.unwrap());
+                    for downstream_chunk_id in world.halt_path(supply_line.iter(), player, &all) {
+                        halt_events.push((
+                            upstream_chunk_id,
+                            (
+                                downstream_chunk_id,
+                                ChunkHaltEvent::SupplyLine(tower_id.into()),
+                            ),
+                        ));
+                    }
+                }
+            }
+            halt_events
+        }
+
+        let mut total = 0;
+        for seed in 0..10 {
+            let mut world = world_with_paths(seed);
+            assert!(world.halt_events().is_empty());
+            assert!(full_scan(&world).is_empty());
+
+            destroy_and_ally(&mut world, seed);
+            let halt_events = world.halt_events();
+            assert_eq!(
+                format!("{halt_events:?}"),
+                format!("{:?}", full_scan(&world)),
+                "seed {seed}"
+            );
+            total += halt_events.len();
+        }
+        assert_ne!(total, 0);
+    }
+
+    /// The indices are updated incrementally, so they must match ones built from scratch.
+    #[test]
+    fn indices_up_to_date() {
+        use crate::chunk::{ChunkMaintenance, PathIndex, PlayerIndex};
+        use kodiak_common::actor_model::WorldTick;
+        use kodiak_common::PlayerId;
+        use std::num::NonZeroU32;
+
+        let mut world = world_with_paths(7);
+        for i in 0..40 {
+            if i % 10 == 0 {
+                destroy_and_ally(&mut world, i);
+            }
+            if i == 25 {
+                let chunk_ids: Vec<_> = Map::iter(&world.chunk).map(|(id, _)| id).collect();
+                for chunk_id in chunk_ids {
+                    world.dispatch_chunk_maintenance(
+                        chunk_id,
+                        ChunkMaintenance::KillPlayer {
+                            player_id: PlayerId(NonZeroU32::new(3).unwrap()),
+                        },
+                        &mut |_| {},
+                    );
+                }
+            }
+            world.tick_before_inputs(&mut |_| {});
+            world.tick_after_inputs(&mut |_| {});
+
+            for (chunk_id, state) in Map::iter(&world.chunk) {
+                let chunk = &state.actor;
+                let towers = chunk.iter(chunk_id).map(|(_, t)| t);
+                assert_eq!(
+                    chunk.path_index(),
+                    &PathIndex::new(towers.clone()),
+                    "chunk {chunk_id:?} at tick {i}"
+                );
+                assert_eq!(
+                    chunk.player_index(),
+                    &PlayerIndex::new(towers),
+                    "chunk {chunk_id:?} at tick {i}"
+                );
+            }
+        }
+    }
 }
@@ -0,0 +1,146 @@
+// SPDX-FileCopyrightText: 2024 Softbear, Inc.
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use crate::chunk::ChunkId;
+use kodiak_common::actor_model::*;
+use kodiak_common::bitcode::{self, *};
+
+/// Sparse alternative to [`ChunkMap`][`super::ChunkMap`] that only stores present chunks, sorted
+/// by [`ChunkId`] (so it iterates in the same order). Lookups are a binary search, so it is best
+/// for mostly empty worlds or clients that only see a few chunks.
+#[derive(Clone, Debug, Hash, PartialEq, Eq, Encode, Decode)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SparseChunkMap<T> {
+    chunks: Vec<(ChunkId, T)>,
+}
+
+impl<T> Default for SparseChunkMap<T> {
+    fn default() -> Self {
+        Self { chunks: Vec::new() }
+    }
+}
+
+impl<T> SparseChunkMap<T> {
+    fn search(&self, id: ChunkId) -> Result<usize, usize> {
+        self.chunks.binary_search_by(|(k, _)| k.cmp(&id))
+    }
+}
+
+impl<T> IntoIterator for SparseChunkMap<T> {
+    type Item = (ChunkId, T);
+    type IntoIter = std::vec::IntoIter<(ChunkId, T)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.chunks.into_iter()
+    }
+}
+
+impl<T> FromIterator<(ChunkId, T)> for SparseChunkMap<T> {
+    fn from_iter<I: IntoIterator<Item = (ChunkId, T)>>(iter: I) -> Self {
+        let mut ret = Self::default();
+        for (id, v) in iter {
+            ret.insert(id, v);
+        }
+        ret
+    }
+}
+
+impl<T> Map<ChunkId, T> for SparseChunkMap<T> {
+    type Iter<'a> = impl Iterator<Item = (ChunkId, &'a T)> + Clone where T: 'a;
+    type IterMut<'a> = impl Iterator<Item = (ChunkId, &'a mut T)> where T: 'a;
+
+    fn get(&self, id: ChunkId) -> Option<&T> {
+        self.search(id).ok().map(|i| &self.chunks[i].1)
+    }
+
+    fn get_mut(&mut self, id: ChunkId) -> Option<&mut T> {
+        self.search(id).ok().map(|i| &mut self.chunks[i].1)
+    }
+
+    fn insert(&mut self, id: ChunkId, v: T) -> Option<T> {
+        match self.search(id) {
+            Ok(i) => Some(std::mem::replace(&mut self.chunks[i].1, v)),
+            Err(i) => {
+                self.chunks.insert(i, (id, v));
+                None
+            }
+        }
+    }
+
+    fn iter(&self) -> Self::Iter<'_> {
+        self.chunks.iter().map(|(id, v)| (*id, v))
+    }
+
+    fn iter_mut(&mut self) -> Self::IterMut<'_> {
+        self.chunks.iter_mut().map(|(id, v)| (*id, v))
+    }
+
+    fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    fn or_default(&mut self, id: ChunkId) -> &mut T
+    where
+        T: Default,
+    {
+        let i = match self.search(id) {
+            Ok(i) => i,
+            Err(i) => {
+                self.chunks.insert(i, (id, T::default()));
+                i
+            }
+        };
+        &mut self.chunks[i].1
+    }
+
+    fn remove(&mut self, id: ChunkId) -> Option<T> {
+        self.search(id).ok().map(|i| self.chunks.remove(i).1)
+    }
+
+    fn retain(&mut self, mut f: impl FnMut(ChunkId, &mut T) -> bool) {
+        self.chunks.retain_mut(|(id, v)| f(*id, v));
+        crate::shrink_vec(&mut self.chunks);
+    }
+}
+
+impl<T> OrdIter for SparseChunkMap<T> {}
+impl<T> Efficient for SparseChunkMap<T> {}
+
+#[cfg(test)]
+mod tests {
+    use crate::chunk::ChunkId;
+    use crate::world::{ChunkMap, SparseChunkMap};
+    use kodiak_common::actor_model::Map;
+
+    #[test]
+    fn sparse_chunk_map() {
+        let ids = [(3, 1), (0, 2), (31, 0), (5, 1), (0, 0)].map(|(x, y)| ChunkId::new(x, y));
+
+        let mut sparse = SparseChunkMap::default();
+        let mut dense = ChunkMap::default();
+        for (i, id) in ids.into_iter().enumerate() {
+            assert_eq!(Map::insert(&mut sparse, id, i), None);
+            Map::insert(&mut dense, id, i);
+        }
+        assert_eq!(Map::len(&sparse), ids.len());
+
+        // Same order as the dense map.
+        assert!(Map::iter(&sparse).eq(Map::iter(&dense)));
+
+        assert_eq!(Map::insert(&mut sparse, ids[0], 10), Some(0));
+        assert_eq!(Map::get(&sparse, ids[0]), Some(&10));
+        assert_eq!(Map::get(&sparse, ChunkId::new(1, 1)), None);
+
+        *Map::or_default(&mut sparse, ChunkId::new(1, 1)) += 1;
+        assert_eq!(Map::get(&sparse, ChunkId::new(1, 1)), Some(&1));
+
+        assert_eq!(Map::remove(&mut sparse, ids[1]), Some(1));
+        assert_eq!(Map::remove(&mut sparse, ids[1]), None);
+
+        Map::retain(&mut sparse, |_, v| *v % 2 == 0);
+        assert!(Map::iter(&sparse).all(|(_, v)| v % 2 == 0));
+        assert!(Map::iter(&sparse)
+            .zip(Map::iter(&sparse).skip(1))
+            .all(|((a, _), (b, _))| a < b));
+    }
+}
@@ -139,6 +139,15 @@ impl WorldChunks {
         self.get_chunk(chunk_id).and_then(|c| c.get(tower_id))
     }
 
+    /// Returns true if none of `tower_id`'s neighbors are large, so it can be upgraded to a large
+    /// tower without their footprints overlapping.
+    pub fn has_room_for_large(&self, tower_id: TowerId) -> bool {
+        tower_id.neighbors().all(|neighbor| {
+            self.get(neighbor)
+                .map_or(true, |tower| !tower.tower_type.is_large())
+        })
+    }
+
     pub fn get_chunk(&self, chunk_id: ChunkId) -> Option<&Chunk> {
         Map::get(self, chunk_id).map(|chunk_data| &chunk_data.actor)
     }